@@ -0,0 +1,93 @@
+// Bloom mip chain for the post-processing stage.
+//
+// `prefilter` keeps the exposed radiance above the threshold at half
+// resolution, `downsample` halves it again level by level, and `upsample`
+// adds each level to the bilinearly upsampled sum of the coarser ones.
+// Mirrored on the CPU by `renderer::post`.
+
+struct PostUniforms {
+    exposure: f32,
+    tonemapper: u32,
+    bloom_strength: f32,
+    bloom_threshold: f32,
+    bloom_levels: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+};
+
+@group(0) @binding(0) var src: texture_2d<f32>;
+@group(0) @binding(1) var coarse: texture_2d<f32>;
+@group(0) @binding(2) var dst: texture_storage_2d<rgba16float, write>;
+@group(0) @binding(3) var<uniform> post: PostUniforms;
+
+fn load_clamped(t: texture_2d<f32>, p: vec2<i32>) -> vec3<f32> {
+    let size = vec2<i32>(textureDimensions(t));
+    return textureLoad(t, clamp(p, vec2<i32>(0), size - 1), 0).rgb;
+}
+
+fn bilinear(t: texture_2d<f32>, uv: vec2<f32>) -> vec3<f32> {
+    let p = uv * vec2<f32>(textureDimensions(t)) - 0.5;
+    let p0 = floor(p);
+    let f = p - p0;
+    let i = vec2<i32>(p0);
+    let a = load_clamped(t, i);
+    let b = load_clamped(t, i + vec2<i32>(1, 0));
+    let c = load_clamped(t, i + vec2<i32>(0, 1));
+    let d = load_clamped(t, i + vec2<i32>(1, 1));
+    return mix(mix(a, b, f.x), mix(c, d, f.x), f.y);
+}
+
+// Exposed radiance, scaled down to the part of its luminance above the
+// threshold so bright pixels keep their colour.
+fn bright_pass(c: vec3<f32>) -> vec3<f32> {
+    let e = c * post.exposure;
+    let l = dot(e, vec3<f32>(0.2126, 0.7152, 0.0722));
+    return e * (max(l - post.bloom_threshold, 0.0) / max(l, 1e-4));
+}
+
+// Tent weights 1, 3, 3, 1 (/ 8) over source texels 2p - 1 .. 2p + 2.
+fn tap_weight(i: i32) -> f32 {
+    return select(0.375, 0.125, i == 0 || i == 3);
+}
+
+fn downsample_at(p: vec2<i32>, bright: bool) -> vec3<f32> {
+    var sum = vec3<f32>(0.0);
+    for (var j = 0; j < 4; j++) {
+        for (var i = 0; i < 4; i++) {
+            var c = load_clamped(src, 2 * p + vec2<i32>(i - 1, j - 1));
+            if bright {
+                c = bright_pass(c);
+            }
+            sum += tap_weight(i) * tap_weight(j) * c;
+        }
+    }
+    return sum;
+}
+
+@compute @workgroup_size(8, 8)
+fn prefilter(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= textureDimensions(dst)) {
+        return;
+    }
+    textureStore(dst, id.xy, vec4<f32>(downsample_at(vec2<i32>(id.xy), true), 1.0));
+}
+
+@compute @workgroup_size(8, 8)
+fn downsample(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= textureDimensions(dst)) {
+        return;
+    }
+    textureStore(dst, id.xy, vec4<f32>(downsample_at(vec2<i32>(id.xy), false), 1.0));
+}
+
+@compute @workgroup_size(8, 8)
+fn upsample(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(dst);
+    if any(id.xy >= size) {
+        return;
+    }
+    let uv = (vec2<f32>(id.xy) + 0.5) / vec2<f32>(size);
+    let c = textureLoad(src, vec2<i32>(id.xy), 0).rgb + bilinear(coarse, uv);
+    textureStore(dst, id.xy, vec4<f32>(c, 1.0));
+}
@@ -1,5 +1,8 @@
 // Fullscreen triangle blit shader.
-// Draws a fullscreen triangle (3 vertices, no vertex buffer needed).
+// Draws a fullscreen triangle (3 vertices, no vertex buffer needed) and
+// composites the linear HDR ray-march output for display: exposure, bloom
+// from `bloom.wgsl`, then the selected tonemapper. Mirrored on the CPU by
+// `renderer::post::apply`.
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
@@ -22,10 +25,95 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
     return out;
 }
 
-@group(0) @binding(0) var t: texture_2d<f32>;
-@group(0) @binding(1) var s: sampler;
+struct PostUniforms {
+    exposure: f32,
+    tonemapper: u32,
+    bloom_strength: f32,
+    bloom_threshold: f32,
+    bloom_levels: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+};
+
+@group(0) @binding(0) var hdr: texture_2d<f32>;
+@group(0) @binding(1) var bloom: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> post: PostUniforms;
+
+fn load_clamped(t: texture_2d<f32>, p: vec2<i32>) -> vec3<f32> {
+    let size = vec2<i32>(textureDimensions(t));
+    return textureLoad(t, clamp(p, vec2<i32>(0), size - 1), 0).rgb;
+}
+
+fn bilinear(t: texture_2d<f32>, uv: vec2<f32>) -> vec3<f32> {
+    let p = uv * vec2<f32>(textureDimensions(t)) - 0.5;
+    let p0 = floor(p);
+    let f = p - p0;
+    let i = vec2<i32>(p0);
+    let a = load_clamped(t, i);
+    let b = load_clamped(t, i + vec2<i32>(1, 0));
+    let c = load_clamped(t, i + vec2<i32>(0, 1));
+    let d = load_clamped(t, i + vec2<i32>(1, 1));
+    return mix(mix(a, b, f.x), mix(c, d, f.x), f.y);
+}
+
+// ── Tonemappers, matching `renderer::tonemap` ────────────────────────
+
+fn aces(x: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return (x * (a * x + b)) / (x * (c * x + d) + e);
+}
+
+fn agx(x: vec3<f32>) -> vec3<f32> {
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+    let inset = mat3x3<f32>(
+        0.84247906, 0.04232824, 0.042375655,
+        0.0784336, 0.8784686, 0.0784336,
+        0.079223745, 0.07916613, 0.87914297,
+    );
+    let outset = mat3x3<f32>(
+        1.196879, -0.05289685, -0.052971635,
+        -0.09802088, 1.1519031, -0.09804345,
+        -0.09902974, -0.09896118, 1.1510737,
+    );
+
+    var v = inset * max(x, vec3<f32>(1e-10));
+    v = clamp(log2(v), vec3<f32>(min_ev), vec3<f32>(max_ev));
+    v = (v - min_ev) / (max_ev - min_ev);
+    // Sixth-order fit of the AgX contrast curve
+    let v2 = v * v;
+    let v4 = v2 * v2;
+    v = 15.5 * v4 * v2 - 40.14 * v4 * v + 31.96 * v4 - 6.868 * v2 * v + 0.4298 * v2
+        + 0.1191 * v - 0.00232;
+    return pow(max(outset * v, vec3<f32>(0.0)), vec3<f32>(2.2));
+}
+
+fn tonemap(x: vec3<f32>) -> vec3<f32> {
+    var mapped = x;
+    switch post.tonemapper {
+        case 1u: { mapped = aces(x); }
+        case 2u: { mapped = x / (1.0 + max(x, vec3<f32>(0.0))); }
+        case 3u: { mapped = agx(x); }
+        default: {}
+    }
+    return clamp(mapped, vec3<f32>(0.0), vec3<f32>(1.0));
+}
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t, s, in.uv);
+    let size = vec2<i32>(textureDimensions(hdr));
+    let pixel = clamp(vec2<i32>(in.uv * vec2<f32>(size)), vec2<i32>(0), size - 1);
+    let texel = textureLoad(hdr, pixel, 0);
+
+    var color = texel.rgb * post.exposure;
+    if post.bloom_strength > 0.0 {
+        let glow = bilinear(bloom, in.uv) / f32(post.bloom_levels);
+        color += post.bloom_strength * glow;
+    }
+    return vec4<f32>(tonemap(color), texel.a);
 }
@@ -12,8 +12,32 @@ struct Uniforms {
     background_mode: u32,
     time: f32,
     grid_enabled: u32,
-    _pad0: f32,
-    _pad1: f32,
+    geodesic_model: u32,
+    integrator: u32,
+    // Adaptive integrator: relative error tolerance and step bounds
+    tolerance: f32,
+    min_step: f32,
+    max_step: f32,
+    // Far-body cutoff in Schwarzschild radii, 0 for none
+    body_cutoff: f32,
+    // Progressive accumulation: sub-pixel ray offset and samples so far
+    jitter: vec2<f32>,
+    sample_index: u32,
+    // Volumetric disk: scale height over radius and inner-edge optical depth
+    disk_model: u32,
+    disk_scale_height: f32,
+    disk_optical_depth: f32,
+    render_mode: u32,
+    // Disk redshift: beaming and gravitational shift on/off, and the
+    // exponent n of the gⁿ intensity scaling
+    doppler_beaming: u32,
+    gravitational_redshift: u32,
+    intensity_exponent: f32,
+    // Layout of the background image, one of the PROJECTION_* constants
+    environment_projection: u32,
+    _pad0: u32,
+    // Camera velocity relative to the local static observer, in units of c
+    observer_velocity: vec4<f32>,
 };
 
 struct Body {
@@ -21,17 +45,79 @@ struct Body {
     rs: f32,
     disk_inner: f32,
     disk_outer: f32,
-    _padding: f32,
+    spin: f32,
+    // Characteristic radii supplied by the CPU-side `Metric`
+    horizon: f32,
+    isco: f32,
+    _pad0: f32,
+    _pad1: f32,
+    // Unit normal of the disk plane
+    disk_normal: vec4<f32>,
+};
+
+// Mirrors `renderer::stars::GpuStar`
+struct Star {
+    direction: vec3<f32>,
+    brightness: f32,
+    // Unit-luminance blackbody colour
+    color: vec4<f32>,
 };
 
 @group(0) @binding(0) var<uniform> u: Uniforms;
 @group(0) @binding(1) var output: texture_storage_2d<rgba16float, write>;
 @group(0) @binding(2) var<storage, read> bodies: array<Body>;
+// Running sum of the samples of each pixel, row-major
+@group(0) @binding(3) var<storage, read_write> accum: array<vec4<f32>>;
+// Background image: an equirectangular map, or a 3:2 atlas of cube faces
+// with +x -x +y in the top row and -y +z -z below
+@group(0) @binding(4) var environment: texture_2d<f32>;
+@group(0) @binding(5) var environment_sampler: sampler;
+// Catalogue stars sorted into bins: first bin of each of the STAR_ROWS rows
+// and the bin count, then first star of each bin and the star count
+@group(0) @binding(6) var<storage, read> star_index: array<u32>;
+@group(0) @binding(7) var<storage, read> stars: array<Star>;
 
 const PI: f32 = 3.14159265358979;
-const MAX_BODIES: u32 = 8u;
 const ESCAPE_RADIUS: f32 = 50.0;
 
+// Mirrors `metrics::GeodesicModel`
+const MODEL_PSEUDO_NEWTONIAN: u32 = 0u;
+const MODEL_KERR_SCHILD: u32 = 1u;
+const MODEL_NULL_GEODESIC: u32 = 2u;
+
+// Mirrors `renderer::uniforms::Integrator`
+const INTEGRATOR_FIXED_RK4: u32 = 0u;
+const INTEGRATOR_ADAPTIVE_RK45: u32 = 1u;
+
+// Mirrors `renderer::uniforms::DiskModel`
+const DISK_THIN: u32 = 0u;
+const DISK_VOLUMETRIC: u32 = 1u;
+
+// Mirrors `renderer::environment::Projection`
+const PROJECTION_EQUIRECTANGULAR: u32 = 0u;
+const PROJECTION_CUBEMAP: u32 = 1u;
+
+// Mirrors `renderer::stars`
+const STAR_ROWS: u32 = 256u;
+const STAR_CUTOFF: f32 = 3.0;
+const STAR_WIDTH: f32 = 0.6;
+
+// Mirrors `renderer::uniforms::RenderMode`
+const MODE_SHADED: u32 = 0u;
+const MODE_DISK_ORDER: u32 = 1u;
+const MODE_STEPS: u32 = 2u;
+const MODE_DEFLECTION: u32 = 3u;
+const MODE_FATE: u32 = 4u;
+const MODE_REDSHIFT: u32 = 5u;
+const MODE_BODY: u32 = 6u;
+
+// Ray fates of the fate view, indexing `fate_color`
+const FATE_ESCAPED: u32 = 0u;
+const FATE_CAPTURED: u32 = 1u;
+const FATE_STEPS_AWAY: u32 = 2u;
+const FATE_STEPS_INWARD: u32 = 3u;
+const FATE_ABSORBED: u32 = 4u;
+
 // ── Hash / noise ──────────────────────────────────────────────────────
 
 fn hash21(p: vec2<f32>) -> f32 {
@@ -130,7 +216,117 @@ fn starfield(theta: f32, phi: f32) -> vec3<f32> {
     return col;
 }
 
+// Inverse of `dir_to_spherical`
+fn sky_direction(theta: f32, phi: f32) -> vec3<f32> {
+    return vec3<f32>(sin(theta) * cos(phi - PI), cos(theta), sin(theta) * sin(phi - PI));
+}
+
+// Cube face index and its texture coordinates, OpenGL cube map convention
+fn cube_face(dir: vec3<f32>) -> vec3<f32> {
+    let a = abs(dir);
+    var face = 0.0;
+    var major = a.x;
+    var sc = -dir.z;
+    var tc = -dir.y;
+    if a.x >= a.y && a.x >= a.z {
+        if dir.x <= 0.0 {
+            face = 1.0;
+            sc = dir.z;
+        }
+    } else if a.y >= a.z {
+        major = a.y;
+        sc = dir.x;
+        if dir.y > 0.0 {
+            face = 2.0;
+            tc = dir.z;
+        } else {
+            face = 3.0;
+            tc = -dir.z;
+        }
+    } else {
+        major = a.z;
+        if dir.z > 0.0 {
+            face = 4.0;
+            sc = dir.x;
+        } else {
+            face = 5.0;
+            sc = -dir.x;
+        }
+    }
+    return vec3<f32>((vec2<f32>(sc, tc) / major + 1.0) * 0.5, face);
+}
+
+fn environment_color(theta: f32, phi: f32) -> vec3<f32> {
+    if u.environment_projection == PROJECTION_CUBEMAP {
+        let dims = vec2<f32>(textureDimensions(environment));
+        let face = cube_face(sky_direction(theta, phi));
+        // Half a texel inside the face, so filtering never reaches into its
+        // neighbours in the atlas
+        let half_texel = 1.0 / dims.y;
+        let uv = clamp(face.xy, vec2<f32>(half_texel), vec2<f32>(1.0 - half_texel));
+        let cell = vec2<f32>(face.z % 3.0, floor(face.z / 3.0));
+        return textureSampleLevel(environment, environment_sampler, (cell + uv) / vec2<f32>(3.0, 2.0), 0.0).rgb;
+    }
+    let uv = vec2<f32>(phi / (2.0 * PI), theta / PI);
+    return textureSampleLevel(environment, environment_sampler, uv, 0.0).rgb;
+}
+
+// Gaussian width of catalogue stars on the sky, in radians
+fn star_sigma() -> f32 {
+    return STAR_WIDTH * 2.0 * tan(0.5 * u.fov) / u.resolution.y;
+}
+
+// Sum of the catalogue stars within reach of the direction, visiting only
+// the bins that overlap it
+fn catalog_stars(theta: f32, phi: f32) -> vec3<f32> {
+    let dir = sky_direction(theta, phi);
+    let sigma = star_sigma();
+    let cutoff = STAR_CUTOFF * sigma;
+    // Angle subtended by the cutoff chord
+    let reach = 2.0 * asin(min(0.5 * cutoff, 1.0));
+    // Widest azimuth span of the cap within reach; all of it near a pole
+    var half_width = PI;
+    if theta > reach && theta < PI - reach {
+        half_width = asin(min(sin(reach) / sin(theta), 1.0));
+    }
+    let row_height = PI / f32(STAR_ROWS);
+    let first_row = u32(max(floor((theta - reach) / row_height), 0.0));
+    let last_row = min(u32(floor((theta + reach) / row_height)), STAR_ROWS - 1u);
+
+    var sum = vec3<f32>(0.0);
+    for (var row = first_row; row <= last_row; row = row + 1u) {
+        let start = i32(star_index[row]);
+        let columns = i32(star_index[row + 1u]) - start;
+        var lo = i32(floor((phi - half_width) / (2.0 * PI) * f32(columns)));
+        var hi = i32(floor((phi + half_width) / (2.0 * PI) * f32(columns)));
+        if hi - lo + 1 >= columns {
+            lo = 0;
+            hi = columns - 1;
+        }
+        for (var column = lo; column <= hi; column = column + 1) {
+            let wrapped = ((column % columns) + columns) % columns;
+            let cell = STAR_ROWS + 1u + u32(start + wrapped);
+            for (var i = star_index[cell]; i < star_index[cell + 1u]; i = i + 1u) {
+                let star = stars[i];
+                // Chord length, which is the angle to well within f32 precision
+                let d = length(dir - star.direction);
+                if d < cutoff {
+                    let x = d / sigma;
+                    sum = sum + star.color.rgb * star.brightness * exp(-0.5 * x * x);
+                }
+            }
+        }
+    }
+    return sum;
+}
+
 fn background(theta: f32, phi: f32) -> vec3<f32> {
+    if u.background_mode == 3u {
+        return catalog_stars(theta, phi);
+    }
+    if u.background_mode == 2u {
+        return environment_color(theta, phi);
+    }
     if u.background_mode == 1u {
         return starfield(theta, phi);
     }
@@ -188,33 +384,100 @@ fn blackbody(temp: f32) -> vec3<f32> {
     return vec3<f32>(r, g, b);
 }
 
-fn disk_detail(r: f32, azimuth: f32, rs: f32, disk_inner: f32) -> f32 {
+fn disk_detail(r: f32, azimuth: f32, rs: f32, disk_inner: f32, time: f32) -> f32 {
     let rn = r / rs;
     var detail = 1.0;
 
     detail *= 0.95 + 0.05 * sin(rn * 6.0);
     detail *= 0.97 + 0.03 * sin(rn * 15.0 + 2.0);
 
-    let spiral = sin(azimuth * 2.0 - rn * 4.0 + u.time * 0.2);
+    let spiral = sin(azimuth * 2.0 - rn * 4.0 + time * 0.2);
     detail *= 0.96 + 0.04 * spiral;
 
     let noise_uv = vec2<f32>(rn * 4.0, azimuth * 3.0 / PI);
     let turb = fbm(noise_uv);
     detail *= 0.88 + 0.12 * turb;
 
-    let inner_turb = fbm(vec2<f32>(azimuth * 5.0 / PI + u.time * 0.15, rn * 10.0));
+    let inner_turb = fbm(vec2<f32>(azimuth * 5.0 / PI + time * 0.15, rn * 10.0));
     let inner_weight = exp(-max(rn - disk_inner / rs, 0.0) * 1.5);
     detail += inner_turb * inner_weight * 0.2;
 
     return max(detail, 0.0);
 }
 
-fn disk_color_for_body(pos: vec3<f32>, body_pos: vec3<f32>, rs: f32, disk_inner: f32, disk_outer: f32) -> vec3<f32> {
-    let delta = pos - body_pos;
-    let r = length(vec2<f32>(delta.x, delta.z));
-    let azimuth = atan2(delta.z, delta.x) + u.time * 0.5;
+// Radius, azimuth and height of `delta` from a body in the frame of its
+// disk plane with unit normal `n`. Azimuth runs from +x projected into the
+// plane towards e1 × n, so an untilted disk (n = +y) keeps the world's x and
+// z axes.
+fn disk_coords(delta: vec3<f32>, n: vec3<f32>) -> vec3<f32> {
+    var e1 = vec3<f32>(1.0, 0.0, 0.0) - n.x * n;
+    if dot(e1, e1) < 1e-6 {
+        e1 = vec3<f32>(0.0, 0.0, 1.0) - n.z * n;
+    }
+    e1 = normalize(e1);
+    let e2 = cross(e1, n);
+    let height = dot(delta, n);
+    let in_plane = delta - height * n;
+    return vec3<f32>(length(in_plane), atan2(dot(in_plane, e2), dot(in_plane, e1)), height);
+}
+
+// Frequency ratio g = ν_obs/ν_emit from the orbital motion of body b's
+// disk gas at `pos`: light from gas on a circular Keplerian orbit about the
+// disk normal over light from a static emitter at the same point. The ray
+// is traced backwards with covariant momentum p = (p_i, p_t), so the
+// photon's angular momentum about the normal, p_φ = (n × δ)·p, has the
+// opposite sign to the light's, whose p·u = u^t (p_t - Ω p_φ).
+//
+// Ω and u^t are those of the equatorial Kerr orbit at Boyer–Lindquist
+// radius r, where the Kerr–Schild cylinder radius is √(r² + a²), and the
+// static emitter's u^t is 1/√(-g_tt) with -g_tt = 1 - rs r/Σ = 1 - rs/r
+// there. This is exact for an untilted disk; a tilted disk takes the spin
+// projected onto its normal, as the equatorial orbits of a slower hole.
+fn orbital_doppler(b: u32, pos: vec3<f32>, p: vec4<f32>) -> f32 {
+    let rs = bodies[b].rs;
+    let n = bodies[b].disk_normal.xyz;
+    let delta = pos - bodies[b].position.xyz;
+    let rho = disk_coords(delta, n).x;
+    let m = 0.5 * rs;
+    let a = bodies[b].spin * m * n.y;
+    let r = max(sqrt(max(rho * rho - a * a, 0.0)), 1.01 * rs);
+    let a_sqrt_m = a * sqrt(m);
+    let r32 = r * sqrt(r);
+    let omega = sqrt(m) / (r32 + a_sqrt_m);
+    let u_t = (r32 + a_sqrt_m) / (pow(r, 0.75) * sqrt(max(r32 - 3.0 * m * sqrt(r) + 2.0 * a_sqrt_m, 1e-4)));
+    let lapse = sqrt(max(1.0 - rs / r, 1e-4));
+    let p_phi = dot(cross(n, delta), p.xyz);
+    return 1.0 / (lapse * u_t * max(1.0 - omega * p_phi / p.w, 1e-3));
+}
+
+// Frequency ratio g = ν_obs/ν_emit of body b's disk light at `pos`, from
+// the camera's motion and whichever of the orbital and gravitational shifts
+// are enabled
+fn disk_redshift(b: u32, pos: vec3<f32>, p: vec4<f32>, grav_redshift: f32) -> f32 {
+    var g = observer_doppler;
+    if u.doppler_beaming == 1u {
+        g *= orbital_doppler(b, pos, p);
+    }
+    if u.gravitational_redshift == 1u {
+        g *= grav_redshift;
+    }
+    return g;
+}
+
+// Colour of body b's thin disk at `pos`. `time` is the emission time of the
+// light (u.time unless light-travel delay is tracked), `p` the ray's
+// covariant momentum there (see `orbital_doppler`), `grav_redshift` the
+// observed/emitted frequency ratio of a static emitter and `heat` scales the
+// gas temperature.
+fn disk_color_for_body(b: u32, pos: vec3<f32>, time: f32, p: vec4<f32>, grav_redshift: f32, heat: f32) -> vec3<f32> {
+    let rs = bodies[b].rs;
+    let r_isco = bodies[b].isco;
+    let disk_inner = bodies[b].disk_inner;
+    let disk_outer = bodies[b].disk_outer;
+    let coords = disk_coords(pos - bodies[b].position.xyz, bodies[b].disk_normal.xyz);
+    let r = coords.x;
+    let azimuth = coords.y + time * 0.5;
 
-    let r_isco = 3.0 * rs;
     var luminosity: f32;
     if r > r_isco {
         luminosity = (1.0 / (r * r)) * (1.0 - sqrt(r_isco / r));
@@ -225,74 +488,405 @@ fn disk_color_for_body(pos: vec3<f32>, body_pos: vec3<f32>, rs: f32, disk_inner:
     let l_peak = (1.0 / (r_peak * r_peak)) * (1.0 - sqrt(r_isco / r_peak));
     luminosity = luminosity / max(l_peak, 0.001);
 
+    // The observed spectrum is the emitted blackbody at g times the
+    // temperature, gⁿ times as bright
+    let g = disk_redshift(b, pos, p, grav_redshift);
+
     let t_normalized = pow(clamp(disk_inner / r, 0.0, 1.0), 0.75);
-    let temp = mix(1500.0, 6500.0, t_normalized);
+    let temp = mix(1500.0, 6500.0, t_normalized) * heat * g;
     var col = blackbody(temp);
 
-    let detail = disk_detail(r, azimuth, rs, disk_inner);
-    col = col * luminosity * detail * 3.0;
+    let detail = disk_detail(r, azimuth, rs, disk_inner, time);
+    col = col * luminosity * detail * 3.0 * pow(g, u.intensity_exponent);
 
-    // Doppler shift from Keplerian orbital velocity
-    let v_orb = sqrt(rs / (2.0 * r));
-    let doppler = 1.0 / (1.0 + v_orb * sin(azimuth));
-    let doppler3 = doppler * doppler * doppler;
-    col = col * clamp(doppler3, 0.1, 4.0);
+    // Soft outer edge
+    let outer_fade = 1.0 - smoothstep(disk_outer - 1.0 * rs, disk_outer, r);
+    col = col * outer_fade;
+
+    return col;
+}
 
-    // Gravitational redshift from all bodies
+// False colour of disk image `order`: direct, secondary, tertiary, then
+// the photon ring beyond
+fn order_color(order: u32) -> vec3<f32> {
+    switch min(order, 3u) {
+        case 0u: { return vec3<f32>(1.0, 0.25, 0.1); }
+        case 1u: { return vec3<f32>(0.2, 1.0, 0.2); }
+        case 2u: { return vec3<f32>(0.2, 0.4, 1.0); }
+        default: { return vec3<f32>(1.0, 0.3, 1.0); }
+    }
+}
+
+// ── Diagnostic views ──────────────────────────────────────────────────
+//
+// Mirrors the colour tables in `renderer::uniforms`.
+
+fn fate_color(fate: u32) -> vec3<f32> {
+    switch fate {
+        case 0u: { return vec3<f32>(0.2, 0.4, 1.0); }
+        case 1u: { return vec3<f32>(0.5, 0.0, 0.0); }
+        case 2u: { return vec3<f32>(1.0, 0.9, 0.1); }
+        case 3u: { return vec3<f32>(1.0, 0.3, 1.0); }
+        default: { return vec3<f32>(1.0, 0.5, 0.1); }
+    }
+}
+
+fn body_color(b: u32) -> vec3<f32> {
+    switch b % 6u {
+        case 0u: { return vec3<f32>(1.0, 0.25, 0.1); }
+        case 1u: { return vec3<f32>(0.2, 1.0, 0.2); }
+        case 2u: { return vec3<f32>(0.2, 0.4, 1.0); }
+        case 3u: { return vec3<f32>(1.0, 0.9, 0.1); }
+        case 4u: { return vec3<f32>(1.0, 0.3, 1.0); }
+        default: { return vec3<f32>(0.1, 0.9, 0.9); }
+    }
+}
+
+// Dark blue through green and yellow to red over t in 0..1
+fn ramp_color(t: f32) -> vec3<f32> {
+    var stops = array<vec3<f32>, 5>(
+        vec3<f32>(0.05, 0.05, 0.3),
+        vec3<f32>(0.1, 0.4, 1.0),
+        vec3<f32>(0.1, 0.9, 0.6),
+        vec3<f32>(0.95, 0.9, 0.1),
+        vec3<f32>(1.0, 0.15, 0.05),
+    );
+    let x = clamp(t, 0.0, 1.0) * 4.0;
+    let i = min(u32(x), 3u);
+    return mix(stops[i], stops[i + 1u], x - f32(i));
+}
+
+// White when unshifted, red by g = 1/2 and blue by g = 2
+fn redshift_color(g: f32) -> vec3<f32> {
+    let t = clamp(log2(max(g, 1e-6)), -1.0, 1.0);
+    let end = select(vec3<f32>(0.1, 0.3, 1.0), vec3<f32>(1.0, 0.15, 0.05), t < 0.0);
+    return mix(vec3<f32>(1.0), end, abs(t));
+}
+
+// Sky reduced to a dim grey behind false colours
+fn dimmed(color: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(0.2 * dot(color, vec3<f32>(0.2126, 0.7152, 0.0722)));
+}
+
+// ── Volumetric disk ───────────────────────────────────────────────────
+//
+// With DISK_VOLUMETRIC each disk is gas around the body's equatorial plane,
+// Gaussian in height with scale height H = disk_scale_height · R. Its column
+// density falls off as 1/R from the inner edge, where a vertical path through
+// it has optical depth disk_optical_depth. The gas radiates the thin disk's
+// colour at the same radius as its source function, hotter in the thin layers
+// above the plane, and emission and absorption are integrated along the bent
+// ray step by step.
+
+// Gas ends this many scale heights from the plane
+const DISK_EXTENT: f32 = 4.0;
+// Density samples per scale height along a step, and at most per step
+const DISK_SAMPLES_PER_HEIGHT: f32 = 2.0;
+const MAX_DISK_SAMPLES: u32 = 16u;
+// Transmittance below which nothing behind the disk shows
+const MIN_TRANSMITTANCE: f32 = 1e-3;
+
+// Absorption coefficient of body b's disk gas at `pos`, and its heating
+fn disk_gas(b: u32, pos: vec3<f32>) -> vec2<f32> {
+    let coords = disk_coords(pos - bodies[b].position.xyz, bodies[b].disk_normal.xyz);
+    let r = coords.x;
+    let inner = bodies[b].disk_inner;
+    let outer = bodies[b].disk_outer;
+    let height = u.disk_scale_height * r;
+    let z = coords.z / max(height, 1e-6);
+    if r <= inner || r >= outer || abs(z) > DISK_EXTENT {
+        return vec2<f32>(0.0);
+    }
+    let rs = bodies[b].rs;
+    let edges = smoothstep(inner, inner + 0.5 * rs, r) * (1.0 - smoothstep(outer - rs, outer, r));
+    let column = edges * inner / r;
+    let density = column * exp(-0.5 * z * z) / (sqrt(2.0 * PI) * height);
+    return vec2<f32>(u.disk_optical_depth * density, 1.0 + 0.25 * z * z);
+}
+
+// Density samples needed along the chord a → b, 0 if it misses all gas
+fn disk_samples(a: vec3<f32>, b: vec3<f32>) -> u32 {
+    let len = length(b - a);
+    var n = 0.0;
+    for (var i = 0u; i < body_count(); i = i + 1u) {
+        let normal = bodies[i].disk_normal.xyz;
+        let da = disk_coords(a - bodies[i].position.xyz, normal);
+        let db = disk_coords(b - bodies[i].position.xyz, normal);
+        let ra = da.x;
+        let rb = db.x;
+        let inner = bodies[i].disk_inner;
+        let outer = bodies[i].disk_outer;
+        // The cylindrical radius along a chord peaks at an end and changes
+        // no faster than the chord's length.
+        let r_max = min(max(ra, rb), outer);
+        let r_min = min(ra, rb) - len;
+        let z_max = DISK_EXTENT * u.disk_scale_height * r_max;
+        let above = da.z * db.z > 0.0 && min(abs(da.z), abs(db.z)) > z_max;
+        if max(ra, rb) > inner && r_min < outer && !above {
+            let h_min = u.disk_scale_height * max(r_min, inner);
+            n = max(n, len * DISK_SAMPLES_PER_HEIGHT / h_min);
+        }
+    }
+    return min(u32(ceil(n)), MAX_DISK_SAMPLES);
+}
+
+// ── Bodies ────────────────────────────────────────────────────────────
+//
+// The body buffer grows on demand and may hold more entries than the scene.
+
+fn body_count() -> u32 {
+    return min(u.num_bodies, arrayLength(&bodies));
+}
+
+// Bodies farther than `body_cutoff` Schwarzschild radii from a step barely
+// change their pull along it, so their field is evaluated once at the start
+// of the step and held for every integrator stage; only the bodies within
+// reach are summed per stage. Bodies past MAX_NEARBY count as far.
+const MAX_NEARBY: u32 = 32u;
+var<private> nearby: array<u32, MAX_NEARBY>;
+var<private> nearby_count: u32;
+// Frozen far-field contribution to the position and velocity derivatives
+var<private> far_dx: vec3<f32>;
+var<private> far_dv: vec3<f32>;
+// Crossings of each body's disk plane so far, i.e. the order of the next
+// image of its disk. Orders past 3 share a colour, so each body's count
+// saturates there and takes 2 bits; bodies past MAX_ORDER_BODIES share the
+// last count.
+const MAX_ORDER_BODIES: u32 = 512u;
+var<private> plane_crossings: array<u32, MAX_ORDER_BODIES / 16u>;
+
+fn crossings_of(b: u32) -> u32 {
+    let i = min(b, MAX_ORDER_BODIES - 1u);
+    return (plane_crossings[i / 16u] >> (2u * (i % 16u))) & 3u;
+}
+
+fn count_crossing(b: u32) {
+    let i = min(b, MAX_ORDER_BODIES - 1u);
+    if crossings_of(i) < 3u {
+        plane_crossings[i / 16u] += 1u << (2u * (i % 16u));
+    }
+}
+
+// Split the bodies into those within `reach` of a step from `s` and the far
+// field, evaluated at `s`.
+fn gather_nearby(s: RayState, reach: f32) {
+    nearby_count = 0u;
+    far_dx = vec3<f32>(0.0);
+    far_dv = vec3<f32>(0.0);
+    let pos = s.x.xyz;
+    for (var i = 0u; i < body_count(); i = i + 1u) {
+        let near = length(pos - bodies[i].position.xyz) < u.body_cutoff * bodies[i].rs + reach;
+        if near && nearby_count < MAX_NEARBY {
+            nearby[nearby_count] = i;
+            nearby_count = nearby_count + 1u;
+        } else if u.geodesic_model == MODEL_KERR_SCHILD {
+            let d = kerr_body_derivs(i, pos, s.v.xyz);
+            far_dx += d.dx;
+            far_dv += d.dp;
+        } else {
+            far_dv += body_acceleration(i, pos, s.v.xyz);
+        }
+    }
+}
+
+// Number of bodies summed per stage, and the index of the j-th one
+fn active_count() -> u32 {
+    return select(body_count(), nearby_count, u.body_cutoff > 0.0);
+}
+
+fn active_body(j: u32) -> u32 {
+    return select(j, nearby[j], u.body_cutoff > 0.0);
+}
+
+// Gravitational redshift from all bodies, for an emitter at rest at `pos`
+// seen from infinity
+fn potential_redshift(pos: vec3<f32>) -> f32 {
     var grav_potential = 0.0;
-    for (var i = 0u; i < u.num_bodies; i = i + 1u) {
+    for (var i = 0u; i < body_count(); i = i + 1u) {
         let bp = bodies[i].position.xyz;
         let dist = length(pos - bp);
         if dist > 0.01 {
             grav_potential += bodies[i].rs / dist;
         }
     }
-    let grav_redshift = sqrt(max(1.0 - grav_potential, 0.001));
-    col = col * grav_redshift;
-
-    // Soft outer edge
-    let outer_fade = 1.0 - smoothstep(disk_outer - 1.0 * rs, disk_outer, r);
-    col = col * outer_fade;
-
-    return col;
+    return sqrt(max(1.0 - grav_potential, 0.001));
 }
 
 // ── Multi-body gravitational acceleration ─────────────────────────────
 
+fn body_acceleration(i: u32, pos: vec3<f32>, vel: vec3<f32>) -> vec3<f32> {
+    let body_pos = bodies[i].position.xyz;
+    let rs_i = bodies[i].rs;
+    let delta = pos - body_pos;
+    let r = length(delta);
+
+    // Skip if too close (inside event horizon)
+    if r < rs_i * 0.5 {
+        return vec3<f32>(0.0);
+    }
+
+    let r2 = r * r;
+    let r5 = r2 * r2 * r;
+
+    // |cross(delta, vel)|^2
+    let c = cross(delta, vel);
+    let L2 = dot(c, c);
+
+    // a = -1.5 * rs / r^5 * L^2 * delta
+    return -1.5 * rs_i / r5 * L2 * delta;
+}
+
 fn gravitational_acceleration(pos: vec3<f32>, vel: vec3<f32>) -> vec3<f32> {
-    var accel = vec3<f32>(0.0);
+    var accel = far_dv;
+    for (var j = 0u; j < active_count(); j = j + 1u) {
+        accel += body_acceleration(active_body(j), pos, vel);
+    }
+    return accel;
+}
 
-    for (var i = 0u; i < u.num_bodies; i = i + 1u) {
-        let body_pos = bodies[i].position.xyz;
-        let rs_i = bodies[i].rs;
-        let delta = pos - body_pos;
-        let r = length(delta);
+// ── Kerr (rotating) bodies ────────────────────────────────────────────
+//
+// Each body is a Kerr black hole in Cartesian Kerr–Schild form,
+//   g_μν = η_μν + f l_μ l_ν,   g^μν = η^μν - f l^μ l^ν,
+// with its spin axis along +y. The local Kerr–Schild frame (X, Y, Z) with Z as
+// the axis is the cyclic permutation (X, Y, Z) = (z, x, y) of world coordinates.
+// Several bodies are superposed by summing their f l l terms, in the same spirit
+// as the pseudo-Newtonian force; for a single body the geodesics are exact.
+
+struct KerrField {
+    // f = rs r³ / (r⁴ + a² Z²)
+    f: f32,
+    grad_f: vec3<f32>,
+    // l^μ p_μ = 1 + l·p for a photon with p_t = -1
+    lp: f32,
+    grad_lp: vec3<f32>,
+    // Spatial part of l, world frame
+    l: vec3<f32>,
+};
 
-        // Skip if too close (inside event horizon)
-        if r < rs_i * 0.5 {
-            continue;
-        }
+struct KerrDeriv {
+    dx: vec3<f32>,
+    dp: vec3<f32>,
+};
 
-        let r2 = r * r;
-        let r5 = r2 * r2 * r;
+fn to_ks(v: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(v.z, v.x, v.y);
+}
 
-        // |cross(delta, vel)|^2
-        let c = cross(delta, vel);
-        let L2 = dot(c, c);
+fn from_ks(v: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(v.y, v.z, v.x);
+}
 
-        // a = -1.5 * rs / r^5 * L^2 * delta
-        accel -= 1.5 * rs_i / r5 * L2 * delta;
+// Boyer–Lindquist radius r of a point at offset `delta` from a body with spin
+// parameter a (length units), solving r⁴ - (R² - a²) r² - a² Z² = 0.
+fn kerr_radius(delta: vec3<f32>, a: f32) -> f32 {
+    if a == 0.0 {
+        return length(delta);
     }
+    let r2_flat = dot(delta, delta);
+    let b = 0.5 * (r2_flat - a * a);
+    let r2 = b + sqrt(b * b + a * a * delta.y * delta.y);
+    return sqrt(max(r2, 0.0));
+}
 
-    return accel;
+fn kerr_field(delta_world: vec3<f32>, p_world: vec3<f32>, rs: f32, spin: f32) -> KerrField {
+    let q = to_ks(delta_world);
+    let p = to_ks(p_world);
+    let a = spin * 0.5 * rs;
+    let a2 = a * a;
+
+    let r = kerr_radius(delta_world, a);
+    let r2 = r * r;
+    let big_r2 = dot(q, q);
+
+    // ∂r/∂q from implicit differentiation of the radius equation
+    let d = max(2.0 * r2 - big_r2 + a2, 1e-6);
+    let dr = vec3<f32>(q.x * r / d, q.y * r / d, q.z * (r2 + a2) / (r * d));
+
+    let n = r2 * r2 + a2 * q.z * q.z;
+    let f = rs * r2 * r / n;
+    let grad_f = rs * r2 * ((3.0 * a2 * q.z * q.z - r2 * r2) * dr - vec3<f32>(0.0, 0.0, 2.0 * a2 * r * q.z)) / (n * n);
+
+    let big_a = r2 + a2;
+    let u1 = q.x * p.x + q.y * p.y;
+    let u2 = q.y * p.x - q.x * p.y;
+    let l = vec3<f32>((r * q.x + a * q.y) / big_a, (r * q.y - a * q.x) / big_a, q.z / r);
+    let lp = 1.0 + dot(l, p);
+
+    let num = r * u1 + a * u2;
+    let du1 = vec3<f32>(p.x, p.y, 0.0);
+    let du2 = vec3<f32>(-p.y, p.x, 0.0);
+    let grad_planar = ((dr * u1 + r * du1 + a * du2) * big_a - num * 2.0 * r * dr) / (big_a * big_a);
+    let grad_axial = (vec3<f32>(0.0, 0.0, p.z * r) - q.z * p.z * dr) / r2;
+
+    var field: KerrField;
+    field.f = f;
+    field.grad_f = from_ks(grad_f);
+    field.lp = lp;
+    field.grad_lp = from_ks(grad_planar + grad_axial);
+    field.l = from_ks(l);
+    return field;
+}
+
+// Hamilton's equations for H = ½ g^μν p_μ p_ν with p_t = -1:
+//   dx/dλ = p - Σ f (l^μ p_μ) l
+//   dp/dλ = ½ Σ [(l^μ p_μ)² ∇f + 2 f (l^μ p_μ) ∇(l^μ p_μ)]
+fn kerr_derivs(pos: vec3<f32>, p: vec3<f32>) -> KerrDeriv {
+    var out: KerrDeriv;
+    out.dx = p + far_dx;
+    out.dp = far_dv;
+
+    for (var j = 0u; j < active_count(); j = j + 1u) {
+        let d = kerr_body_derivs(active_body(j), pos, p);
+        out.dx += d.dx;
+        out.dp += d.dp;
+    }
+
+    return out;
+}
+
+// Contribution of body `i` to the sums above
+fn kerr_body_derivs(i: u32, pos: vec3<f32>, p: vec3<f32>) -> KerrDeriv {
+    var out: KerrDeriv;
+    out.dx = vec3<f32>(0.0);
+    out.dp = vec3<f32>(0.0);
+    let delta = pos - bodies[i].position.xyz;
+    if dot(delta, delta) < 1e-8 {
+        return out;
+    }
+    let field = kerr_field(delta, p, bodies[i].rs, bodies[i].spin);
+    out.dx = -field.f * field.lp * field.l;
+    out.dp = 0.5 * field.lp * field.lp * field.grad_f + field.f * field.lp * field.grad_lp;
+    return out;
+}
+
+// Scale a launch direction n to p = k n so the momentum is null (H = 0):
+//   (1 - Σ f (l·n)²) k² - 2 Σ f (l·n) k - (1 + Σ f) = 0
+fn kerr_initial_momentum(pos: vec3<f32>, dir: vec3<f32>) -> vec3<f32> {
+    var qa = 1.0;
+    var qb = 0.0;
+    var qc = 1.0;
+    for (var i = 0u; i < body_count(); i = i + 1u) {
+        let delta = pos - bodies[i].position.xyz;
+        if dot(delta, delta) < 1e-8 {
+            continue;
+        }
+        let field = kerr_field(delta, dir, bodies[i].rs, bodies[i].spin);
+        let ln = field.lp - 1.0;
+        qa -= field.f * ln * ln;
+        qb += field.f * ln;
+        qc += field.f;
+    }
+    let k = (qb + sqrt(max(qb * qb + qa * qc, 0.0))) / max(qa, 1e-4);
+    return k * dir;
 }
 
 fn check_capture(pos: vec3<f32>) -> i32 {
-    for (var i = 0u; i < u.num_bodies; i = i + 1u) {
+    for (var i = 0u; i < body_count(); i = i + 1u) {
         let body_pos = bodies[i].position.xyz;
-        let rs_i = bodies[i].rs;
-        let r = length(pos - body_pos);
-        if r < rs_i {
+        let a_i = bodies[i].spin * 0.5 * bodies[i].rs;
+        let r = kerr_radius(pos - body_pos, a_i);
+        if r < bodies[i].horizon {
             return i32(i);
         }
     }
@@ -300,24 +894,199 @@ fn check_capture(pos: vec3<f32>) -> i32 {
 }
 
 fn check_escape(pos: vec3<f32>) -> bool {
-    for (var i = 0u; i < u.num_bodies; i = i + 1u) {
+    for (var i = 0u; i < body_count(); i = i + 1u) {
         let body_pos = bodies[i].position.xyz;
-        let r = length(pos - body_pos);
-        if r < ESCAPE_RADIUS {
+        let a_i = bodies[i].spin * 0.5 * bodies[i].rs;
+        let r = kerr_radius(pos - body_pos, a_i);
+        if r <= ESCAPE_RADIUS {
             return false;
         }
     }
     return true;
 }
 
-// ACES filmic tonemapping
-fn aces(x: vec3<f32>) -> vec3<f32> {
-    let a = 2.51;
-    let b = 0.03;
-    let c = 2.43;
-    let d = 0.59;
-    let e = 0.14;
-    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+// ── Exact Schwarzschild null geodesics ───────────────────────────────
+//
+// Full geodesic equation d²x^μ/dλ² = -Γ^μ_αβ k^α k^β for body 0 in Kerr–Schild
+// coordinates, g_μν = η_μν + f l_μ l_ν with f = rs/r and l_μ = (1, n), n = x/r.
+// The state carries k^μ = (k.xyz, k.w = k^t), so coordinate time along the ray
+// and the conserved energy p_t are available for light-travel delay and
+// redshift.
+
+// -Γ^μ_αβ k^α k^β, returned as (spatial, w = time component)
+fn null_geodesic_accel(delta: vec3<f32>, k: vec4<f32>, rs: f32) -> vec4<f32> {
+    let r = length(delta);
+    let n = delta / r;
+    let f = rs / r;
+    let kv = k.xyz;
+    let nk = dot(n, kv);
+    let lk = k.w + nk;
+
+    // Directional derivatives along k of f and l_i, and k^i k^j ∂_i l_j
+    let k_grad_f = -rs * nk / (r * r);
+    let m = (kv - n * nk) / r;
+    let q = (dot(kv, kv) - nk * nk) / r;
+
+    // C_ν = (∂_α g_νβ - ½ ∂_ν g_αβ) k^α k^β
+    let c0 = k_grad_f * lk + f * q;
+    let a_vec = k_grad_f * lk * n + f * (lk * m + q * n);
+    let b_vec = -0.5 * rs / (r * r) * lk * lk * n + f * lk * m;
+    let c = a_vec - b_vec;
+
+    // Raise with g^μν = η^μν - f l^μ l^ν, l^μ = (-1, n)
+    let lc = -c0 + dot(n, c);
+    return vec4<f32>(-c + f * lc * n, c0 - f * lc);
+}
+
+// Null 4-momentum with spatial part `dir`: solves g_μν k^μ k^ν = 0 for k^t > 0
+fn null_initial_momentum(delta: vec3<f32>, dir: vec3<f32>, rs: f32) -> vec4<f32> {
+    let r = length(delta);
+    let f = rs / r;
+    let s = dot(delta / r, dir);
+    let k0 = (f * s + sqrt(f * f * s * s + (1.0 - f) * (1.0 + f * s * s))) / max(1.0 - f, 1e-4);
+    return vec4<f32>(dir, k0);
+}
+
+// Covariant energy component p_t = g_tν k^ν = -k^t + f (l·k)
+fn null_p_t(delta: vec3<f32>, k: vec4<f32>, rs: f32) -> f32 {
+    let r = length(delta);
+    return -k.w + rs / r * (k.w + dot(delta / r, k.xyz));
+}
+
+// Frequency measured by a static observer at `delta`, up to the common
+// factor of the photon's energy: -p_t / sqrt(1 - rs/r)
+fn static_frequency(delta: vec3<f32>, k: vec4<f32>, rs: f32) -> f32 {
+    return -null_p_t(delta, k, rs) / sqrt(max(1.0 - rs / length(delta), 1e-4));
+}
+
+// Covariant momentum (p_i, p_t) of the ray at state `s`, as taken by
+// `orbital_doppler`. The pseudo-Newtonian and Kerr–Schild rays have p_t = -1;
+// for the null geodesic only p_t needs lowering, since the spatial part
+// enters through p_φ alone, where the l_i term drops out.
+fn ray_momentum(s: RayState) -> vec4<f32> {
+    if u.geodesic_model == MODEL_NULL_GEODESIC {
+        return vec4<f32>(s.v.xyz, null_p_t(s.x.xyz - bodies[0].position.xyz, s.v, bodies[0].rs));
+    }
+    if u.geodesic_model == MODEL_KERR_SCHILD {
+        return vec4<f32>(s.v.xyz, -1.0);
+    }
+    return vec4<f32>(normalize(s.v.xyz), -1.0);
+}
+
+// ── Integrators ───────────────────────────────────────────────────────
+//
+// All geodesic models share one state so the steppers are written once. For
+// the null-geodesic model x.w is coordinate time and v = k^μ (v.w = k^t); the
+// other models leave w at zero and keep the direction (pseudo-Newtonian) or
+// the covariant momentum p_i (Kerr–Schild) in v.xyz.
+
+struct RayState {
+    x: vec4<f32>,
+    v: vec4<f32>,
+};
+
+fn ray_derivs(s: RayState) -> RayState {
+    if u.geodesic_model == MODEL_NULL_GEODESIC {
+        let accel = null_geodesic_accel(s.x.xyz - bodies[0].position.xyz, s.v, bodies[0].rs);
+        return RayState(s.v, accel);
+    }
+    if u.geodesic_model == MODEL_KERR_SCHILD {
+        let d = kerr_derivs(s.x.xyz, s.v.xyz);
+        return RayState(vec4<f32>(d.dx, 0.0), vec4<f32>(d.dp, 0.0));
+    }
+    let accel = gravitational_acceleration(s.x.xyz, s.v.xyz);
+    return RayState(vec4<f32>(s.v.xyz, 0.0), vec4<f32>(accel, 0.0));
+}
+
+// s + h d
+fn ray_offset(s: RayState, h: f32, d: RayState) -> RayState {
+    return RayState(s.x + h * d.x, s.v + h * d.v);
+}
+
+// Classical RK4 with a fixed step
+fn rk4_step(s: RayState, h: f32) -> RayState {
+    let d1 = ray_derivs(s);
+    let d2 = ray_derivs(ray_offset(s, 0.5 * h, d1));
+    let d3 = ray_derivs(ray_offset(s, 0.5 * h, d2));
+    let d4 = ray_derivs(ray_offset(s, h, d3));
+    return RayState(
+        s.x + (h / 6.0) * (d1.x + 2.0 * d2.x + 2.0 * d3.x + d4.x),
+        s.v + (h / 6.0) * (d1.v + 2.0 * d2.v + 2.0 * d3.v + d4.v),
+    );
+}
+
+struct AdaptiveStep {
+    next: RayState,
+    // Difference between the 5th- and embedded 4th-order solutions
+    err: RayState,
+    // Derivative at `next`, reused as the first stage of the following step
+    d_next: RayState,
+};
+
+// Dormand–Prince 5(4). `d1` is the derivative at `s` (first same as last).
+fn dp45_step(s: RayState, d1: RayState, h: f32) -> AdaptiveStep {
+    let d2 = ray_derivs(ray_offset(s, h * 0.2, d1));
+    let d3 = ray_derivs(RayState(
+        s.x + h * (3.0 / 40.0 * d1.x + 9.0 / 40.0 * d2.x),
+        s.v + h * (3.0 / 40.0 * d1.v + 9.0 / 40.0 * d2.v),
+    ));
+    let d4 = ray_derivs(RayState(
+        s.x + h * (44.0 / 45.0 * d1.x - 56.0 / 15.0 * d2.x + 32.0 / 9.0 * d3.x),
+        s.v + h * (44.0 / 45.0 * d1.v - 56.0 / 15.0 * d2.v + 32.0 / 9.0 * d3.v),
+    ));
+    let d5 = ray_derivs(RayState(
+        s.x + h * (19372.0 / 6561.0 * d1.x - 25360.0 / 2187.0 * d2.x + 64448.0 / 6561.0 * d3.x - 212.0 / 729.0 * d4.x),
+        s.v + h * (19372.0 / 6561.0 * d1.v - 25360.0 / 2187.0 * d2.v + 64448.0 / 6561.0 * d3.v - 212.0 / 729.0 * d4.v),
+    ));
+    let d6 = ray_derivs(RayState(
+        s.x + h * (9017.0 / 3168.0 * d1.x - 355.0 / 33.0 * d2.x + 46732.0 / 5247.0 * d3.x + 49.0 / 176.0 * d4.x - 5103.0 / 18656.0 * d5.x),
+        s.v + h * (9017.0 / 3168.0 * d1.v - 355.0 / 33.0 * d2.v + 46732.0 / 5247.0 * d3.v + 49.0 / 176.0 * d4.v - 5103.0 / 18656.0 * d5.v),
+    ));
+    let next = RayState(
+        s.x + h * (35.0 / 384.0 * d1.x + 500.0 / 1113.0 * d3.x + 125.0 / 192.0 * d4.x - 2187.0 / 6784.0 * d5.x + 11.0 / 84.0 * d6.x),
+        s.v + h * (35.0 / 384.0 * d1.v + 500.0 / 1113.0 * d3.v + 125.0 / 192.0 * d4.v - 2187.0 / 6784.0 * d5.v + 11.0 / 84.0 * d6.v),
+    );
+    let d7 = ray_derivs(next);
+    let err = RayState(
+        h * (71.0 / 57600.0 * d1.x - 71.0 / 16695.0 * d3.x + 71.0 / 1920.0 * d4.x - 17253.0 / 339200.0 * d5.x + 22.0 / 525.0 * d6.x - 1.0 / 40.0 * d7.x),
+        h * (71.0 / 57600.0 * d1.v - 71.0 / 16695.0 * d3.v + 71.0 / 1920.0 * d4.v - 17253.0 / 339200.0 * d5.v + 22.0 / 525.0 * d6.v - 1.0 / 40.0 * d7.v),
+    );
+    return AdaptiveStep(next, err, d7);
+}
+
+// Error of a step relative to the tolerance: position against the distance to
+// the nearest body (the local curvature scale), direction against its length.
+fn step_error(s: RayState, err: RayState) -> f32 {
+    var r_near = 1e30;
+    for (var i = 0u; i < body_count(); i = i + 1u) {
+        r_near = min(r_near, length(s.x.xyz - bodies[i].position.xyz));
+    }
+    let e_x = length(err.x.xyz) / max(r_near, 1e-3);
+    let e_v = length(err.v.xyz) / max(length(s.v.xyz), 1e-6);
+    return max(e_x, e_v) / u.tolerance;
+}
+
+// ── Moving observer ──────────────────────────────────────────────────
+//
+// Mirrors `renderer::observer`.
+
+// Frequency the camera measures over what the static observer measures for
+// the current ray
+var<private> observer_doppler: f32 = 1.0;
+
+// Static-frame direction of the ray a camera moving at `beta` sees along
+// `local`
+fn aberrate(local: vec3<f32>, beta: vec3<f32>) -> vec3<f32> {
+    let b2 = dot(beta, beta);
+    let gamma = 1.0 / sqrt(max(1.0 - b2, 1e-12));
+    let along = dot(local, beta) / b2;
+    return (local + ((gamma - 1.0) * along - gamma) * beta) / (gamma * (1.0 - dot(local, beta)));
+}
+
+// D = γ(1 + β·d) for light seen along static-frame direction `dir`
+fn doppler_factor(dir: vec3<f32>, beta: vec3<f32>) -> f32 {
+    let gamma = 1.0 / sqrt(max(1.0 - dot(beta, beta), 1e-12));
+    return gamma * (1.0 + dot(dir, beta));
 }
 
 // ── Main compute shader ──────────────────────────────────────────────
@@ -332,30 +1101,83 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     }
 
     let ndc = vec2<f32>(
-        (f32(pixel.x) + 0.5 - f32(dims.x) * 0.5) / (f32(dims.x) * 0.5),
-        -(f32(pixel.y) + 0.5 - f32(dims.y) * 0.5) / (f32(dims.y) * 0.5),
+        (f32(pixel.x) + 0.5 + u.jitter.x - f32(dims.x) * 0.5) / (f32(dims.x) * 0.5),
+        -(f32(pixel.y) + 0.5 + u.jitter.y - f32(dims.y) * 0.5) / (f32(dims.y) * 0.5),
     );
 
     let aspect = f32(dims.x) / f32(dims.y);
     let half_fov = tan(u.fov * 0.5);
-    let ray_dir = normalize(
+    var ray_dir = normalize(
         u.camera_forward.xyz
         + ndc.x * aspect * half_fov * u.camera_right.xyz
         + ndc.y * half_fov * u.camera_up.xyz
     );
 
+    // A moving camera's view is aberrated towards its direction of motion
+    // and Doppler shifted against the static observer rays launch from
+    observer_doppler = 1.0;
+    let beta = u.observer_velocity.xyz;
+    if dot(beta, beta) > 0.0 {
+        ray_dir = aberrate(ray_dir, beta);
+        observer_doppler = doppler_factor(ray_dir, beta);
+    }
+
     // 3D ray integration state
     var pos = u.camera_pos.xyz;
     var vel = ray_dir; // normalized direction (null geodesic, speed = 1)
 
-    let dt = u.step_size;
+    // The Kerr–Schild model integrates Hamilton's equations, where `vel` holds
+    // the covariant spatial momentum p_i (equal to the direction far away).
+    // Non-rotating scenes keep the pseudo-Newtonian path unchanged.
+    let use_kerr = u.geodesic_model == MODEL_KERR_SCHILD;
+    if use_kerr {
+        vel = kerr_initial_momentum(pos, ray_dir);
+    }
+
+    // The null-geodesic model follows body 0 only and also integrates k^t.
+    let use_null = u.geodesic_model == MODEL_NULL_GEODESIC;
+    let rs0 = bodies[0].rs;
+    var k = vec4<f32>(0.0);
+    // Frequency seen by the camera as a static observer, per unit photon
+    // energy: exact for the null geodesic, else from the summed potential
+    var observed_frequency = 1.0 / potential_redshift(pos);
+    if use_null {
+        k = null_initial_momentum(pos - bodies[0].position.xyz, ray_dir, rs0);
+        observed_frequency = static_frequency(pos - bodies[0].position.xyz, k, rs0);
+    }
+
+    var state = RayState(vec4<f32>(pos, 0.0), vec4<f32>(vel, 0.0));
+    if use_null {
+        state.v = k;
+    }
+
+    // The adaptive integrator starts from `step_size` and then follows its
+    // error estimate within [min_step, max_step]; rejected steps count
+    // against `max_steps` so the per-ray cost stays bounded.
+    let adaptive = u.integrator == INTEGRATOR_ADAPTIVE_RK45;
+    var h = u.step_size;
+    var d_first = RayState(vec4<f32>(0.0), vec4<f32>(0.0));
+    if adaptive {
+        h = clamp(h, u.min_step, u.max_step);
+        d_first = ray_derivs(state);
+    }
+    let split_far = u.body_cutoff > 0.0 && !use_null;
     var captured = false;
     var escaped = false;
 
-    // Disk crossing state
+    // What the diagnostic views show: steps taken, the body captured by (or
+    // -1), the disk's g where the ray first meets it (0 if never) and how the
+    // march ended
+    var steps = 0u;
+    var capturer = -1i;
+    var hit_redshift = 0.0;
+    var absorbed = false;
+
+    // Disk state: light gathered so far and the fraction of light from
+    // behind that still gets through
     var disk_color_accum = vec3<f32>(0.0);
-    var disk_hit = false;
-    var prev_y = pos.y;
+    let volumetric = u.disk_enabled == 1u && u.disk_model == DISK_VOLUMETRIC;
+    var transmittance = 1.0;
 
     // Grid volume state: composite grid lines along the curved ray path
     var grid_accum_color = vec3<f32>(0.0);
@@ -363,10 +1185,10 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     let grid_line_color = vec3<f32>(0.1, 0.6, 0.8);
     let grid_step_alpha = 0.12;
 
-    // RK4 integration in 3D
     for (var i = 0u; i < u.max_steps; i = i + 1u) {
         // Check capture
-        if check_capture(pos) >= 0i {
+        capturer = check_capture(pos);
+        if capturer >= 0i {
             captured = true;
             break;
         }
@@ -376,82 +1198,139 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
             escaped = true;
             break;
         }
+        steps += 1u;
 
-        // Sample Cartesian grid at current position (grid lives in flat space, ray is bent)
+        // Store pre-step position for disk crossing detection
+        let pos_before = pos;
+        let h_taken = h;
+
+        // Every stage of a step stays within h |v| of its start. The first
+        // stage is re-evaluated against the freshly frozen far field.
+        if split_far {
+            gather_nearby(state, 2.0 * h * max(length(vel), 1.0));
+            if adaptive {
+                d_first = ray_derivs(state);
+            }
+        }
+
+        if adaptive {
+            let trial = dp45_step(state, d_first, h);
+            let err = max(step_error(state, trial.err), 1e-10);
+            let factor = 0.9 * pow(err, -0.2);
+            if err > 1.0 && h > u.min_step {
+                h = max(h * max(factor, 0.2), u.min_step);
+                continue;
+            }
+            state = trial.next;
+            d_first = trial.d_next;
+            h = clamp(h * min(factor, 5.0), u.min_step, u.max_step);
+        } else {
+            state = rk4_step(state, h);
+        }
+        pos = state.x.xyz;
+        vel = state.v.xyz;
+
+        // Sample Cartesian grid along the step (grid lives in flat space, ray
+        // is bent); adaptive steps weight the sample by their length.
         if u.grid_enabled == 1u {
-            let strength = grid_strength_at_pos(pos);
-            let line_alpha = strength * grid_step_alpha;
+            let strength = grid_strength_at_pos(pos_before);
+            let weight = select(1.0, h_taken / u.step_size, adaptive);
+            let line_alpha = min(strength * grid_step_alpha * weight, 1.0);
             grid_accum_color += (1.0 - grid_accum_alpha) * line_alpha * grid_line_color;
             grid_accum_alpha += (1.0 - grid_accum_alpha) * line_alpha;
         }
 
-        // Store pre-step y for disk crossing detection
-        let y_before = pos.y;
-        let pos_before = pos;
+        // Volumetric disk: midpoint samples along the step
+        if volumetric {
+            let n = disk_samples(pos_before, pos);
+            let ds = length(pos - pos_before) / f32(max(n, 1u));
+            let momentum = ray_momentum(state);
+            for (var j = 0u; j < n; j = j + 1u) {
+                let p = mix(pos_before, pos, (f32(j) + 0.5) / f32(n));
+                var emit_time = u.time;
+                var grav_redshift = potential_redshift(p) * observed_frequency;
+                if use_null {
+                    emit_time = u.time - state.x.w;
+                    let emitted_frequency = static_frequency(p - bodies[0].position.xyz, state.v, rs0);
+                    grav_redshift = observed_frequency / max(emitted_frequency, 1e-4);
+                }
+                for (var b = 0u; b < body_count(); b = b + 1u) {
+                    let gas = disk_gas(b, p);
+                    if gas.x > 0.0 {
+                        if hit_redshift == 0.0 {
+                            hit_redshift = disk_redshift(b, p, momentum, grav_redshift);
+                        }
+                        var source = disk_color_for_body(b, p, emit_time, momentum, grav_redshift, gas.y);
+                        if u.render_mode == MODE_DISK_ORDER {
+                            source = order_color(crossings_of(b));
+                        }
+                        let absorbed = 1.0 - exp(-gas.x * ds);
+                        disk_color_accum += transmittance * absorbed * source;
+                        transmittance *= 1.0 - absorbed;
+                    }
+                }
+            }
+        }
 
-        // RK4 step: state = (pos, vel), derivative = (vel, accel)
-        let a1 = gravitational_acceleration(pos, vel);
-        let k1_pos = vel;
-        let k1_vel = a1;
-
-        let p2 = pos + 0.5 * dt * k1_pos;
-        let v2 = vel + 0.5 * dt * k1_vel;
-        let a2 = gravitational_acceleration(p2, v2);
-        let k2_pos = v2;
-        let k2_vel = a2;
-
-        let p3 = pos + 0.5 * dt * k2_pos;
-        let v3 = vel + 0.5 * dt * k2_vel;
-        let a3 = gravitational_acceleration(p3, v3);
-        let k3_pos = v3;
-        let k3_vel = a3;
-
-        let p4 = pos + dt * k3_pos;
-        let v4 = vel + dt * k3_vel;
-        let a4 = gravitational_acceleration(p4, v4);
-        let k4_pos = v4;
-        let k4_vel = a4;
-
-        pos = pos + (dt / 6.0) * (k1_pos + 2.0 * k2_pos + 2.0 * k3_pos + k4_pos);
-        vel = vel + (dt / 6.0) * (k1_vel + 2.0 * k2_vel + 2.0 * k3_vel + k4_vel);
-
-        // Disk crossing detection
-        if u.disk_enabled == 1u && !disk_hit {
-            let cur_y = pos.y;
-            if y_before * cur_y < 0.0 {
-                // Interpolate crossing point
-                let t_cross = abs(y_before) / (abs(y_before) + abs(cur_y));
+        // Thin disk: every crossing of a body's disk plane is one more image
+        // of its disk (direct, secondary, photon ring, ...), laid over what
+        // lies behind with an opacity given by how bright it is, so a single
+        // image shows mix(behind, disk, opacity).
+        if u.disk_enabled == 1u {
+            for (var b = 0u; b < body_count(); b = b + 1u) {
+                let body_pos = bodies[b].position.xyz;
+                let n = bodies[b].disk_normal.xyz;
+                let h_before = dot(pos_before - body_pos, n);
+                let h_after = dot(pos - body_pos, n);
+                if h_before * h_after >= 0.0 {
+                    continue;
+                }
+                let t_cross = abs(h_before) / (abs(h_before) + abs(h_after));
                 let cross_pos = pos_before + t_cross * (pos - pos_before);
+                let r_disk = disk_coords(cross_pos - body_pos, n).x;
+
+                if !volumetric && r_disk > bodies[b].disk_inner && r_disk < bodies[b].disk_outer {
+                    // With the null-geodesic model the disk is seen as it was
+                    // one light-travel time ago, redshifted by the ratio of
+                    // static observer frequencies at camera and emitter.
+                    var emit_time = u.time;
+                    var grav_redshift = potential_redshift(cross_pos) * observed_frequency;
+                    if use_null {
+                        emit_time = u.time - state.x.w;
+                        let emitted_frequency = static_frequency(cross_pos - bodies[0].position.xyz, state.v, rs0);
+                        grav_redshift = observed_frequency / max(emitted_frequency, 1e-4);
+                    }
 
-                disk_hit = true;
-
-                // Check each body's disk
-                for (var b = 0u; b < u.num_bodies; b = b + 1u) {
-                    let body_pos = bodies[b].position.xyz;
-                    let delta = cross_pos - body_pos;
-                    let r_disk = length(vec2<f32>(delta.x, delta.z));
-
-                    if r_disk > bodies[b].disk_inner && r_disk < bodies[b].disk_outer {
-                        let col = disk_color_for_body(
-                            cross_pos,
-                            body_pos,
-                            bodies[b].rs,
-                            bodies[b].disk_inner,
-                            bodies[b].disk_outer
-                        );
-                        // Additive blending for overlapping disks
-                        disk_color_accum += col;
+                    let momentum = ray_momentum(state);
+                    if hit_redshift == 0.0 {
+                        hit_redshift = disk_redshift(b, cross_pos, momentum, grav_redshift);
                     }
+                    var layer = disk_color_for_body(b, cross_pos, emit_time, momentum, grav_redshift, 1.0);
+                    let opacity = clamp(max(layer.x, max(layer.y, layer.z)), 0.0, 1.0);
+                    if u.render_mode == MODE_DISK_ORDER {
+                        layer = order_color(crossings_of(b));
+                    }
+                    disk_color_accum += transmittance * opacity * layer;
+                    transmittance *= 1.0 - opacity;
                 }
+                count_crossing(b);
             }
         }
+
+        // Opaque disk hides whatever lies behind, as a horizon would
+        if transmittance < MIN_TRANSMITTANCE {
+            captured = true;
+            absorbed = true;
+            break;
+        }
     }
 
     // If we ran out of steps, determine outcome from velocity
-    if !captured && !escaped {
+    let out_of_steps = !captured && !escaped;
+    if out_of_steps {
         // Check if heading away from all bodies
         var heading_away = true;
-        for (var i = 0u; i < u.num_bodies; i = i + 1u) {
+        for (var i = 0u; i < body_count(); i = i + 1u) {
             let delta = pos - bodies[i].position.xyz;
             if dot(vel, delta) < 0.0 {
                 heading_away = false;
@@ -467,40 +1346,57 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
 
     // ── Coloring ──
 
-    var color = vec3<f32>(0.0);
-    let has_disk = disk_hit && (disk_color_accum.x > 0.0 || disk_color_accum.y > 0.0 || disk_color_accum.z > 0.0);
-
-    if has_disk {
-        if escaped {
-            let exit_dir = normalize(vel);
-            let angles = dir_to_spherical(exit_dir);
-            let bg = background(angles.x, angles.y);
-            let disk_lum = max(disk_color_accum.x, max(disk_color_accum.y, disk_color_accum.z));
-            let opacity = clamp(disk_lum, 0.0, 1.0);
-            let behind = mix(bg, disk_color_accum, opacity);
-            if u.grid_enabled == 1u {
-                color = grid_accum_color + (1.0 - grid_accum_alpha) * behind;
-            } else {
-                color = behind;
+    // Disk light plus whatever shows through it: the sky, or nothing
+    var behind = vec3<f32>(0.0);
+    if escaped {
+        let angles = dir_to_spherical(normalize(vel));
+        behind = background(angles.x, angles.y) * pow(observer_doppler, u.intensity_exponent);
+        if u.render_mode == MODE_DISK_ORDER {
+            behind = dimmed(behind);
+        }
+    }
+    var color = disk_color_accum + transmittance * behind;
+    if escaped && u.grid_enabled == 1u {
+        color = grid_accum_color + (1.0 - grid_accum_alpha) * color;
+    }
+
+    // Diagnostic views replace the shaded colour outright
+    switch u.render_mode {
+        case MODE_STEPS: {
+            color = ramp_color(f32(steps) / f32(max(u.max_steps, 1u)));
+        }
+        case MODE_DEFLECTION: {
+            let cos_angle = clamp(dot(ray_dir, normalize(vel)), -1.0, 1.0);
+            color = select(vec3<f32>(0.0), ramp_color(acos(cos_angle) / PI), escaped);
+        }
+        case MODE_FATE: {
+            var fate = select(FATE_CAPTURED, FATE_ESCAPED, escaped);
+            if absorbed {
+                fate = FATE_ABSORBED;
+            } else if out_of_steps {
+                fate = select(FATE_STEPS_INWARD, FATE_STEPS_AWAY, escaped);
             }
-        } else {
-            color = disk_color_accum;
+            color = fate_color(fate);
         }
-    } else if captured {
-        color = vec3<f32>(0.0);
-    } else {
-        let exit_dir = normalize(vel);
-        let angles = dir_to_spherical(exit_dir);
-        let bg = background(angles.x, angles.y);
-        if u.grid_enabled == 1u {
-            color = grid_accum_color + (1.0 - grid_accum_alpha) * bg;
-        } else {
-            color = bg;
+        case MODE_REDSHIFT: {
+            color = select(dimmed(behind), redshift_color(hit_redshift), hit_redshift > 0.0);
+        }
+        case MODE_BODY: {
+            color = dimmed(behind);
+            if capturer >= 0i {
+                color = body_color(u32(capturer));
+            }
         }
+        default: {}
     }
 
-    // ACES tonemapping
-    color = aces(color);
-
-    textureStore(output, pixel, vec4<f32>(color, 1.0));
+    // Average with the earlier samples of this pixel. The output is linear
+    // HDR radiance; tonemapping happens when the image is displayed or saved.
+    let index = u32(pixel.y) * u32(dims.x) + u32(pixel.x);
+    var sum = vec4<f32>(color, 1.0);
+    if u.sample_index > 0u {
+        sum += accum[index];
+    }
+    accum[index] = sum;
+    textureStore(output, pixel, sum / f32(u.sample_index + 1u));
 }
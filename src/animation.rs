@@ -0,0 +1,240 @@
+//! Camera animation: a timeline of keyframes, each holding a camera pose and
+//! the simulation time to show, interpolated into a smooth flythrough.
+//!
+//! Camera parameters follow a Catmull–Rom spline through the keyframes, so
+//! motion does not stop at each one, and the azimuth turns the shorter way
+//! round between keyframes. Simulation time is interpolated
+//! linearly and may not decrease, so rendering can step the simulation
+//! forward frame by frame.
+
+use std::f64::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+use crate::scene::CameraSettings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// Seconds since the start of the animation.
+    pub time: f64,
+    /// Simulation time shown at this keyframe.
+    #[serde(default)]
+    pub sim_time: f64,
+    #[serde(flatten)]
+    pub camera: CameraSettings,
+}
+
+/// Keyframes ordered by `time`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Timeline {
+    keyframes: Vec<Keyframe>,
+}
+
+impl Timeline {
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// Insert a keyframe, replacing any at the same time.
+    pub fn insert(&mut self, keyframe: Keyframe) {
+        let i = self.keyframes.partition_point(|k| k.time < keyframe.time);
+        if self
+            .keyframes
+            .get(i)
+            .is_some_and(|k| k.time == keyframe.time)
+        {
+            self.keyframes[i] = keyframe;
+        } else {
+            self.keyframes.insert(i, keyframe);
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> Keyframe {
+        self.keyframes.remove(index)
+    }
+
+    /// Replace keyframe `index`, keeping the timeline ordered. Unlike
+    /// `insert`, a clash with another keyframe's time is left for `validate`,
+    /// which `Scene::save` checks.
+    pub fn update(&mut self, index: usize, keyframe: Keyframe) {
+        self.keyframes.remove(index);
+        let i = self.keyframes.partition_point(|k| k.time <= keyframe.time);
+        self.keyframes.insert(i, keyframe);
+    }
+
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> f64 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// Frames needed to cover the timeline at `fps`, including both ends.
+    pub fn frame_count(&self, fps: f64) -> u32 {
+        if self.keyframes.is_empty() {
+            return 0;
+        }
+        (self.duration() * fps + 1e-9).floor() as u32 + 1
+    }
+
+    /// Check what `sample` relies on: distinct times, sorted, with
+    /// simulation time never running backwards.
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, pair) in self.keyframes.windows(2).enumerate() {
+            if pair[1].time <= pair[0].time {
+                return Err(format!("keyframe {} is not after keyframe {}", i + 1, i));
+            }
+            if pair[1].sim_time < pair[0].sim_time {
+                return Err(format!("keyframe {} goes back in simulation time", i + 1));
+            }
+        }
+        Ok(())
+    }
+
+    /// Interpolated keyframe at `time`, clamped to the ends of the timeline.
+    pub fn sample(&self, time: f64) -> Option<Keyframe> {
+        let keys = &self.keyframes;
+        let (first, last) = (keys.first()?, keys.last()?);
+        if keys.len() == 1 || time <= first.time {
+            return Some(Keyframe { time, ..*first });
+        }
+        if time >= last.time {
+            return Some(Keyframe { time, ..*last });
+        }
+
+        // Unwrap the azimuth so each keyframe is within half a turn of the
+        // one before
+        let mut points: Vec<_> = keys.iter().map(params).collect();
+        for i in 1..points.len() {
+            let turn = points[i][1] - points[i - 1][1];
+            points[i][1] -= TAU * (turn / TAU).round();
+        }
+
+        // Segment k..k+1 containing `time`, with Catmull–Rom tangents from
+        // the neighbouring keyframes (one-sided at the ends).
+        let k = keys.partition_point(|key| key.time <= time) - 1;
+        let (a, b) = (&keys[k], &keys[k + 1]);
+        let tangent = |i: usize| {
+            let (lo, hi) = (i.saturating_sub(1), (i + 1).min(keys.len() - 1));
+            let (p0, p1) = (points[lo], points[hi]);
+            let dt = keys[hi].time - keys[lo].time;
+            std::array::from_fn::<f64, PARAMS, _>(|j| (p1[j] - p0[j]) / dt)
+        };
+        let (m0, m1) = (tangent(k), tangent(k + 1));
+        let h = b.time - a.time;
+        let s = (time - a.time) / h;
+
+        // Cubic Hermite basis
+        let s2 = s * s;
+        let s3 = s2 * s;
+        let h00 = 2.0 * s3 - 3.0 * s2 + 1.0;
+        let h10 = s3 - 2.0 * s2 + s;
+        let h01 = -2.0 * s3 + 3.0 * s2;
+        let h11 = s3 - s2;
+        let (pa, pb) = (points[k], points[k + 1]);
+        let p =
+            std::array::from_fn(|j| h00 * pa[j] + h10 * h * m0[j] + h01 * pb[j] + h11 * h * m1[j]);
+
+        Some(Keyframe {
+            time,
+            sim_time: a.sim_time + s * (b.sim_time - a.sim_time),
+            camera: from_params(p),
+        })
+    }
+}
+
+const PARAMS: usize = 7;
+
+/// Camera settings as one vector for interpolation.
+fn params(key: &Keyframe) -> [f64; PARAMS] {
+    let c = &key.camera;
+    [
+        c.distance,
+        c.azimuth,
+        c.elevation,
+        c.target[0],
+        c.target[1],
+        c.target[2],
+        c.fov,
+    ]
+}
+
+fn from_params(p: [f64; PARAMS]) -> CameraSettings {
+    CameraSettings {
+        distance: p[0],
+        azimuth: p[1],
+        elevation: p[2],
+        target: [p[3], p[4], p[5]],
+        fov: p[6],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(time: f64, sim_time: f64, distance: f64, azimuth: f64) -> Keyframe {
+        Keyframe {
+            time,
+            sim_time,
+            camera: CameraSettings {
+                distance,
+                azimuth,
+                ..CameraSettings::default()
+            },
+        }
+    }
+
+    #[test]
+    fn sampling_passes_through_keyframes_smoothly() {
+        let mut timeline = Timeline::default();
+        timeline.insert(key(4.0, 20.0, 10.0, 2.0));
+        timeline.insert(key(0.0, 0.0, 20.0, 0.0));
+        timeline.insert(key(2.0, 10.0, 12.0, 1.0));
+        timeline.validate().unwrap();
+        assert_eq!(timeline.duration(), 4.0);
+        assert_eq!(timeline.frame_count(30.0), 121);
+
+        for k in timeline.keyframes() {
+            let s = timeline.sample(k.time).unwrap();
+            assert!((s.camera.distance - k.camera.distance).abs() < 1e-12);
+            assert!((s.sim_time - k.sim_time).abs() < 1e-12);
+        }
+        // Linear keyframes stay linear; simulation time is linear in any case.
+        let mid = timeline.sample(3.0).unwrap();
+        assert!((mid.camera.azimuth - 1.5).abs() < 1e-12);
+        assert!((mid.sim_time - 15.0).abs() < 1e-12);
+
+        // No kink at the middle keyframe: equal slopes on either side.
+        let slope = |t0: f64, t1: f64| {
+            let (a, b) = (timeline.sample(t0).unwrap(), timeline.sample(t1).unwrap());
+            (b.camera.distance - a.camera.distance) / (t1 - t0)
+        };
+        let eps = 1e-5;
+        assert!((slope(2.0 - eps, 2.0) - slope(2.0, 2.0 + eps)).abs() < 1e-3);
+
+        // Clamped outside the timeline
+        assert_eq!(timeline.sample(9.0).unwrap().camera.distance, 10.0);
+    }
+
+    #[test]
+    fn azimuth_turns_the_short_way_round() {
+        let mut timeline = Timeline::default();
+        timeline.insert(key(0.0, 0.0, 10.0, 6.2));
+        timeline.insert(key(1.0, 0.0, 10.0, 0.1));
+        let mid = timeline.sample(0.5).unwrap().camera.azimuth;
+        let expected = 0.5 * (6.2 + 0.1 + TAU);
+        assert!((mid - expected).abs() < 1e-12, "{mid}");
+    }
+
+    #[test]
+    fn simulation_time_may_not_run_backwards() {
+        let mut timeline = Timeline::default();
+        timeline.insert(key(0.0, 5.0, 10.0, 0.0));
+        timeline.insert(key(1.0, 4.0, 10.0, 0.0));
+        assert!(timeline.validate().is_err());
+    }
+}
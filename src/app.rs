@@ -1,12 +1,16 @@
 use std::sync::Arc;
 
+use glam::Vec3;
 use winit::event::{ElementState, WindowEvent};
 use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::Window;
 
 use crate::renderer::camera::OrbitalCamera;
+use crate::renderer::environment::{EnvironmentMap, Projection};
+use crate::renderer::observer::ObserverFrame;
 use crate::renderer::pipeline::RayMarchPipeline;
-use crate::renderer::uniforms::Uniforms;
+use crate::renderer::stars::{SkyLocation, StarCatalog};
+use crate::renderer::uniforms::{IntegrationSettings, Uniforms};
 use crate::simulation::{Preset, Simulation};
 use crate::ui::{self, UiState};
 
@@ -19,14 +23,19 @@ pub struct App {
     camera: OrbitalCamera,
     simulation: Simulation,
     ui_state: UiState,
-    max_steps: u32,
-    step_size: f32,
+    integration: IntegrationSettings,
     egui_ctx: egui::Context,
     egui_winit: egui_winit::State,
     egui_renderer: egui_wgpu::Renderer,
     window: Arc<Window>,
     last_frame_time: std::time::Instant,
-    start_time: std::time::Instant,
+    /// Clock driving the disk animation; held while a still image accumulates.
+    animation_time: f32,
+    /// Loaded star catalogue and the sky location it was last binned for.
+    star_catalog: StarCatalog,
+    binned_sky: Option<SkyLocation>,
+    /// Layout of the uploaded background image.
+    environment_projection: Projection,
 }
 
 impl App {
@@ -107,14 +116,20 @@ impl App {
             ui_state: UiState::default(),
             // GPU cost: lower max_steps / higher step_size = less work per frame (fewer RK4 steps per ray).
             // On macOS we use low defaults to avoid main-thread block on present(); tune here to binary-search.
-            max_steps: if cfg!(target_os = "macos") { 80 } else { 600 },
-            step_size: if cfg!(target_os = "macos") { 0.4 } else { 0.1 },
+            integration: IntegrationSettings {
+                max_steps: if cfg!(target_os = "macos") { 80 } else { 600 },
+                step_size: if cfg!(target_os = "macos") { 0.4 } else { 0.1 },
+                ..Default::default()
+            },
             egui_ctx,
             egui_winit,
             egui_renderer,
             window,
             last_frame_time: std::time::Instant::now(),
-            start_time: std::time::Instant::now(),
+            animation_time: 0.0,
+            star_catalog: StarCatalog::default(),
+            binned_sky: None,
+            environment_projection: Projection::default(),
         }
     }
 
@@ -141,11 +156,29 @@ impl App {
                 true
             }
             WindowEvent::MouseInput { button, state, .. } => {
-                self.camera.handle_mouse_button(*button, *state);
+                if self.ui_state.placement.enabled {
+                    if let Some(body) = self.ui_state.placement.handle_mouse_button(*button, *state)
+                    {
+                        self.ui_state.selected_body = self.simulation.add_body(body);
+                    }
+                } else if self.ui_state.inspector.enabled {
+                    self.ui_state.inspector.handle_mouse_button(*button, *state);
+                } else {
+                    self.camera.handle_mouse_button(*button, *state);
+                }
                 true
             }
             WindowEvent::CursorMoved { position, .. } => {
                 self.camera.handle_mouse_move(position.x, position.y);
+                self.ui_state
+                    .inspector
+                    .handle_mouse_move(position.x, position.y);
+                self.ui_state.placement.handle_mouse_move(
+                    &self.camera,
+                    position.x as f32,
+                    position.y as f32,
+                    (self.config.width as f32, self.config.height as f32),
+                );
                 true
             }
             WindowEvent::MouseWheel { delta, .. } => {
@@ -160,6 +193,15 @@ impl App {
                     if key == KeyCode::F12 && event.state == ElementState::Pressed {
                         self.ui_state.screenshot_requested = true;
                     }
+                    if key == KeyCode::Delete
+                        && event.state == ElementState::Pressed
+                        && self.ui_state.selected_body < self.simulation.bodies.len()
+                    {
+                        self.simulation.remove_body(self.ui_state.selected_body);
+                    }
+                    if key == KeyCode::Escape && event.state == ElementState::Pressed {
+                        self.ui_state.placement.cancel();
+                    }
                     self.camera.handle_key(key, event.state);
                 }
                 true
@@ -168,6 +210,45 @@ impl App {
         }
     }
 
+    /// Load the UI's background image and upload it for the image background.
+    fn load_background_image(&mut self) {
+        let path = std::path::PathBuf::from(&self.ui_state.background_image);
+        self.ui_state.background_image_status = Some(match EnvironmentMap::load(&path) {
+            Ok(map) => {
+                self.pipeline
+                    .set_environment(&self.device, &self.queue, Some(&map));
+                self.environment_projection = map.projection;
+                format!(
+                    "Loaded {} ({}×{}{})",
+                    path.display(),
+                    map.width(),
+                    map.height(),
+                    if map.projection == Projection::Cubemap {
+                        " cubemap"
+                    } else {
+                        ""
+                    }
+                )
+            }
+            Err(e) => format!("Load failed: {}", e),
+        });
+    }
+
+    /// Load the UI's star catalogue; it is binned for the GPU on the next
+    /// frame.
+    fn load_star_catalog(&mut self) {
+        let path = std::path::PathBuf::from(&self.ui_state.star_catalog);
+        self.ui_state.star_catalog_status = Some(match StarCatalog::load(&path) {
+            Ok(catalog) => {
+                let status = format!("Loaded {} stars", catalog.stars.len());
+                self.star_catalog = catalog;
+                self.binned_sky = None;
+                status
+            }
+            Err(e) => format!("Load failed: {}", e),
+        });
+    }
+
     pub fn render(&mut self) {
         let now = std::time::Instant::now();
         let dt = (now - self.last_frame_time).as_secs_f32();
@@ -180,46 +261,76 @@ impl App {
 
         // Upload body data
         let gpu_bodies = self.simulation.gpu_bodies();
-        self.pipeline.update_bodies(&self.queue, &gpu_bodies);
+        self.pipeline
+            .update_bodies(&self.device, &self.queue, &gpu_bodies);
+
+        // A moving observer follows body 0; without one, or around a
+        // spinning one, it stays static
+        let observer = &mut self.ui_state.observer;
+        let frame = match gpu_bodies.first() {
+            Some(body) if observer.motion.supports_spin(body.spin) => {
+                if !self.simulation.paused {
+                    observer.advance(dt as f64, body.rs as f64);
+                }
+                let centre = Vec3::new(body.position[0], body.position[1], body.position[2]);
+                observer.frame(&self.camera, centre, body.rs)
+            }
+            _ => ObserverFrame::at_rest(&self.camera),
+        };
+        let to4 = |v: Vec3| [v.x, v.y, v.z, 0.0];
 
         // Update uniforms
-        let uniforms = Uniforms {
-            camera_pos: [
-                self.camera.position().x,
-                self.camera.position().y,
-                self.camera.position().z,
-                0.0,
-            ],
-            camera_forward: [
-                self.camera.forward().x,
-                self.camera.forward().y,
-                self.camera.forward().z,
-                0.0,
-            ],
-            camera_up: [
-                self.camera.up().x,
-                self.camera.up().y,
-                self.camera.up().z,
-                0.0,
-            ],
-            camera_right: [
-                self.camera.right().x,
-                self.camera.right().y,
-                self.camera.right().z,
-                0.0,
-            ],
+        let mut uniforms = Uniforms {
+            camera_pos: to4(frame.position),
+            camera_forward: to4(frame.forward),
+            camera_up: to4(frame.up),
+            camera_right: to4(frame.right),
+            observer_velocity: to4(frame.velocity),
             resolution: [self.config.width as f32, self.config.height as f32],
             fov: self.camera.fov,
             num_bodies: self.simulation.bodies.len() as u32,
-            max_steps: self.max_steps,
-            step_size: self.step_size,
+            max_steps: self.integration.max_steps,
+            step_size: self.integration.step_size,
             disk_enabled: if self.ui_state.disk_enabled { 1 } else { 0 },
             background_mode: self.ui_state.background_mode,
-            time: self.start_time.elapsed().as_secs_f32(),
+            environment_projection: self.environment_projection as u32,
+            time: self.animation_time,
             grid_enabled: if self.ui_state.grid_enabled { 1 } else { 0 },
-            _padding: [0.0; 2],
+            geodesic_model: self
+                .simulation
+                .geodesic_model(self.ui_state.exact_geodesics) as u32,
+            integrator: self.integration.integrator as u32,
+            tolerance: self.integration.tolerance,
+            min_step: self.integration.min_step,
+            max_step: self.integration.max_step,
+            body_cutoff: self.integration.body_cutoff,
+            disk_model: self.ui_state.disk.model as u32,
+            disk_scale_height: self.ui_state.disk.scale_height,
+            disk_optical_depth: self.ui_state.disk.optical_depth,
+            doppler_beaming: self.ui_state.disk.doppler_beaming as u32,
+            gravitational_redshift: self.ui_state.disk.gravitational_redshift as u32,
+            intensity_exponent: self.ui_state.disk.intensity_exponent(),
+            render_mode: self.ui_state.render_mode as u32,
+            ..Default::default()
         };
+        // A paused scene seen from a still camera accumulates jittered
+        // samples; anything else traces one sample per frame as before.
+        let accumulate = self.ui_state.accumulate && self.simulation.paused;
+        let trace = self
+            .pipeline
+            .next_sample(&mut uniforms, &gpu_bodies, accumulate);
+        self.ui_state.accumulated_samples = self.pipeline.samples();
+        if self.ui_state.accumulated_samples <= 1 {
+            self.animation_time += dt;
+        }
         self.pipeline.update_uniforms(&self.queue, &uniforms);
+        let post = self.ui_state.post.for_mode(self.ui_state.render_mode);
+        self.pipeline.update_post(&self.queue, &post);
+        self.ui_state.inspector.update(
+            &uniforms,
+            &gpu_bodies,
+            (self.config.width, self.config.height),
+        );
 
         // Get surface texture
         let output = match self.surface.get_current_texture() {
@@ -245,14 +356,28 @@ impl App {
                 &mut self.ui_state,
                 &mut self.simulation,
                 &mut self.camera,
-                &mut self.max_steps,
-                &mut self.step_size,
+                &mut self.integration,
             );
         });
 
         self.egui_winit
             .handle_platform_output(&self.window, full_output.platform_output);
 
+        if self.ui_state.background_image_requested {
+            self.ui_state.background_image_requested = false;
+            self.load_background_image();
+        }
+        if self.ui_state.star_catalog_requested {
+            self.ui_state.star_catalog_requested = false;
+            self.load_star_catalog();
+        }
+        // Re-bin the stars when the black hole moves on the sky.
+        if self.binned_sky != Some(self.ui_state.sky) {
+            let bins = self.star_catalog.bin(self.ui_state.sky);
+            self.pipeline.set_stars(&self.device, &bins);
+            self.binned_sky = Some(self.ui_state.sky);
+        }
+
         let paint_jobs = self
             .egui_ctx
             .tessellate(full_output.shapes, full_output.pixels_per_point);
@@ -282,14 +407,21 @@ impl App {
                 label: Some("Main Encoder"),
             });
 
-        self.pipeline.dispatch_compute(&mut encoder);
+        if trace {
+            self.pipeline.dispatch_compute(&mut encoder);
+        }
+        self.pipeline.dispatch_post(&mut encoder);
         self.pipeline.render_fullscreen(&mut encoder, &view);
 
         self.queue.submit(std::iter::once(encoder.finish()));
 
         if self.ui_state.screenshot_requested {
             self.ui_state.screenshot_requested = false;
-            self.pipeline.capture_screenshot(&self.device, &self.queue);
+            self.pipeline.capture_screenshot(
+                &self.device,
+                &self.queue,
+                self.ui_state.screenshot_format,
+            );
         }
 
         let mut egui_encoder = self
@@ -0,0 +1,379 @@
+//! Gravitational-wave strain from the quadrupole formula, recorded while the
+//! simulation runs, with CSV and audio (WAV) export.
+//!
+//! Only the last [`LIVE_SAMPLES`] samples are kept for the live plot; the
+//! full history is kept only while a recording for export is running.
+//!
+//! With G = c = 1 the transverse-traceless strain at distance D is
+//! h_ij = (2/D) Ï_ij^TT, where I_ij = Σ m x_i x_j. Strains are stored as D·h,
+//! which does not depend on the (arbitrary) observer distance.
+
+use std::collections::VecDeque;
+use std::f64::consts::TAU;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use glam::{DVec3, Vec3};
+
+use crate::simulation::Body;
+
+/// Direction to the observer, in spherical angles about the +y axis.
+#[derive(Debug, Clone, Copy)]
+pub struct Observer {
+    /// Angle from +y in radians: 0 sees the presets' orbits face-on.
+    pub inclination: f32,
+    /// Angle around +y from +x, in radians.
+    pub azimuth: f32,
+}
+
+impl Default for Observer {
+    fn default() -> Self {
+        Self {
+            inclination: 0.0,
+            azimuth: 0.0,
+        }
+    }
+}
+
+impl Observer {
+    /// Unit vector from the source toward the observer.
+    pub fn direction(&self) -> DVec3 {
+        let (i, a) = (self.inclination as f64, self.azimuth as f64);
+        DVec3::new(i.sin() * a.cos(), i.cos(), i.sin() * a.sin())
+    }
+
+    /// Polarisation basis (p, q) transverse to the line of sight.
+    fn polarization_basis(&self) -> (DVec3, DVec3) {
+        let n = self.direction();
+        let p = n.cross(DVec3::Y).try_normalize().unwrap_or(DVec3::X);
+        (p, n.cross(p))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StrainSample {
+    pub time: f64,
+    /// D·h+
+    pub h_plus: f64,
+    /// D·h×
+    pub h_cross: f64,
+}
+
+/// h+ and h× (times distance) seen by `observer`, given each body's state
+/// and acceleration.
+pub fn quadrupole_strain(
+    bodies: &[Body],
+    accelerations: &[Vec3],
+    observer: &Observer,
+) -> (f64, f64) {
+    let (p, q) = observer.polarization_basis();
+    // Ï_ab = Σ m (a_a x_b + 2 v_a v_b + x_a a_b), contracted with a, b ∈ {p, q}
+    let ddot = |a: DVec3, b: DVec3| -> f64 {
+        bodies
+            .iter()
+            .zip(accelerations)
+            .map(|(body, accel)| {
+                let (x, v, acc) = (
+                    body.position.as_dvec3(),
+                    body.velocity.as_dvec3(),
+                    accel.as_dvec3(),
+                );
+                body.mass() as f64
+                    * (acc.dot(a) * x.dot(b) + 2.0 * v.dot(a) * v.dot(b) + x.dot(a) * acc.dot(b))
+            })
+            .sum()
+    };
+    (ddot(p, p) - ddot(q, q), 2.0 * ddot(p, q))
+}
+
+/// Number of recent samples kept for the live plot.
+pub const LIVE_SAMPLES: usize = 4096;
+
+/// Strain of the running simulation: a bounded window of recent samples,
+/// plus the full history while recording for export.
+#[derive(Debug, Default)]
+pub struct StrainRecorder {
+    pub observer: Observer,
+    recent: VecDeque<StrainSample>,
+    recording: Option<Vec<StrainSample>>,
+}
+
+impl StrainRecorder {
+    pub fn record(&mut self, time: f64, bodies: &[Body], accelerations: &[Vec3]) {
+        let (h_plus, h_cross) = quadrupole_strain(bodies, accelerations, &self.observer);
+        self.push(StrainSample {
+            time,
+            h_plus,
+            h_cross,
+        });
+    }
+
+    fn push(&mut self, sample: StrainSample) {
+        if self.recent.len() == LIVE_SAMPLES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(sample);
+        if let Some(recording) = &mut self.recording {
+            recording.push(sample);
+        }
+    }
+
+    /// The last [`LIVE_SAMPLES`] samples, oldest first.
+    pub fn recent(&self) -> &VecDeque<StrainSample> {
+        &self.recent
+    }
+
+    /// Start keeping every sample for export, or stop and drop them.
+    pub fn set_recording(&mut self, recording: bool) {
+        if recording != self.is_recording() {
+            self.recording = recording.then(Vec::new);
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Samples recorded for export so far.
+    pub fn recorded(&self) -> &[StrainSample] {
+        self.recording.as_deref().unwrap_or_default()
+    }
+
+    /// Forget all samples; a running recording continues from empty.
+    pub fn clear(&mut self) {
+        self.recent.clear();
+        if let Some(recording) = &mut self.recording {
+            recording.clear();
+        }
+    }
+
+    fn require_recording(&self) -> io::Result<&[StrainSample]> {
+        self.recording.as_deref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "strain is not being recorded")
+        })
+    }
+
+    /// Write the recording as `time,h_plus,h_cross` CSV.
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        let samples = self.require_recording()?;
+        let mut out = io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(out, "time,h_plus,h_cross")?;
+        for s in samples {
+            writeln!(out, "{},{:e},{:e}", s.time, s.h_plus, s.h_cross)?;
+        }
+        out.flush()
+    }
+
+    /// Sonify the recorded h+ as a 16-bit mono WAV: the simulation time up
+    /// to the strain peak is squeezed into `duration` seconds and the wave's
+    /// frequency scaled so the peak sounds at `peak_frequency` Hz, keeping
+    /// the chirp's amplitude envelope and frequency ratios.
+    pub fn write_wav(&self, path: &Path, duration: f64, peak_frequency: f64) -> io::Result<()> {
+        let samples = self.require_recording()?;
+        let audio = sonify(samples, duration, peak_frequency).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not enough strain samples to sonify",
+            )
+        })?;
+        write_wav(path, SAMPLE_RATE, &audio)
+    }
+}
+
+fn sonify(samples: &[StrainSample], duration: f64, peak_frequency: f64) -> Option<Vec<f32>> {
+    // Track frequency and amplitude of h+ half-cycle by half-cycle: each
+    // pair of zero crossings gives one (time, frequency, amplitude) point.
+    let mut track: Vec<(f64, f64, f64)> = Vec::new();
+    let mut last_crossing = None;
+    let mut amplitude: f64 = 0.0;
+    for pair in samples.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        amplitude = amplitude.max(b.h_plus.abs());
+        if a.h_plus * b.h_plus < 0.0 {
+            let w = a.h_plus / (a.h_plus - b.h_plus);
+            let crossing = a.time + w * (b.time - a.time);
+            if let Some(previous) = last_crossing {
+                let half_period: f64 = crossing - previous;
+                let omega = TAU / (2.0 * half_period.max(1e-9));
+                track.push((0.5 * (previous + crossing), omega, amplitude));
+            }
+            last_crossing = Some(crossing);
+            amplitude = 0.0;
+        }
+    }
+    let peak = (0..track.len()).max_by(|&a, &b| track[a].2.total_cmp(&track[b].2))?;
+    if peak < 2 {
+        return None;
+    }
+    let track = &track[..=peak];
+    let (t0, t1) = (track[0].0, track[peak].0);
+    let (peak_omega, peak_amplitude) = (track[peak].1, track[peak].2);
+    let scale = peak_frequency * TAU / peak_omega;
+
+    // Resample onto the audio clock, integrating the scaled frequency
+    let count = (duration * SAMPLE_RATE as f64) as usize;
+    let ringdown = count / 20;
+    let mut audio = Vec::with_capacity(count + ringdown);
+    let (mut audio_phase, mut j) = (0.0, 0);
+    for n in 0..count {
+        let t = t0 + (t1 - t0) * n as f64 / count as f64;
+        while j + 1 < peak && track[j + 1].0 < t {
+            j += 1;
+        }
+        let (a, b) = (track[j], track[j + 1]);
+        let w = ((t - a.0) / (b.0 - a.0)).clamp(0.0, 1.0);
+        let omega = a.1 + w * (b.1 - a.1);
+        let amp = a.2 + w * (b.2 - a.2);
+        audio_phase += omega * scale / SAMPLE_RATE as f64;
+        audio.push((amp / peak_amplitude * audio_phase.sin()) as f32);
+    }
+    // Short exponential ringdown at the peak frequency
+    for n in 0..ringdown {
+        audio_phase += peak_frequency * TAU / SAMPLE_RATE as f64;
+        let decay = (-5.0 * n as f64 / ringdown as f64).exp();
+        audio.push((decay * audio_phase.sin()) as f32);
+    }
+    Some(audio)
+}
+
+const SAMPLE_RATE: u32 = 44_100;
+
+/// Default length of the exported chirp, in seconds.
+pub const WAV_DURATION: f64 = 3.0;
+/// Default pitch of the strain peak in the exported chirp, in Hz.
+pub const WAV_PEAK_FREQUENCY: f64 = 400.0;
+
+/// Timestamped path for an export from the app, e.g. `strain/strain_<ms>.csv`.
+pub fn export_path(extension: &str) -> io::Result<PathBuf> {
+    let dir = PathBuf::from("strain");
+    std::fs::create_dir_all(&dir)?;
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    Ok(dir.join(format!("strain_{}.{}", timestamp, extension)))
+}
+
+/// Write samples in [-1, 1] as a 16-bit PCM mono WAV file.
+fn write_wav(path: &Path, sample_rate: u32, samples: &[f32]) -> io::Result<()> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut out = io::BufWriter::new(std::fs::File::create(path)?);
+    out.write_all(b"RIFF")?;
+    out.write_all(&(36 + data_len).to_le_bytes())?;
+    out.write_all(b"WAVEfmt ")?;
+    out.write_all(&16u32.to_le_bytes())?;
+    out.write_all(&1u16.to_le_bytes())?; // PCM
+    out.write_all(&1u16.to_le_bytes())?; // mono
+    out.write_all(&sample_rate.to_le_bytes())?;
+    out.write_all(&(sample_rate * 2).to_le_bytes())?;
+    out.write_all(&2u16.to_le_bytes())?;
+    out.write_all(&16u16.to_le_bytes())?;
+    out.write_all(b"data")?;
+    out.write_all(&data_len.to_le_bytes())?;
+    for &s in samples {
+        let v = (s.clamp(-1.0, 1.0) * 0.9 * i16::MAX as f32) as i16;
+        out.write_all(&v.to_le_bytes())?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Newtonian circular binary of two masses rs/2 with separation `r` along
+    /// the x axis, orbiting in the xz-plane.
+    fn circular_binary(rs: f32, r: f32) -> (Vec<Body>, Vec<Vec3>, f64) {
+        let m = rs as f64;
+        let omega = (m / (r as f64).powi(3)).sqrt();
+        let v = (omega * r as f64 / 2.0) as f32;
+        let a = (omega * omega * r as f64 / 2.0) as f32;
+        let bodies = vec![
+            Body::new(Vec3::new(r / 2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, v), rs),
+            Body::new(Vec3::new(-r / 2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -v), rs),
+        ];
+        (
+            bodies,
+            vec![Vec3::new(-a, 0.0, 0.0), Vec3::new(a, 0.0, 0.0)],
+            omega,
+        )
+    }
+
+    #[test]
+    fn circular_binary_strain_matches_quadrupole_amplitude() {
+        let (rs, r) = (0.5_f32, 10.0_f32);
+        let (bodies, accels, omega) = circular_binary(rs, r);
+        // D·h = 4 μ ω² r² face-on, with μ = m/4 for equal masses
+        let mu = rs as f64 / 4.0;
+        let expected = 4.0 * mu * omega * omega * (r as f64).powi(2);
+
+        let face_on = Observer::default();
+        let (h_plus, h_cross) = quadrupole_strain(&bodies, &accels, &face_on);
+        assert!((h_plus.hypot(h_cross) - expected).abs() < 1e-5 * expected);
+
+        // Edge-on the cross polarisation vanishes and h+ peaks at half the
+        // face-on amplitude when the separation is transverse.
+        let edge_on = Observer {
+            inclination: std::f32::consts::FRAC_PI_2,
+            azimuth: std::f32::consts::FRAC_PI_2,
+        };
+        let (h_plus, h_cross) = quadrupole_strain(&bodies, &accels, &edge_on);
+        assert!(h_cross.abs() < 1e-6 * expected);
+        assert!((h_plus.abs() - 0.5 * expected).abs() < 1e-5 * expected);
+    }
+
+    #[test]
+    fn live_window_is_bounded_and_full_history_only_kept_while_recording() {
+        let (bodies, accels, _) = circular_binary(0.5, 10.0);
+        let mut recorder = StrainRecorder::default();
+        for i in 0..LIVE_SAMPLES + 10 {
+            recorder.record(i as f64, &bodies, &accels);
+        }
+        assert_eq!(recorder.recent().len(), LIVE_SAMPLES);
+        assert_eq!(recorder.recent()[0].time, 10.0);
+        assert!(recorder.recorded().is_empty());
+        assert!(recorder.write_csv(Path::new("unused.csv")).is_err());
+
+        recorder.set_recording(true);
+        for i in 0..2 * LIVE_SAMPLES {
+            recorder.record(i as f64, &bodies, &accels);
+        }
+        assert_eq!(recorder.recent().len(), LIVE_SAMPLES);
+        assert_eq!(recorder.recorded().len(), 2 * LIVE_SAMPLES);
+
+        recorder.set_recording(false);
+        assert!(recorder.recorded().is_empty());
+    }
+
+    #[test]
+    fn sonified_chirp_rises_to_the_requested_pitch() {
+        // Synthetic chirp: frequency and amplitude grow towards t = 100.
+        let mut samples = Vec::new();
+        let mut phase = 0.0;
+        for i in 0..20_000 {
+            let t = i as f64 * 0.005;
+            let omega = 2.0 + 0.2 * t;
+            phase += omega * 0.005;
+            let amp = 1.0 + 0.02 * t;
+            samples.push(StrainSample {
+                time: t,
+                h_plus: amp * phase.cos(),
+                h_cross: amp * phase.sin(),
+            });
+        }
+
+        let audio = sonify(&samples, 1.0, 440.0).unwrap();
+        assert_eq!(audio.len(), 44_100 + 44_100 / 20);
+        assert!(audio.iter().all(|s| s.abs() <= 1.0));
+
+        // Count zero crossings in the first and last tenth of the chirp
+        let crossings = |chunk: &[f32]| chunk.windows(2).filter(|w| w[0] * w[1] < 0.0).count();
+        let (early, late) = (crossings(&audio[..4410]), crossings(&audio[39_690..44_100]));
+        // ω rises 2 → 22, so the pitch rises ~11× to 440 Hz (88 crossings / 0.1 s)
+        assert!(
+            (late as f64 - 88.0).abs() < 6.0,
+            "{late} crossings at the peak"
+        );
+        assert!(late > 5 * early, "{early} → {late} crossings");
+    }
+}
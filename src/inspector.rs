@@ -0,0 +1,234 @@
+//! Pixel inspector: click a pixel to re-trace its ray with the CPU reference
+//! tracer and see where it went, how close it came to each body and how
+//! often it wound around them.
+
+use std::f64::consts::TAU;
+
+use glam::DVec3;
+use winit::event::{ElementState, MouseButton};
+
+use crate::metrics;
+use crate::renderer::reference::{RayTrace, ReferenceTracer};
+use crate::renderer::uniforms::Uniforms;
+use crate::simulation::GpuBody;
+
+/// Turns around a body are counted while the ray is within this many times
+/// the radius of its outermost (retrograde) photon orbit.
+const ORBIT_ZONE: f64 = 2.0;
+
+/// How an inspected ray passed one body.
+#[derive(Debug, Clone, Copy)]
+pub struct BodyPass {
+    /// Distance of the unbent camera ray from the body's centre.
+    pub impact_parameter: f64,
+    /// Smallest distance of the traced path from the body's centre.
+    pub closest_approach: f64,
+    /// Turns the ray made around the body while near its photon orbits.
+    pub orbits: f64,
+    pub rs: f64,
+    /// Radius of the body's prograde photon orbit, 1.5 rs without spin.
+    pub photon_sphere: f64,
+}
+
+/// A re-traced pixel and the path of its ray.
+pub struct Inspection {
+    pub pixel: (u32, u32),
+    /// Size of the image the pixel belongs to.
+    pub size: (u32, u32),
+    pub trace: RayTrace,
+    /// The camera position, then the position after every step.
+    pub path: Vec<DVec3>,
+    /// Camera basis when the ray was traced, for the side view.
+    pub forward: DVec3,
+    pub up: DVec3,
+    /// Bodies as they were when the ray was traced.
+    pub bodies: Vec<GpuBody>,
+    /// How the ray passed each of `bodies`.
+    pub passes: Vec<BodyPass>,
+}
+
+impl Inspection {
+    /// Trace the ray through the centre of `pixel` in a `size` image rendered
+    /// with `uniforms`.
+    pub fn trace(
+        uniforms: &Uniforms,
+        bodies: &[GpuBody],
+        pixel: (u32, u32),
+        size: (u32, u32),
+    ) -> Self {
+        let uniforms = Uniforms {
+            jitter: [0.0; 2],
+            ..*uniforms
+        };
+        let tracer = ReferenceTracer::new(&uniforms, bodies);
+        let origin = vec3(uniforms.camera_pos);
+        let dir = tracer.pixel_ray(pixel.0, pixel.1, size.0, size.1);
+        let (trace, path) = tracer.trace_path(origin, dir);
+
+        let bodies = bodies[..(uniforms.num_bodies as usize).min(bodies.len())].to_vec();
+        let passes = bodies
+            .iter()
+            .map(|body| body_pass(body, origin, dir, &path))
+            .collect();
+        Self {
+            pixel,
+            size,
+            trace,
+            path,
+            forward: vec3(uniforms.camera_forward),
+            up: vec3(uniforms.camera_up),
+            bodies,
+            passes,
+        }
+    }
+}
+
+fn body_pass(body: &GpuBody, origin: DVec3, dir: DVec3, path: &[DVec3]) -> BodyPass {
+    let center = vec3(body.position);
+    let rs = body.rs as f64;
+    let spin = body.spin as f64;
+    let photon_sphere = metrics::black_hole(rs, spin).photon_sphere_radius();
+    let retrograde = metrics::black_hole(rs, -spin).photon_sphere_radius();
+    let closest_approach = path
+        .iter()
+        .map(|p| (*p - center).length())
+        .fold(f64::INFINITY, f64::min);
+
+    // Angle swept about the centre by each step taken inside the zone
+    let zone = ORBIT_ZONE * photon_sphere.max(retrograde);
+    let swept: f64 = path
+        .windows(2)
+        .map(|step| (step[0] - center, step[1] - center))
+        .filter(|(a, b)| a.length() < zone && b.length() < zone)
+        .map(|(a, b)| a.cross(b).length().atan2(a.dot(b)))
+        .sum();
+
+    BodyPass {
+        impact_parameter: (center - origin).cross(dir).length(),
+        closest_approach,
+        orbits: swept / TAU,
+        rs,
+        photon_sphere,
+    }
+}
+
+fn vec3(v: [f32; 4]) -> DVec3 {
+    DVec3::new(v[0] as f64, v[1] as f64, v[2] as f64)
+}
+
+#[derive(Default)]
+pub struct PixelInspector {
+    /// While enabled, left clicks inspect pixels instead of orbiting the camera.
+    pub enabled: bool,
+    /// Cursor position in physical pixels.
+    cursor: Option<(f64, f64)>,
+    /// Pixel clicked and not yet traced.
+    requested: Option<(u32, u32)>,
+    /// The last pixel traced.
+    pub inspection: Option<Inspection>,
+}
+
+impl PixelInspector {
+    /// Track the cursor, at (x, y) in physical pixels.
+    pub fn handle_mouse_move(&mut self, x: f64, y: f64) {
+        self.cursor = Some((x, y));
+    }
+
+    /// Pick the pixel under the cursor on a left click.
+    pub fn handle_mouse_button(&mut self, button: MouseButton, state: ElementState) {
+        if button == MouseButton::Left && state == ElementState::Pressed {
+            self.requested = self
+                .cursor
+                .map(|(x, y)| (x.max(0.0) as u32, y.max(0.0) as u32));
+        }
+    }
+
+    /// Trace the clicked pixel, if any, of a `size` image rendered with
+    /// `uniforms`.
+    pub fn update(&mut self, uniforms: &Uniforms, bodies: &[GpuBody], size: (u32, u32)) {
+        if let Some((x, y)) = self.requested.take() {
+            let pixel = (x.min(size.0 - 1), y.min(size.1 - 1));
+            self.inspection = Some(Inspection::trace(uniforms, bodies, pixel, size));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::kerr::KerrParams;
+    use crate::metrics::schwarzschild::SchwarzschildParams;
+    use crate::metrics::{GeodesicModel, Metric};
+    use crate::renderer::reference::RayFate;
+    use crate::renderer::uniforms::Integrator;
+    use crate::simulation::{Preset, Simulation};
+
+    /// Inspect a single-pixel image whose ray starts at x = `b`, far down -z,
+    /// and heads along +z past a hole of rs = 1 at the origin.
+    fn inspect_offset(b: f64) -> Inspection {
+        inspect_spinning(0.0, GeodesicModel::PseudoNewtonian, b)
+    }
+
+    /// `inspect_offset` past a hole of spin `spin`, traced with `model`.
+    fn inspect_spinning(spin: f32, model: GeodesicModel, b: f64) -> Inspection {
+        let mut sim = Simulation::new(Preset::Single);
+        sim.bodies[0].spin = spin;
+        let bodies = sim.gpu_bodies().to_vec();
+        let uniforms = Uniforms {
+            camera_pos: [b as f32, 0.0, -45.0, 0.0],
+            camera_forward: [0.0, 0.0, 1.0, 0.0],
+            camera_up: [0.0, 1.0, 0.0, 0.0],
+            camera_right: [-1.0, 0.0, 0.0, 0.0],
+            num_bodies: 1,
+            max_steps: 20_000,
+            step_size: 0.01,
+            disk_enabled: 0,
+            geodesic_model: model as u32,
+            integrator: Integrator::FixedRk4 as u32,
+            ..Default::default()
+        };
+        Inspection::trace(&uniforms, &bodies, (0, 0), (1, 1))
+    }
+
+    #[test]
+    fn distant_ray_passes_without_winding() {
+        let inspection = inspect_offset(6.0);
+        let pass = inspection.passes[0];
+        assert_eq!(inspection.trace.fate, RayFate::Escaped);
+        assert!((pass.impact_parameter - 6.0).abs() < 1e-4);
+        // Bent towards the hole, so it comes a little closer than b
+        assert!(pass.closest_approach < 6.0 && pass.closest_approach > 4.5);
+        assert_eq!(pass.orbits, 0.0);
+        assert_eq!(inspection.path[0], DVec3::new(6.0, 0.0, -45.0));
+    }
+
+    #[test]
+    fn ray_grazing_the_photon_sphere_winds_around_it() {
+        let schwarzschild = SchwarzschildParams { rs: 1.0 };
+        let b_crit = schwarzschild.critical_impact_parameter();
+        let pass = inspect_offset(1.001 * b_crit).passes[0];
+        assert_eq!(pass.photon_sphere, schwarzschild.photon_sphere_radius());
+        assert!(
+            (pass.closest_approach - pass.photon_sphere).abs() < 0.1,
+            "{}",
+            pass.closest_approach
+        );
+        assert!(pass.orbits > 0.75, "{}", pass.orbits);
+    }
+
+    #[test]
+    fn spinning_hole_winds_rays_around_its_own_photon_orbit() {
+        let kerr = KerrParams { rs: 1.0, spin: 0.5 };
+        let r_ph = kerr.photon_sphere_radius();
+        // The ray's angular momentum is along -y for b < 0, with the spin.
+        let b = -1.001 * kerr.critical_impact_parameter();
+        let pass = inspect_spinning(0.5, GeodesicModel::KerrSchild, b).passes[0];
+        assert_eq!(pass.photon_sphere, r_ph);
+        assert!(r_ph < 1.2);
+        // In the equatorial plane the Kerr–Schild r is √(R² - a²).
+        let a = kerr.a();
+        let closest_r = (pass.closest_approach.powi(2) - a * a).sqrt();
+        assert!((closest_r - r_ph).abs() < 0.1, "{closest_r} vs {r_ph}");
+        assert!(pass.orbits > 0.75, "{}", pass.orbits);
+    }
+}
@@ -1,6 +1,13 @@
+mod animation;
 mod app;
+mod gravitational_waves;
+mod inspector;
 mod metrics;
+mod placement;
+mod post_newtonian;
+mod remnant;
 mod renderer;
+mod scene;
 mod screenshot;
 mod simulation;
 mod ui;
@@ -44,12 +51,9 @@ impl ApplicationHandler for SpacetimeApp {
 
         let Some(app) = &mut self.app else { return };
 
-        match &event {
-            WindowEvent::RedrawRequested => {
-                app.render();
-                return;
-            }
-            _ => {}
+        if matches!(&event, WindowEvent::RedrawRequested) {
+            app.render();
+            return;
         }
 
         app.handle_window_event(&event);
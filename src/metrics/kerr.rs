@@ -0,0 +1,109 @@
+use super::{kerr_schild_inverse, kerr_schild_metric, Event, GeodesicModel, Metric, Tensor2};
+
+/// Parameters for the Kerr metric of a rotating black hole.
+///
+/// In Boyer–Lindquist coordinates with M = rs/2 and a = χM:
+/// ds² = -(1 - rs r/Σ)dt² - (2 rs a r sin²θ/Σ)dt dφ + (Σ/Δ)dr² + Σ dθ²
+///       + (r² + a² + rs a² r sin²θ/Σ) sin²θ dφ²
+/// with Σ = r² + a² cos²θ and Δ = r² - rs r + a².
+///
+/// As a `Metric` it is evaluated in Cartesian Kerr–Schild form with the spin
+/// along +y: g_μν = η_μν + f l_μ l_ν, f = rs r³/(r⁴ + a² y²). The local frame
+/// (X, Y, Z) = (z, x, y) is the textbook one with Z as the axis.
+pub struct KerrParams {
+    /// Schwarzschild radius rs = 2M
+    pub rs: f64,
+    /// Dimensionless spin χ = a/M in (-1, 1); negative values are retrograde
+    /// with respect to the accretion disk.
+    pub spin: f64,
+}
+
+impl Default for KerrParams {
+    fn default() -> Self {
+        Self { rs: 1.0, spin: 0.0 }
+    }
+}
+
+impl KerrParams {
+    /// Spin parameter a = χM in length units.
+    pub fn a(&self) -> f64 {
+        self.spin * self.mass()
+    }
+
+    fn field(&self, x: &Event) -> (f64, [f64; 3]) {
+        let a = self.a();
+        let r = self.radius(x);
+        let (big_x, big_y, big_z) = (x[3], x[1], x[2]);
+        let f = self.rs * r * r * r / (r * r * r * r + a * a * big_z * big_z);
+        let denom = r * r + a * a;
+        let lx = (r * big_x + a * big_y) / denom;
+        let ly = (r * big_y - a * big_x) / denom;
+        let lz = big_z / r;
+        // Back from (X, Y, Z) to world (x, y, z) = (Y, Z, X)
+        (f, [ly, lz, lx])
+    }
+}
+
+impl Metric for KerrParams {
+    fn name(&self) -> &'static str {
+        "Kerr"
+    }
+
+    fn mass(&self) -> f64 {
+        0.5 * self.rs
+    }
+
+    fn metric_tensor(&self, x: &Event) -> Tensor2 {
+        let (f, l) = self.field(x);
+        kerr_schild_metric(f, l)
+    }
+
+    fn inverse_metric(&self, x: &Event) -> Tensor2 {
+        let (f, l) = self.field(x);
+        kerr_schild_inverse(f, l)
+    }
+
+    /// Boyer–Lindquist r from r⁴ - (R² - a²) r² - a² y² = 0.
+    fn radius(&self, x: &Event) -> f64 {
+        let a = self.a();
+        let big_r2 = x[1] * x[1] + x[2] * x[2] + x[3] * x[3];
+        let b = 0.5 * (big_r2 - a * a);
+        (b + (b * b + a * a * x[2] * x[2]).sqrt()).max(0.0).sqrt()
+    }
+
+    /// Outer event horizon: r+ = M + √(M² - a²)
+    fn horizon_radius(&self) -> f64 {
+        let m = self.mass();
+        let a = self.a();
+        m + (m * m - a * a).max(0.0).sqrt()
+    }
+
+    /// Equatorial photon orbit co-rotating with the disk:
+    /// r = 2M(1 + cos(2/3 · acos(-χ)))
+    fn photon_sphere_radius(&self) -> f64 {
+        let chi = self.spin.clamp(-1.0, 1.0);
+        2.0 * self.mass() * (1.0 + (2.0 / 3.0 * (-chi).acos()).cos())
+    }
+
+    /// Prograde equatorial critical impact parameter:
+    /// b = -a + 6M cos(1/3 · acos(-χ))
+    fn critical_impact_parameter(&self) -> f64 {
+        let chi = self.spin.clamp(-1.0, 1.0);
+        -self.a() + 6.0 * self.mass() * ((-chi).acos() / 3.0).cos()
+    }
+
+    /// Innermost stable circular orbit for a disk rotating in the +y sense
+    /// (Bardeen, Press & Teukolsky 1972). Equals 3 rs at zero spin.
+    fn isco_radius(&self) -> f64 {
+        let m = self.mass();
+        let chi = self.spin.clamp(-0.9999, 0.9999);
+        let z1 = 1.0 + (1.0 - chi * chi).cbrt() * ((1.0 + chi).cbrt() + (1.0 - chi).cbrt());
+        let z2 = (3.0 * chi * chi + z1 * z1).sqrt();
+        let root = ((3.0 - z1) * (3.0 + z1 + 2.0 * z2)).max(0.0).sqrt();
+        m * (3.0 + z2 - chi.signum() * root)
+    }
+
+    fn geodesic_model(&self) -> GeodesicModel {
+        GeodesicModel::KerrSchild
+    }
+}
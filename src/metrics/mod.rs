@@ -1 +1,296 @@
+//! Spacetime metrics.
+//!
+//! Every metric is written in the Cartesian coordinates the renderer uses,
+//! x^μ = (t, x, y, z) centred on the body, with +y as the symmetry (spin) axis
+//! and signature (-, +, +, +). Horizon-penetrating Kerr–Schild forms are
+//! preferred so rays can be integrated straight through r = rs.
+//!
+//! The CPU side goes through the `Metric` trait: body horizons and ISCOs,
+//! mergers, and the reference tracer's capture and escape tests. The
+//! ray-march shader is not generated from it. It keeps hand-written
+//! pseudo-Newtonian, Kerr–Schild and Schwarzschild geodesic paths, and
+//! `geodesic_model` only picks which of them renders a metric, so a new
+//! metric also needs a matching shader path.
+
+pub mod geodesic;
+pub mod kerr;
 pub mod schwarzschild;
+
+use kerr::KerrParams;
+use schwarzschild::SchwarzschildParams;
+
+/// An event x^μ = (t, x, y, z).
+pub type Event = [f64; 4];
+
+/// A rank-2 tensor with both indices down (g_μν) or both up (g^μν).
+pub type Tensor2 = [[f64; 4]; 4];
+
+/// Christoffel symbols Γ^μ_αβ, indexed as `[μ][α][β]`.
+pub type Christoffel = [[[f64; 4]; 4]; 4];
+
+/// Minkowski metric η_μν = diag(-1, 1, 1, 1).
+pub const MINKOWSKI: Tensor2 = [
+    [-1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// How the ray-march shader integrates photons through a given metric.
+///
+/// The discriminant is uploaded as `Uniforms::geodesic_model`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum GeodesicModel {
+    /// Spatial ray with the Binet-equation force -1.5·rs·L²/r⁵ per body.
+    PseudoNewtonian = 0,
+    /// Hamilton's equations on a (superposed) Kerr–Schild inverse metric.
+    KerrSchild = 1,
+    /// Full geodesic equation for k^μ with Christoffel symbols of a single
+    /// Schwarzschild body, tracking coordinate time and p_t along the ray.
+    NullGeodesic = 2,
+}
+
+pub trait Metric {
+    fn name(&self) -> &'static str;
+
+    /// Mass M in geometric units (rs = 2M).
+    fn mass(&self) -> f64;
+
+    /// Covariant metric g_μν at `x`.
+    fn metric_tensor(&self, x: &Event) -> Tensor2;
+
+    /// Contravariant metric g^μν at `x`. Defaults to a numerical inverse.
+    fn inverse_metric(&self, x: &Event) -> Tensor2 {
+        invert(&self.metric_tensor(x))
+    }
+
+    /// Christoffel symbols of the second kind at `x`. Defaults to central
+    /// differences of `metric_tensor`, so a new metric only has to supply g_μν.
+    fn christoffel(&self, x: &Event) -> Christoffel {
+        numerical_christoffel(self, x)
+    }
+
+    /// Coordinate radius of `x` used for horizon and escape tests.
+    fn radius(&self, x: &Event) -> f64;
+
+    /// Radius of the outer event horizon.
+    fn horizon_radius(&self) -> f64;
+
+    /// Radius of the circular photon orbit (prograde and equatorial if the
+    /// metric is not spherically symmetric).
+    fn photon_sphere_radius(&self) -> f64;
+
+    /// Impact parameter separating captured from escaping photons on the
+    /// photon orbit returned by `photon_sphere_radius`.
+    fn critical_impact_parameter(&self) -> f64;
+
+    /// Innermost stable circular orbit of the accretion disk.
+    fn isco_radius(&self) -> f64;
+
+    /// Whether `x` lies inside the outer event horizon.
+    fn is_inside_horizon(&self, x: &Event) -> bool {
+        self.radius(x) < self.horizon_radius()
+    }
+
+    /// Whether `x` lies beyond `escape_radius`, where a ray is taken to be
+    /// free of the hole.
+    fn has_escaped(&self, x: &Event, escape_radius: f64) -> bool {
+        self.radius(x) > escape_radius
+    }
+
+    /// Shader integration path able to render this metric.
+    fn geodesic_model(&self) -> GeodesicModel;
+}
+
+/// Metric of a black hole with Schwarzschild radius `rs` and spin χ = a/M:
+/// Schwarzschild when non-rotating, Kerr otherwise.
+pub fn black_hole(rs: f64, spin: f64) -> Box<dyn Metric> {
+    if spin == 0.0 {
+        Box::new(SchwarzschildParams { rs })
+    } else {
+        Box::new(KerrParams { rs, spin })
+    }
+}
+
+/// Γ^μ_αβ = ½ g^μν (∂_α g_νβ + ∂_β g_να - ∂_ν g_αβ) with ∂g by central differences.
+pub fn numerical_christoffel<M: Metric + ?Sized>(metric: &M, x: &Event) -> Christoffel {
+    let h = 1e-5 * metric.radius(x).max(1.0);
+    let mut dg = [[[0.0; 4]; 4]; 4];
+    for (k, dg_k) in dg.iter_mut().enumerate() {
+        let mut xp = *x;
+        let mut xm = *x;
+        xp[k] += h;
+        xm[k] -= h;
+        let gp = metric.metric_tensor(&xp);
+        let gm = metric.metric_tensor(&xm);
+        for a in 0..4 {
+            for b in 0..4 {
+                dg_k[a][b] = (gp[a][b] - gm[a][b]) / (2.0 * h);
+            }
+        }
+    }
+    christoffel_from_derivatives(&metric.inverse_metric(x), &dg)
+}
+
+/// Contract metric derivatives `dg[k][a][b] = ∂_k g_ab` into Γ^μ_αβ.
+pub fn christoffel_from_derivatives(g_inv: &Tensor2, dg: &[[[f64; 4]; 4]; 4]) -> Christoffel {
+    let mut gamma = [[[0.0; 4]; 4]; 4];
+    for (mu, gamma_mu) in gamma.iter_mut().enumerate() {
+        for a in 0..4 {
+            for b in a..4 {
+                let mut sum = 0.0;
+                for nu in 0..4 {
+                    sum += g_inv[mu][nu] * (dg[a][nu][b] + dg[b][nu][a] - dg[nu][a][b]);
+                }
+                gamma_mu[a][b] = 0.5 * sum;
+                gamma_mu[b][a] = 0.5 * sum;
+            }
+        }
+    }
+    gamma
+}
+
+/// Inverse of a 4×4 matrix by Gauss–Jordan elimination with partial pivoting.
+pub fn invert(m: &Tensor2) -> Tensor2 {
+    let mut a = *m;
+    let mut inv = [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+    for col in 0..4 {
+        let pivot = (col..4)
+            .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+            .unwrap_or(col);
+        a.swap(col, pivot);
+        inv.swap(col, pivot);
+        let p = a[col][col];
+        for k in 0..4 {
+            a[col][k] /= p;
+            inv[col][k] /= p;
+        }
+        for row in 0..4 {
+            if row != col {
+                let factor = a[row][col];
+                for k in 0..4 {
+                    a[row][k] -= factor * a[col][k];
+                    inv[row][k] -= factor * inv[col][k];
+                }
+            }
+        }
+    }
+    inv
+}
+
+/// Kerr–Schild form g_μν = η_μν + f l_μ l_ν for a null covector l_μ = (1, l).
+pub(crate) fn kerr_schild_metric(f: f64, l: [f64; 3]) -> Tensor2 {
+    let l4 = [1.0, l[0], l[1], l[2]];
+    let mut g = MINKOWSKI;
+    for a in 0..4 {
+        for b in 0..4 {
+            g[a][b] += f * l4[a] * l4[b];
+        }
+    }
+    g
+}
+
+/// Exact inverse of the Kerr–Schild form: g^μν = η^μν - f l^μ l^ν, l^μ = (-1, l).
+pub(crate) fn kerr_schild_inverse(f: f64, l: [f64; 3]) -> Tensor2 {
+    let l4 = [-1.0, l[0], l[1], l[2]];
+    let mut g = MINKOWSKI;
+    for a in 0..4 {
+        for b in 0..4 {
+            g[a][b] -= f * l4[a] * l4[b];
+        }
+    }
+    g
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Events around the hole, off every axis and plane of symmetry.
+    const EVENTS: [Event; 3] = [
+        [0.0, 2.0, 0.7, -1.1],
+        [3.0, -0.4, -2.5, 5.0],
+        [0.0, 12.0, 3.0, 4.0],
+    ];
+
+    fn assert_close(a: f64, b: f64, tol: f64, what: &str) {
+        assert!((a - b).abs() < tol, "{what}: {a} vs {b}");
+    }
+
+    #[test]
+    fn inverse_metric_inverts_the_metric() {
+        let metrics: [Box<dyn Metric>; 3] = [
+            Box::new(SchwarzschildParams { rs: 1.0 }),
+            Box::new(KerrParams { rs: 1.0, spin: 0.9 }),
+            Box::new(KerrParams { rs: 2.0, spin: 0.3 }),
+        ];
+        for metric in &metrics {
+            for x in &EVENTS {
+                let g = metric.metric_tensor(x);
+                let g_inv = metric.inverse_metric(x);
+                let numerical = invert(&g);
+                for a in 0..4 {
+                    for b in 0..4 {
+                        let product: f64 = (0..4).map(|c| g_inv[a][c] * g[c][b]).sum();
+                        let identity = if a == b { 1.0 } else { 0.0 };
+                        assert_close(product, identity, 1e-12, metric.name());
+                        assert_close(numerical[a][b], g_inv[a][b], 1e-10, metric.name());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn horizon_and_escape_tests_use_the_metric_radius() {
+        let schwarzschild = SchwarzschildParams { rs: 1.0 };
+        assert!(schwarzschild.is_inside_horizon(&[0.0, 0.0, 0.0, 0.99]));
+        assert!(!schwarzschild.is_inside_horizon(&[0.0, 0.0, 0.0, 1.01]));
+        assert!(schwarzschild.has_escaped(&[0.0, 30.0, 0.0, 40.1], 50.0));
+        assert!(!schwarzschild.has_escaped(&[0.0, 30.0, 0.0, 39.9], 50.0));
+
+        // r+ = 0.5 + √(0.25 - 0.45²) ≈ 0.718 for χ = 0.9. In the equatorial
+        // plane r² = R² - a², so the horizon sits at R = √(r+² + a²) ≈ 0.84
+        // there, while on the spin axis r = |y|.
+        let kerr = KerrParams { rs: 1.0, spin: 0.9 };
+        let r_plus = kerr.horizon_radius();
+        assert_close(r_plus, 0.5 + (0.25_f64 - 0.2025).sqrt(), 1e-12, "r+");
+        let equator = (r_plus * r_plus + 0.45 * 0.45).sqrt();
+        assert!(kerr.is_inside_horizon(&[0.0, equator - 0.01, 0.0, 0.0]));
+        assert!(!kerr.is_inside_horizon(&[0.0, equator + 0.01, 0.0, 0.0]));
+        assert!(kerr.is_inside_horizon(&[0.0, 0.0, r_plus - 0.01, 0.0]));
+        assert!(!kerr.is_inside_horizon(&[0.0, 0.0, r_plus + 0.01, 0.0]));
+        // An equatorial point at R = 50 is at r = √(50² - a²), just short of
+        // escaping.
+        assert!(!kerr.has_escaped(&[0.0, 0.0, 0.0, 50.0], 50.0));
+        assert!(kerr.has_escaped(&[0.0, 0.0, 0.0, 50.01], 50.0));
+        assert!(kerr.has_escaped(&[0.0, 0.0, 50.01, 0.0], 50.0));
+    }
+
+    #[test]
+    fn schwarzschild_christoffels_match_central_differences() {
+        let metric = SchwarzschildParams { rs: 1.0 };
+        for x in &EVENTS {
+            let exact = metric.christoffel(x);
+            let numerical = numerical_christoffel(&metric, x);
+            for mu in 0..4 {
+                for a in 0..4 {
+                    for b in 0..4 {
+                        assert_close(exact[mu][a][b], numerical[mu][a][b], 1e-7, "Γ");
+                    }
+                }
+            }
+        }
+        // Far away, Γ^x_tt ≈ M x/r³ is the Newtonian pull, up to O(rs/r).
+        let x = [0.0, 100.0, 0.0, 0.0];
+        let newtonian = 0.5 / 1e4;
+        let pull = metric.christoffel(&x)[1][0][0];
+        assert_close(pull / newtonian, 1.0, 0.02, "Γ^x_tt");
+    }
+}
@@ -1,9 +1,18 @@
+use super::{
+    christoffel_from_derivatives, kerr_schild_inverse, kerr_schild_metric, Christoffel, Event,
+    GeodesicModel, Metric, Tensor2,
+};
+
 /// Parameters for the Schwarzschild metric.
 ///
 /// ds² = -(1 - rs/r)dt² + (1 - rs/r)⁻¹dr² + r²(dθ² + sin²θ dφ²)
+///
+/// As a `Metric` it is evaluated in ingoing Kerr–Schild coordinates,
+/// g_μν = η_μν + (rs/r) l_μ l_ν with l_μ = (1, x/r, y/r, z/r), whose spatial
+/// slices coincide with the Schwarzschild r, θ, φ.
 pub struct SchwarzschildParams {
     /// Schwarzschild radius rs = 2GM/c² (natural units: rs = 1)
-    pub rs: f32,
+    pub rs: f64,
 }
 
 impl Default for SchwarzschildParams {
@@ -13,18 +22,78 @@ impl Default for SchwarzschildParams {
 }
 
 impl SchwarzschildParams {
-    /// Critical impact parameter for the photon sphere: b_crit = 3√3/2 * rs
-    pub fn critical_impact_parameter(&self) -> f32 {
-        3.0 * 3.0_f32.sqrt() / 2.0 * self.rs
+    fn field(&self, x: &Event) -> (f64, [f64; 3]) {
+        let r = self.radius(x);
+        (self.rs / r, [x[1] / r, x[2] / r, x[3] / r])
+    }
+}
+
+impl Metric for SchwarzschildParams {
+    fn name(&self) -> &'static str {
+        "Schwarzschild"
+    }
+
+    fn mass(&self) -> f64 {
+        0.5 * self.rs
+    }
+
+    fn metric_tensor(&self, x: &Event) -> Tensor2 {
+        let (f, l) = self.field(x);
+        kerr_schild_metric(f, l)
+    }
+
+    fn inverse_metric(&self, x: &Event) -> Tensor2 {
+        let (f, l) = self.field(x);
+        kerr_schild_inverse(f, l)
+    }
+
+    /// Γ^μ_αβ from the exact derivatives of g_μν = η_μν + f l_μ l_ν, with
+    /// ∂_k f = -rs x_k/r³ and ∂_k l_i = (δ_ik - x_i x_k/r²)/r.
+    fn christoffel(&self, x: &Event) -> Christoffel {
+        let (f, l) = self.field(x);
+        let r = self.radius(x);
+        let l4 = [1.0, l[0], l[1], l[2]];
+        let mut dg = [[[0.0; 4]; 4]; 4];
+        for k in 1..4 {
+            let df = -f * l[k - 1] / r;
+            let dl: [f64; 4] = std::array::from_fn(|i| match i {
+                0 => 0.0,
+                i => ((i == k) as u8 as f64 - l[i - 1] * l[k - 1]) / r,
+            });
+            for a in 0..4 {
+                for b in 0..4 {
+                    dg[k][a][b] = df * l4[a] * l4[b] + f * (dl[a] * l4[b] + l4[a] * dl[b]);
+                }
+            }
+        }
+        christoffel_from_derivatives(&self.inverse_metric(x), &dg)
+    }
+
+    fn radius(&self, x: &Event) -> f64 {
+        (x[1] * x[1] + x[2] * x[2] + x[3] * x[3]).sqrt()
+    }
+
+    /// Event horizon: r = rs
+    fn horizon_radius(&self) -> f64 {
+        self.rs
     }
 
     /// Photon sphere radius: r = 3/2 * rs
-    pub fn photon_sphere_radius(&self) -> f32 {
+    fn photon_sphere_radius(&self) -> f64 {
         1.5 * self.rs
     }
 
+    /// Critical impact parameter for the photon sphere: b_crit = 3√3/2 * rs
+    fn critical_impact_parameter(&self) -> f64 {
+        3.0 * 3.0_f64.sqrt() / 2.0 * self.rs
+    }
+
     /// Innermost stable circular orbit: r_isco = 3 * rs
-    pub fn isco_radius(&self) -> f32 {
+    fn isco_radius(&self) -> f64 {
         3.0 * self.rs
     }
+
+    fn geodesic_model(&self) -> GeodesicModel {
+        GeodesicModel::PseudoNewtonian
+    }
 }
@@ -0,0 +1,94 @@
+//! God-mode body placement: click the disk plane (y = 0) to drop a black
+//! hole, and drag before releasing to give it an initial velocity.
+
+use glam::Vec3;
+use winit::event::{ElementState, MouseButton};
+
+use crate::renderer::camera::OrbitalCamera;
+use crate::simulation::Body;
+
+/// Body being placed: where the button went down and where the cursor is now.
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    start: Vec3,
+    current: Vec3,
+}
+
+pub struct PlacementTool {
+    /// While enabled, left clicks place bodies instead of orbiting the camera.
+    pub enabled: bool,
+    /// Schwarzschild radius of new bodies.
+    pub rs: f32,
+    /// Initial speed per unit of drag distance on the plane.
+    pub velocity_scale: f32,
+    /// Plane point under the cursor.
+    hover: Option<Vec3>,
+    drag: Option<Drag>,
+}
+
+impl Default for PlacementTool {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rs: 0.5,
+            velocity_scale: 0.1,
+            hover: None,
+            drag: None,
+        }
+    }
+}
+
+/// Fastest initial speed a drag can give, as a fraction of c.
+const MAX_SPEED: f32 = 0.9;
+
+impl PlacementTool {
+    /// Track the cursor, at (x, y) in a `size` viewport.
+    pub fn handle_mouse_move(&mut self, camera: &OrbitalCamera, x: f32, y: f32, size: (f32, f32)) {
+        self.hover = camera.pick_plane(x, y, size.0, size.1);
+        if let (Some(drag), Some(point)) = (&mut self.drag, self.hover) {
+            drag.current = point;
+        }
+    }
+
+    /// Start a placement on press; the new body is returned on release.
+    pub fn handle_mouse_button(
+        &mut self,
+        button: MouseButton,
+        state: ElementState,
+    ) -> Option<Body> {
+        if button != MouseButton::Left {
+            return None;
+        }
+        match state {
+            ElementState::Pressed => {
+                self.drag = self.hover.map(|start| Drag {
+                    start,
+                    current: start,
+                });
+                None
+            }
+            ElementState::Released => {
+                let (position, velocity) = self.preview()?;
+                self.drag = None;
+                Some(Body::new(position, velocity, self.rs))
+            }
+        }
+    }
+
+    /// Abandon the body being dragged.
+    pub fn cancel(&mut self) {
+        self.drag = None;
+    }
+
+    /// Position and initial velocity of the body being dragged.
+    pub fn preview(&self) -> Option<(Vec3, Vec3)> {
+        let drag = self.drag?;
+        let velocity = (drag.current - drag.start) * self.velocity_scale;
+        Some((drag.start, velocity.clamp_length_max(MAX_SPEED)))
+    }
+
+    /// Where a click would place a body.
+    pub fn hover(&self) -> Option<Vec3> {
+        self.hover
+    }
+}
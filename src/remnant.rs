@@ -0,0 +1,95 @@
+//! Final state of a binary black hole merger from numerical-relativity fits,
+//! for spins aligned (or anti-aligned) with the orbital angular momentum.
+//!
+//! - Mass: Barausse, Morozova & Rezzolla, ApJ 758, 63 (2012)
+//! - Spin: Rezzolla et al., ApJ 674, L29 (2008)
+//! - Recoil: Campanelli et al., ApJ 659, L5 (2007), with ξ = 145°
+//!
+//! Masses are in geometric units; spins are dimensionless and signed along
+//! the orbital angular momentum.
+
+use glam::DVec2;
+
+use crate::metrics::kerr::KerrParams;
+use crate::metrics::Metric;
+
+/// Speed of light in km/s, for quoting kicks.
+pub const C_KM_S: f64 = 299_792.458;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Remnant {
+    pub mass: f64,
+    /// Dimensionless spin along the orbital angular momentum.
+    pub spin: f64,
+    /// Recoil velocity in units of c, as components along ê₁ (from the
+    /// lighter hole to the heavier one at merger) and ê₂ = L̂ × ê₁.
+    pub kick: DVec2,
+}
+
+/// Remnant of holes with masses `m1 >= m2` and aligned spins `chi1`, `chi2`.
+pub fn remnant(m1: f64, m2: f64, chi1: f64, chi2: f64) -> Remnant {
+    let m = m1 + m2;
+    let q = m2 / m1;
+    let eta = m1 * m2 / (m * m);
+
+    // Energy radiated, interpolating between the test-particle limit (ISCO
+    // binding energy) and equal-mass simulations.
+    let a_mass = (chi1 + chi2 * q * q) / ((1.0 + q) * (1.0 + q));
+    let r_isco = KerrParams {
+        rs: 2.0,
+        spin: a_mass.clamp(-0.998, 0.998),
+    }
+    .isco_radius();
+    let e_isco = (1.0 - 2.0 / (3.0 * r_isco)).sqrt();
+    let (p0, p1) = (0.04827, 0.01707);
+    let mass = m
+        * (1.0
+            - (1.0 - e_isco) * eta
+            - 4.0 * eta * eta * (4.0 * p0 + 16.0 * p1 * a_mass * (a_mass + 1.0) + e_isco - 1.0));
+
+    let a_spin = (chi1 + chi2 * q * q) / (1.0 + q * q);
+    let (s4, s5, t0, t2, t3) = (-0.1229, 0.4537, -2.8904, -3.5171, 2.5763);
+    let spin = a_spin
+        + s4 * a_spin * a_spin * eta
+        + s5 * a_spin * eta * eta
+        + t0 * a_spin * eta
+        + 2.0 * 3.0_f64.sqrt() * eta
+        + t2 * eta * eta
+        + t3 * eta * eta * eta;
+
+    // Mass-asymmetry and spin contributions, both in the orbital plane.
+    let (a, b, h) = (1.2e4 / C_KM_S, -0.93, 6.9e3 / C_KM_S);
+    let xi = 145.0_f64.to_radians();
+    let v_mass = a * eta * eta * (1.0 - q) / (1.0 + q) * (1.0 + b * eta);
+    let v_spin = h * eta * eta / (1.0 + q) * (q * chi2 - chi1);
+    let kick = DVec2::new(v_mass + v_spin * xi.cos(), v_spin * xi.sin());
+
+    Remnant { mass, spin, kick }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_mass_non_spinning_merger_matches_simulations() {
+        let r = remnant(0.5, 0.5, 0.0, 0.0);
+        // Radiated energy ≈ 4.8%, final spin ≈ 0.686, no recoil by symmetry
+        assert!((r.mass - 0.9516).abs() < 2e-3, "mass {}", r.mass);
+        assert!((r.spin - 0.686).abs() < 2e-3, "spin {}", r.spin);
+        assert!(r.kick.length() < 1e-12);
+    }
+
+    #[test]
+    fn recoil_peaks_for_unequal_masses_and_anti_aligned_spins() {
+        // Non-spinning kicks peak near q ≈ 0.36 at about 175 km/s.
+        let peak = (10..=100)
+            .map(|i| remnant(1.0, i as f64 / 100.0, 0.0, 0.0).kick.length() * C_KM_S)
+            .fold(0.0, f64::max);
+        assert!((peak - 175.0).abs() < 15.0, "peak kick {peak} km/s");
+
+        // Equal masses with spins ±0.8 kick at a few hundred km/s.
+        let spun = remnant(0.5, 0.5, 0.8, -0.8).kick.length() * C_KM_S;
+        assert!((300.0..400.0).contains(&spun), "spin kick {spun} km/s");
+    }
+}
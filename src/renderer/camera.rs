@@ -68,6 +68,41 @@ impl OrbitalCamera {
         right
     }
 
+    /// Direction of the straight (unlensed) view ray through screen point
+    /// (x, y) of a `width × height` viewport, with the shader's pixel mapping.
+    pub fn view_ray(&self, x: f32, y: f32, width: f32, height: f32) -> Vec3 {
+        let ndc_x = (x - 0.5 * width) / (0.5 * width);
+        let ndc_y = -(y - 0.5 * height) / (0.5 * height);
+        let half_fov = (self.fov * 0.5).tan();
+        (self.forward()
+            + ndc_x * width / height * half_fov * self.right()
+            + ndc_y * half_fov * self.up())
+        .normalize()
+    }
+
+    /// Point where the view ray through (x, y) meets the disk plane y = 0,
+    /// ignoring lensing.
+    pub fn pick_plane(&self, x: f32, y: f32, width: f32, height: f32) -> Option<Vec3> {
+        let origin = self.position();
+        let dir = self.view_ray(x, y, width, height);
+        let t = -origin.y / dir.y;
+        (dir.y.abs() > 1e-6 && t > 0.0).then(|| origin + t * dir)
+    }
+
+    /// Screen point of world position `p`, the inverse of `view_ray`, or
+    /// `None` when `p` is behind the camera.
+    pub fn project(&self, p: Vec3, width: f32, height: f32) -> Option<(f32, f32)> {
+        let rel = p - self.position();
+        let depth = rel.dot(self.forward());
+        if depth < 1e-4 {
+            return None;
+        }
+        let half_fov = (self.fov * 0.5).tan();
+        let ndc_x = rel.dot(self.right()) / (depth * half_fov * width / height);
+        let ndc_y = rel.dot(self.up()) / (depth * half_fov);
+        Some((0.5 * width * (1.0 + ndc_x), 0.5 * height * (1.0 - ndc_y)))
+    }
+
     pub fn handle_mouse_button(&mut self, button: MouseButton, state: ElementState) {
         if button == MouseButton::Left {
             self.is_dragging = state == ElementState::Pressed;
@@ -128,3 +163,27 @@ impl OrbitalCamera {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn projecting_a_picked_point_returns_the_cursor() {
+        let mut camera = OrbitalCamera::new(12.0, 0.7, 1.3);
+        camera.target = Vec3::new(1.0, 0.0, -0.5);
+        camera.fov = 0.9;
+        let (w, h) = (1280.0, 720.0);
+        for (x, y) in [(640.0, 500.0), (100.0, 700.0), (1200.0, 400.0)] {
+            let p = camera.pick_plane(x, y, w, h).unwrap();
+            assert!(p.y.abs() < 1e-4);
+            let (px, py) = camera.project(p, w, h).unwrap();
+            assert!(
+                (px - x).abs() < 1e-2 && (py - y).abs() < 1e-2,
+                "{x},{y} -> {px},{py}"
+            );
+        }
+        // Looking down from above the plane, the top of the screen can miss it.
+        assert!(camera.pick_plane(640.0, 0.0, w, h).is_none());
+    }
+}
@@ -0,0 +1,348 @@
+//! Image backgrounds: equirectangular panoramas and six-face cubemaps loaded
+//! from PNG, Radiance HDR or OpenEXR files, in linear RGB.
+//!
+//! The ray-march shader samples the map with the angles of the escape
+//! direction from `dir_to_spherical`; `EnvironmentMap::sample` mirrors that
+//! lookup for the CPU reference tracer. Both kinds go to the GPU as one 2D
+//! texture, cubemaps as a 3:2 atlas of their faces (see `texture`), which
+//! every backend can sample from a compute shader; `Uniforms::environment_projection`
+//! tells the shader which kind it is.
+
+use std::fmt;
+use std::path::Path;
+
+use glam::{DVec2, DVec3};
+use image::{imageops, DynamicImage, Rgb32FImage};
+
+use super::sky::sky_direction;
+
+const PI: f64 = std::f64::consts::PI;
+
+/// Cube face file names in layer order: +x, −x, +y, −y, +z, −z.
+pub const CUBE_FACES: [&str; 6] = ["px", "nx", "py", "ny", "pz", "nz"];
+
+/// Extensions tried for each cube face.
+const FACE_EXTENSIONS: [&str; 5] = ["exr", "hdr", "png", "jpg", "jpeg"];
+
+/// Layout of an environment map, mirrored by the `PROJECTION_*` constants in
+/// the shader.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Projection {
+    /// Longitude across, colatitude down, in one image.
+    #[default]
+    Equirectangular = 0,
+    /// Six square faces, OpenGL cube map convention.
+    Cubemap = 1,
+}
+
+#[derive(Debug)]
+pub enum EnvironmentError {
+    Image(image::ImageError),
+    /// A cubemap directory is missing this face.
+    MissingFace(&'static str),
+    Invalid(String),
+}
+
+impl fmt::Display for EnvironmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvironmentError::Image(e) => write!(f, "{}", e),
+            EnvironmentError::MissingFace(face) => write!(
+                f,
+                "cubemap face '{}' not found (expected {}.exr, .hdr, .png or .jpg)",
+                face, face
+            ),
+            EnvironmentError::Invalid(reason) => write!(f, "invalid environment map: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for EnvironmentError {}
+
+impl From<image::ImageError> for EnvironmentError {
+    fn from(e: image::ImageError) -> Self {
+        EnvironmentError::Image(e)
+    }
+}
+
+/// A sky image in linear RGB, one layer per equirectangular map or six per
+/// cubemap, all the same size.
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    pub projection: Projection,
+    layers: Vec<Rgb32FImage>,
+}
+
+impl EnvironmentMap {
+    pub fn equirectangular(image: Rgb32FImage) -> Result<Self, EnvironmentError> {
+        if image.width() == 0 || image.height() == 0 {
+            return Err(EnvironmentError::Invalid("empty image".into()));
+        }
+        Ok(Self {
+            projection: Projection::Equirectangular,
+            layers: vec![image],
+        })
+    }
+
+    /// Faces in the order of `CUBE_FACES`.
+    pub fn cubemap(faces: [Rgb32FImage; 6]) -> Result<Self, EnvironmentError> {
+        let size = faces[0].width();
+        if size == 0 {
+            return Err(EnvironmentError::Invalid("empty cube face".into()));
+        }
+        for (face, name) in faces.iter().zip(CUBE_FACES) {
+            if face.dimensions() != (size, size) {
+                return Err(EnvironmentError::Invalid(format!(
+                    "cube face {} is {}×{}, expected {}×{}",
+                    name,
+                    face.width(),
+                    face.height(),
+                    size,
+                    size
+                )));
+            }
+        }
+        Ok(Self {
+            projection: Projection::Cubemap,
+            layers: faces.into(),
+        })
+    }
+
+    /// Load an equirectangular image file, or a directory holding the six
+    /// cube faces `px`, `nx`, `py`, `ny`, `pz` and `nz`.
+    pub fn load(path: &Path) -> Result<Self, EnvironmentError> {
+        if !path.is_dir() {
+            return Self::equirectangular(linear_image(image::open(path)?));
+        }
+        let mut faces = Vec::with_capacity(6);
+        for name in CUBE_FACES {
+            let file = FACE_EXTENSIONS
+                .iter()
+                .map(|ext| path.join(format!("{}.{}", name, ext)))
+                .find(|file| file.is_file())
+                .ok_or(EnvironmentError::MissingFace(name))?;
+            faces.push(linear_image(image::open(file)?));
+        }
+        let faces: [Rgb32FImage; 6] = faces.try_into().expect("six faces");
+        Self::cubemap(faces)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.layers[0].width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.layers[0].height()
+    }
+
+    /// The image uploaded for the shader: the equirectangular map itself, or
+    /// the cube faces three across and two down, +x −x +y over −y +z −z.
+    pub fn texture(&self) -> Rgb32FImage {
+        match self.projection {
+            Projection::Equirectangular => self.layers[0].clone(),
+            Projection::Cubemap => {
+                let size = self.width();
+                let mut atlas = Rgb32FImage::new(3 * size, 2 * size);
+                for (i, face) in self.layers.iter().enumerate() {
+                    let (x, y) = (i as u32 % 3, i as u32 / 3);
+                    imageops::replace(&mut atlas, face, (x * size) as i64, (y * size) as i64);
+                }
+                atlas
+            }
+        }
+    }
+
+    /// This map shrunk so that its `texture` fits `max_size` texels on each
+    /// side, keeping its aspect ratio, or `None` if it already fits.
+    pub fn fit_within(&self, max_size: u32) -> Option<Self> {
+        let (w, h) = (self.width(), self.height());
+        let max_size = match self.projection {
+            Projection::Equirectangular => max_size,
+            Projection::Cubemap => max_size / 3,
+        };
+        if w <= max_size && h <= max_size {
+            return None;
+        }
+        let scale = max_size as f64 / w.max(h) as f64;
+        let (w, h) = (
+            ((w as f64 * scale) as u32).max(1),
+            ((h as f64 * scale) as u32).max(1),
+        );
+        Some(Self {
+            projection: self.projection,
+            layers: self
+                .layers
+                .iter()
+                .map(|layer| imageops::resize(layer, w, h, imageops::FilterType::Triangle))
+                .collect(),
+        })
+    }
+
+    /// Linear radiance seen in the direction with polar angle `theta` from +y
+    /// and azimuth `phi`, as returned by `dir_to_spherical`.
+    pub fn sample(&self, theta: f64, phi: f64) -> DVec3 {
+        match self.projection {
+            Projection::Equirectangular => {
+                self.bilinear(0, DVec2::new(phi / (2.0 * PI), theta / PI), true)
+            }
+            Projection::Cubemap => {
+                let (face, uv) = cube_face(sky_direction(theta, phi));
+                // Stay half a texel inside the face so filtering never
+                // reaches into its neighbours in the atlas.
+                let half = 0.5 / self.width() as f64;
+                self.bilinear(
+                    face,
+                    uv.clamp(DVec2::splat(half), DVec2::splat(1.0 - half)),
+                    false,
+                )
+            }
+        }
+    }
+
+    /// Bilinear lookup like a linear-filtering sampler, repeating u when
+    /// `wrap` and clamping otherwise.
+    fn bilinear(&self, layer: usize, uv: DVec2, wrap: bool) -> DVec3 {
+        let image = &self.layers[layer];
+        let (w, h) = (image.width() as i64, image.height() as i64);
+        let x = uv.x * w as f64 - 0.5;
+        let y = uv.y * h as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let column = |i: i64| {
+            if wrap {
+                i.rem_euclid(w)
+            } else {
+                i.clamp(0, w - 1)
+            }
+        };
+        let texel = |i: i64, j: i64| {
+            let p = image.get_pixel(column(i) as u32, j.clamp(0, h - 1) as u32);
+            DVec3::new(p[0] as f64, p[1] as f64, p[2] as f64)
+        };
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let top = texel(x0, y0).lerp(texel(x0 + 1, y0), fx);
+        let bottom = texel(x0, y0 + 1).lerp(texel(x0 + 1, y0 + 1), fx);
+        top.lerp(bottom, fy)
+    }
+}
+
+/// Cube face (in `CUBE_FACES` order) that `dir` points through, and the
+/// face's texture coordinates there.
+pub fn cube_face(dir: DVec3) -> (usize, DVec2) {
+    let a = dir.abs();
+    let (face, major, sc, tc) = if a.x >= a.y && a.x >= a.z {
+        if dir.x > 0.0 {
+            (0, a.x, -dir.z, -dir.y)
+        } else {
+            (1, a.x, dir.z, -dir.y)
+        }
+    } else if a.y >= a.z {
+        if dir.y > 0.0 {
+            (2, a.y, dir.x, dir.z)
+        } else {
+            (3, a.y, dir.x, -dir.z)
+        }
+    } else if dir.z > 0.0 {
+        (4, a.z, dir.x, -dir.y)
+    } else {
+        (5, a.z, -dir.x, -dir.y)
+    };
+    (face, DVec2::new(sc / major + 1.0, tc / major + 1.0) * 0.5)
+}
+
+/// Linear RGB of a decoded image: float formats (HDR, EXR) are already
+/// linear, integer ones are sRGB-encoded.
+fn linear_image(image: DynamicImage) -> Rgb32FImage {
+    let float = matches!(
+        image,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    );
+    let mut linear = image.into_rgb32f();
+    if !float {
+        for channel in linear.iter_mut() {
+            *channel = srgb_to_linear(*channel);
+        }
+    }
+    linear
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cube_faces_cover_the_axes() {
+        let axes = [
+            DVec3::X,
+            DVec3::NEG_X,
+            DVec3::Y,
+            DVec3::NEG_Y,
+            DVec3::Z,
+            DVec3::NEG_Z,
+        ];
+        for (i, axis) in axes.into_iter().enumerate() {
+            assert_eq!(cube_face(axis), (i, DVec2::splat(0.5)));
+        }
+        // Looking along +x with +y up, +u runs towards −z and +v downwards.
+        let (face, uv) = cube_face(DVec3::new(1.0, -0.5, -0.5));
+        assert_eq!((face, uv), (0, DVec2::new(0.75, 0.75)));
+    }
+
+    #[test]
+    fn equirectangular_sampling_wraps_in_azimuth() {
+        // Two columns, red then blue; texel centres sit at u = 1/4 and 3/4.
+        let mut image = Rgb32FImage::new(2, 1);
+        image.put_pixel(0, 0, image::Rgb([1.0, 0.0, 0.0]));
+        image.put_pixel(1, 0, image::Rgb([0.0, 0.0, 1.0]));
+        let map = EnvironmentMap::equirectangular(image).unwrap();
+
+        let at = |u: f64| map.sample(0.5 * PI, u * 2.0 * PI);
+        assert!(at(0.25).abs_diff_eq(DVec3::X, 1e-12));
+        assert!(at(0.75).abs_diff_eq(DVec3::Z, 1e-12));
+        // Across the seam the colours blend as on either side of it.
+        assert!(at(0.0).abs_diff_eq(DVec3::new(0.5, 0.0, 0.5), 1e-12));
+        assert!(at(1.0).abs_diff_eq(at(0.0), 1e-12));
+    }
+
+    #[test]
+    fn eight_bit_images_are_decoded_from_srgb() {
+        let path = std::env::temp_dir().join("spacetime_playground_environment_test.png");
+        image::RgbImage::from_pixel(4, 2, image::Rgb([255, 188, 0]))
+            .save(&path)
+            .unwrap();
+        let map = EnvironmentMap::load(&path);
+        std::fs::remove_file(&path).ok();
+
+        let color = map.unwrap().sample(1.0, 2.0);
+        assert!((color.x - 1.0).abs() < 1e-6);
+        assert!((color.y - 0.5).abs() < 0.01);
+        assert_eq!(color.z, 0.0);
+    }
+
+    #[test]
+    fn cubemap_directory_needs_every_face() {
+        let dir = std::env::temp_dir().join("spacetime_playground_cubemap_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in &CUBE_FACES[..5] {
+            image::RgbImage::new(2, 2)
+                .save(dir.join(format!("{}.png", name)))
+                .unwrap();
+        }
+        let missing = EnvironmentMap::load(&dir);
+        image::RgbImage::new(2, 2).save(dir.join("nz.png")).unwrap();
+        let complete = EnvironmentMap::load(&dir);
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert!(matches!(missing, Err(EnvironmentError::MissingFace("nz"))));
+        assert_eq!(complete.unwrap().projection, Projection::Cubemap);
+    }
+}
@@ -0,0 +1,184 @@
+//! Fixtures for the tests that run the ray march on a real GPU and hold it
+//! to the reference tracer. Without a wgpu adapter they are skipped.
+
+use std::sync::Arc;
+
+use super::camera::OrbitalCamera;
+use super::environment::EnvironmentMap;
+use super::pipeline::RayMarchPipeline;
+use super::post;
+use super::reference::ReferenceTracer;
+use super::stars::StarBins;
+use super::uniforms::{DiskSettings, Integrator, RenderMode, Uniforms};
+use crate::simulation::{GpuBody, Preset, Simulation};
+
+/// f32 rounding moves checker edges and disk boundaries by a pixel here
+/// and there; everything else must agree to f16 precision.
+pub const MAX_MISMATCH: f64 = 0.03;
+
+/// Size of the comparison renders.
+const SIZE: (u32, u32) = (64, 36);
+
+/// Device and queue on the software fallback adapter if there is one, so
+/// results do not depend on the machine's GPU. Without any adapter the test
+/// calling this should return early; the skip is reported here.
+pub fn test_gpu() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        force_fallback_adapter: true,
+        ..Default::default()
+    }))
+    .or_else(|| {
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+    });
+    let Some(adapter) = adapter else {
+        eprintln!("no wgpu adapter available, skipping GPU test");
+        return None;
+    };
+    let device =
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None))
+            .expect("Failed to create device");
+    Some(device)
+}
+
+/// What escaping rays show in a comparison render.
+pub enum Sky {
+    Checker,
+    Image(Arc<EnvironmentMap>),
+    Stars(Arc<StarBins>),
+}
+
+/// A scene and the render settings to compare it with.
+pub struct GpuCase {
+    pub sim: Simulation,
+    pub camera_distance: f32,
+    pub camera_elevation: f32,
+    pub exact_geodesics: bool,
+    pub integrator: Integrator,
+    pub body_cutoff: f32,
+    pub disk: DiskSettings,
+    pub sky: Sky,
+    pub render_mode: RenderMode,
+    pub observer_velocity: [f32; 4],
+}
+
+impl Default for GpuCase {
+    fn default() -> Self {
+        Self {
+            sim: Simulation::new(Preset::Single),
+            camera_distance: 12.0,
+            camera_elevation: 1.3,
+            exact_geodesics: false,
+            // Adaptive steps keep the comparisons quick; cases about the
+            // default fixed RK4 ask for it.
+            integrator: Integrator::AdaptiveRk45,
+            body_cutoff: 0.0,
+            disk: DiskSettings::default(),
+            sky: Sky::Checker,
+            render_mode: RenderMode::Shaded,
+            observer_velocity: [0.0; 4],
+        }
+    }
+}
+
+impl GpuCase {
+    /// Uniforms and bodies for a `width × height` render of the case.
+    pub fn scene(&self, width: u32, height: u32) -> (Uniforms, Vec<GpuBody>) {
+        let camera = OrbitalCamera::new(self.camera_distance, 0.5, self.camera_elevation);
+        let to4 = |v: glam::Vec3| [v.x, v.y, v.z, 0.0];
+        let disk = &self.disk;
+        let uniforms = Uniforms {
+            camera_pos: to4(camera.position()),
+            camera_forward: to4(camera.forward()),
+            camera_up: to4(camera.up()),
+            camera_right: to4(camera.right()),
+            resolution: [width as f32, height as f32],
+            fov: camera.fov,
+            num_bodies: self.sim.bodies.len() as u32,
+            background_mode: match self.sky {
+                Sky::Checker => 0,
+                Sky::Image(_) => 2,
+                Sky::Stars(_) => 3,
+            },
+            environment_projection: match &self.sky {
+                Sky::Image(map) => map.projection as u32,
+                _ => 0,
+            },
+            time: 1.5,
+            geodesic_model: self.sim.geodesic_model(self.exact_geodesics) as u32,
+            integrator: self.integrator as u32,
+            body_cutoff: self.body_cutoff,
+            disk_model: disk.model as u32,
+            disk_scale_height: disk.scale_height,
+            disk_optical_depth: disk.optical_depth,
+            doppler_beaming: disk.doppler_beaming as u32,
+            gravitational_redshift: disk.gravitational_redshift as u32,
+            intensity_exponent: disk.intensity_exponent(),
+            render_mode: self.render_mode as u32,
+            observer_velocity: self.observer_velocity,
+            ..Default::default()
+        };
+        (uniforms, self.sim.gpu_bodies().to_vec())
+    }
+
+    /// Fraction of pixels where the GPU and reference renders differ, or
+    /// `None` without a wgpu adapter.
+    fn mismatch(&self) -> Option<f64> {
+        let (device, queue) = test_gpu()?;
+        let (width, height) = SIZE;
+        let mut pipeline =
+            RayMarchPipeline::new(&device, wgpu::TextureFormat::Bgra8Unorm, width, height);
+        let (uniforms, bodies) = self.scene(width, height);
+        pipeline.update_bodies(&device, &queue, &bodies);
+        pipeline.update_uniforms(&queue, &uniforms);
+        let mut tracer = ReferenceTracer::new(&uniforms, &bodies);
+        match &self.sky {
+            Sky::Checker => {}
+            Sky::Image(map) => {
+                pipeline.set_environment(&device, &queue, Some(map));
+                tracer = tracer.with_environment(Some(map.clone()));
+            }
+            Sky::Stars(bins) => {
+                pipeline.set_stars(&device, bins);
+                tracer = tracer.with_stars(Some(bins.clone()));
+            }
+        }
+        let mut encoder = device.create_command_encoder(&Default::default());
+        pipeline.dispatch_compute(&mut encoder);
+        queue.submit(std::iter::once(encoder.finish()));
+        let gpu = pipeline
+            .read_output(&device, &queue)
+            .expect("GPU readback failed");
+        let cpu = tracer.render(width, height);
+
+        // Compare what is displayed, so bright disk pixels are not held to a
+        // tighter relative tolerance than dark ones.
+        let display = |texels: &[[f32; 4]]| post::apply(texels, width, height, &Default::default());
+        Some(display_mismatch(&display(&gpu), &display(&cpu)))
+    }
+}
+
+/// Fraction of display pixels differing by more than 0.02 in any channel.
+pub fn display_mismatch(a: &[[f32; 4]], b: &[[f32; 4]]) -> f64 {
+    let mismatched = a
+        .iter()
+        .zip(b)
+        .filter(|(a, b)| (0..3).any(|i| (a[i] - b[i]).abs() > 0.02))
+        .count();
+    mismatched as f64 / b.len() as f64
+}
+
+/// Render `case` on the GPU and with the reference tracer and check they
+/// agree, or skip the check without a wgpu adapter.
+pub fn assert_matches_gpu(case: GpuCase) {
+    let Some(fraction) = case.mismatch() else {
+        return;
+    };
+    assert!(
+        fraction < MAX_MISMATCH,
+        "{}, {}: {:.1}% of pixels differ",
+        case.integrator.name(),
+        case.render_mode.name(),
+        fraction * 100.0
+    );
+}
@@ -1,3 +1,12 @@
 pub mod camera;
+pub mod environment;
+#[cfg(test)]
+mod gpu_test;
+pub mod observer;
 pub mod pipeline;
+pub mod post;
+pub mod reference;
+pub mod sky;
+pub mod stars;
+pub mod tonemap;
 pub mod uniforms;
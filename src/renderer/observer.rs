@@ -0,0 +1,317 @@
+//! Moving observers: a camera falling radially into body 0 from rest, or on
+//! a circular orbit around it, and what its motion does to the view.
+//!
+//! The motion follows Schwarzschild geodesics of body 0, ignoring the other
+//! bodies, so it is only followed while body 0 does not rotate; around a
+//! spinning body the camera stays at rest. The ray march launches rays in the frame of the
+//! local static observer, so the camera's velocity relative to that observer
+//! is all it needs: rays are aberrated towards the direction of motion and
+//! everything seen is Doppler shifted by D = γ(1 + β·d).
+
+use glam::{DVec3, Quat, Vec3};
+
+use super::camera::OrbitalCamera;
+
+/// How the camera moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Motion {
+    /// At rest, where the orbit controls put it.
+    #[default]
+    Static,
+    /// Dropped from rest at `Observer::radius`, straight towards the body.
+    Infall,
+    /// On a circular orbit of radius `Observer::radius`, prograde with
+    /// respect to an untilted disk. Orbits between the photon sphere and the
+    /// ISCO are unstable but still followed exactly.
+    Orbit,
+}
+
+impl Motion {
+    pub const ALL: [Motion; 3] = [Motion::Static, Motion::Infall, Motion::Orbit];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Motion::Static => "Static",
+            Motion::Infall => "Free fall",
+            Motion::Orbit => "Orbit",
+        }
+    }
+
+    /// Name on the command line.
+    pub fn flag(self) -> &'static str {
+        match self {
+            Motion::Static => "static",
+            Motion::Infall => "infall",
+            Motion::Orbit => "orbit",
+        }
+    }
+
+    /// Whether the motion can be followed around a body of spin `spin`.
+    pub fn supports_spin(self, spin: f32) -> bool {
+        self == Motion::Static || spin == 0.0
+    }
+}
+
+/// The fall stops this many rs from the centre, just outside the horizon:
+/// rays cannot be launched from inside it, and the static observers the
+/// camera's velocity is measured against do not exist there.
+pub const STOP_RADIUS: f64 = 1.05;
+
+/// Where a moving observer is and how fast it goes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObserverState {
+    /// Distance from the body, in rs.
+    pub radius: f64,
+    /// Angle travelled around the orbit.
+    pub angle: f64,
+    /// Speed relative to the local static observer, as a fraction of c.
+    pub speed: f64,
+}
+
+/// Camera position, orientation and velocity relative to the local static
+/// observer, in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObserverFrame {
+    pub position: Vec3,
+    pub forward: Vec3,
+    pub up: Vec3,
+    pub right: Vec3,
+    pub velocity: Vec3,
+}
+
+impl ObserverFrame {
+    /// The frame of `camera` held at rest.
+    pub fn at_rest(camera: &OrbitalCamera) -> Self {
+        Self {
+            position: camera.position(),
+            forward: camera.forward(),
+            up: camera.up(),
+            right: camera.right(),
+            velocity: Vec3::ZERO,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Observer {
+    pub motion: Motion,
+    /// Radius the fall starts from, or of the orbit, in rs.
+    pub radius: f64,
+    /// Proper time of the observer since the motion started.
+    pub proper_time: f64,
+    /// Proper time that passes per second.
+    pub time_scale: f64,
+    /// Direction from the body the motion started in.
+    start_direction: Vec3,
+}
+
+impl Default for Observer {
+    fn default() -> Self {
+        Self {
+            motion: Motion::Static,
+            radius: 10.0,
+            proper_time: 0.0,
+            time_scale: 1.0,
+            start_direction: Vec3::X,
+        }
+    }
+}
+
+impl Observer {
+    /// Start the motion over from where `camera` is seen from `centre`.
+    pub fn restart(&mut self, camera: &OrbitalCamera, centre: Vec3) {
+        self.start_direction = (camera.position() - centre).normalize_or(Vec3::X);
+        self.proper_time = 0.0;
+    }
+
+    /// Let `dt` seconds pass, stopping a fall at `STOP_RADIUS` around a body
+    /// of Schwarzschild radius `rs`.
+    pub fn advance(&mut self, dt: f64, rs: f64) {
+        self.proper_time += dt * self.time_scale;
+        if self.motion == Motion::Infall {
+            self.proper_time = self.proper_time.min(self.stop_time(rs));
+        }
+    }
+
+    /// Proper time a fall takes from its start to `STOP_RADIUS`.
+    pub fn stop_time(&self, rs: f64) -> f64 {
+        let eta = (2.0 * STOP_RADIUS / self.radius - 1.0)
+            .clamp(-1.0, 1.0)
+            .acos();
+        self.fall_time(eta, rs)
+    }
+
+    /// Proper time at cycloid parameter `eta` of the fall, where the radius
+    /// is r₀ (1 + cos η) / 2.
+    fn fall_time(&self, eta: f64, rs: f64) -> f64 {
+        let r0 = self.radius * rs;
+        (r0 * r0 * r0 / (4.0 * rs)).sqrt() * (eta + eta.sin())
+    }
+
+    /// Position and speed after `proper_time` around a body of Schwarzschild
+    /// radius `rs`.
+    pub fn state(&self, rs: f64) -> ObserverState {
+        let r0 = self.radius;
+        match self.motion {
+            Motion::Static => ObserverState {
+                radius: r0,
+                angle: 0.0,
+                speed: 0.0,
+            },
+            Motion::Infall => {
+                // Invert τ(η), which grows monotonically over 0..π
+                let tau = self.proper_time.min(self.stop_time(rs));
+                let (mut lo, mut hi) = (0.0, std::f64::consts::PI);
+                for _ in 0..60 {
+                    let mid = 0.5 * (lo + hi);
+                    if self.fall_time(mid, rs) < tau {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+                let radius = (0.5 * r0 * (1.0 + lo.cos())).max(STOP_RADIUS);
+                // γ relative to the static observer is E / √(1 - rs/r), with
+                // energy per unit mass E = √(1 - rs/r₀) for a fall from rest
+                let inv_gamma_sq = (1.0 - 1.0 / radius) / (1.0 - 1.0 / r0);
+                ObserverState {
+                    radius,
+                    angle: 0.0,
+                    speed: (1.0 - inv_gamma_sq).max(0.0).sqrt(),
+                }
+            }
+            Motion::Orbit => {
+                let r = r0 * rs;
+                let m = 0.5 * rs;
+                let omega = (m / (r * r * r)).sqrt();
+                let dtau_dt = (1.0 - 3.0 * m / r).max(1e-9).sqrt();
+                ObserverState {
+                    radius: r0,
+                    angle: omega * self.proper_time / dtau_dt,
+                    speed: (m / (r - 2.0 * m)).sqrt().min(1.0),
+                }
+            }
+        }
+    }
+
+    /// The camera of a moving observer around a body at `centre` of
+    /// Schwarzschild radius `rs`, looking the way `camera` does. An orbiting
+    /// camera turns with the orbit, so the body stays put in the view.
+    pub fn frame(&self, camera: &OrbitalCamera, centre: Vec3, rs: f32) -> ObserverFrame {
+        let (forward, up, right) = (camera.forward(), camera.up(), camera.right());
+        let state = self.state(rs as f64);
+        let start = self.start_direction;
+        let r = state.radius as f32 * rs;
+        let speed = state.speed as f32;
+        match self.motion {
+            Motion::Static => ObserverFrame::at_rest(camera),
+            Motion::Infall => ObserverFrame {
+                position: centre + r * start,
+                forward,
+                up,
+                right,
+                velocity: -speed * start,
+            },
+            Motion::Orbit => {
+                // Prograde about +y, as the gas of an untilted disk
+                let tangent = Vec3::Y.cross(start).normalize_or(Vec3::Z);
+                let turn = Quat::from_axis_angle(start.cross(tangent), state.angle as f32);
+                ObserverFrame {
+                    position: centre + r * (turn * start),
+                    forward: turn * forward,
+                    up: turn * up,
+                    right: turn * right,
+                    velocity: speed * (turn * tangent),
+                }
+            }
+        }
+    }
+}
+
+/// Direction in the static observer's frame of a ray that an observer
+/// moving at `beta` (a fraction of c) sees along unit vector `local`.
+pub fn aberrate(local: DVec3, beta: DVec3) -> DVec3 {
+    let b2 = beta.length_squared();
+    if b2 == 0.0 {
+        return local;
+    }
+    let gamma = 1.0 / (1.0 - b2).max(1e-12).sqrt();
+    let along = local.dot(beta) / b2;
+    (local + ((gamma - 1.0) * along - gamma) * beta) / (gamma * (1.0 - local.dot(beta)))
+}
+
+/// Frequency an observer moving at `beta` measures over the frequency the
+/// static observer measures, for light seen along static-frame direction
+/// `dir`.
+pub fn doppler_factor(dir: DVec3, beta: DVec3) -> f64 {
+    let gamma = 1.0 / (1.0 - beta.length_squared()).max(1e-12).sqrt();
+    gamma * (1.0 + dir.dot(beta))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::schwarzschild::SchwarzschildParams;
+    use crate::metrics::Metric;
+
+    #[test]
+    fn fall_from_rest_follows_the_radial_geodesic() {
+        let observer = Observer {
+            motion: Motion::Infall,
+            radius: 8.0,
+            ..Default::default()
+        };
+        let rs = 0.5;
+        let at = |tau: f64| {
+            Observer {
+                proper_time: tau,
+                ..observer.clone()
+            }
+            .state(rs)
+        };
+        assert_eq!(at(0.0).radius, 8.0);
+        assert_eq!(at(0.0).speed, 0.0);
+
+        // dr/dτ = -√(rs/r - rs/r₀), with r in world units
+        let tau = 0.6 * observer.stop_time(rs);
+        let h = 1e-4;
+        let rate = (at(tau + h).radius - at(tau - h).radius) * rs / (2.0 * h);
+        let r = at(tau).radius * rs;
+        let expected = -(rs / r - rs / (8.0 * rs)).sqrt();
+        assert!((rate - expected).abs() < 1e-5, "{rate} vs {expected}");
+
+        // The fall stops just outside the horizon, close to light speed.
+        let end = at(10.0 * observer.stop_time(rs));
+        assert!((end.radius - STOP_RADIUS).abs() < 1e-9);
+        assert!(end.speed > 0.97 && end.speed < 1.0, "{}", end.speed);
+    }
+
+    #[test]
+    fn isco_orbit_moves_at_half_light_speed() {
+        let observer = Observer {
+            motion: Motion::Orbit,
+            radius: SchwarzschildParams { rs: 1.0 }.isco_radius(),
+            proper_time: 1.0,
+            ..Default::default()
+        };
+        let state = observer.state(2.0);
+        assert!((state.speed - 0.5).abs() < 1e-12);
+        // Ω = √(M/r³) in coordinate time, and dτ/dt = √(1 - 3M/r) = 1/√2
+        let omega = (1.0_f64 / 216.0).sqrt();
+        assert!((state.angle - omega * 2.0_f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn aberration_crowds_the_view_forwards() {
+        let beta = DVec3::new(0.0, 0.0, 0.6);
+        // Straight ahead is unchanged...
+        assert!((aberrate(DVec3::Z, beta) - DVec3::Z).length() < 1e-12);
+        // ...while what the camera sees side-on lies behind it.
+        let side = aberrate(DVec3::X, beta);
+        assert!((side.length() - 1.0).abs() < 1e-12);
+        assert!((side.z - -0.6).abs() < 1e-12);
+        // Light from ahead is blueshifted, side-on light redshifted by 1/γ.
+        assert!((doppler_factor(DVec3::Z, beta) - 2.0).abs() < 1e-12);
+        assert!((doppler_factor(side, beta) - 0.8).abs() < 1e-12);
+    }
+}
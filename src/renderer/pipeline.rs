@@ -2,17 +2,39 @@ use std::path::PathBuf;
 
 use wgpu::util::DeviceExt;
 
-use super::uniforms::Uniforms;
-use crate::simulation::{GpuBody, MAX_BODIES};
+use super::environment::EnvironmentMap;
+use super::post::{self, PostProcess, PostSettings};
+use super::stars::{GpuStar, StarBins};
+use super::uniforms::{sample_jitter, Uniforms};
+use crate::simulation::GpuBody;
+
+/// Bodies the body buffer holds before it first has to grow.
+const INITIAL_BODY_CAPACITY: usize = 8;
+
+/// Progressive samples after which a still image stops being traced.
+pub const MAX_ACCUMULATED_SAMPLES: u32 = 256;
 
 pub struct RayMarchPipeline {
     pub compute_pipeline: wgpu::ComputePipeline,
     pub render_pipeline: wgpu::RenderPipeline,
+    compute_bind_group_layout: wgpu::BindGroupLayout,
     pub compute_bind_group: wgpu::BindGroup,
     pub render_bind_group: wgpu::BindGroup,
+    post: PostProcess,
     pub uniform_buffer: wgpu::Buffer,
     pub body_buffer: wgpu::Buffer,
+    /// Number of bodies `body_buffer` can hold.
+    body_capacity: usize,
+    /// Per-pixel sums of the progressive samples.
+    accum_buffer: wgpu::Buffer,
+    /// Samples accumulated so far, and the uniforms and bodies they were
+    /// traced with (jitter, sample index and time zeroed).
+    samples: u32,
+    accumulated_scene: Vec<u8>,
+    /// Background image and star catalogue, kept across resizes.
+    sky: SkyResources,
     _output_texture: wgpu::Texture,
+    output_view: wgpu::TextureView,
     pub texture_size: (u32, u32),
 }
 
@@ -34,12 +56,7 @@ impl RayMarchPipeline {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        let body_data = [GpuBody::default(); MAX_BODIES];
-        let body_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Body Buffer"),
-            contents: bytemuck::cast_slice(&body_data),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-        });
+        let body_buffer = create_body_buffer(device, INITIAL_BODY_CAPACITY);
 
         let output_texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("Ray March Output"),
@@ -66,6 +83,16 @@ impl RayMarchPipeline {
             ),
         });
 
+        let storage_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
         let compute_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("Compute Bind Group Layout"),
@@ -103,28 +130,59 @@ impl RayMarchPipeline {
                         },
                         count: None,
                     },
+                    // Accumulation buffer (read-write)
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    // Environment map or cubemap atlas
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    // Star catalogue bins and stars (read-only)
+                    storage_entry(6),
+                    storage_entry(7),
                 ],
             });
 
-        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Compute Bind Group"),
-            layout: &compute_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: uniform_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: body_buffer.as_entire_binding(),
-                },
-            ],
+        let accum_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Accumulation Buffer"),
+            size: (width as u64) * (height as u64) * 16,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
         });
 
+        let sky = SkyResources::new(device);
+
+        let compute_bind_group = create_compute_bind_group(
+            device,
+            &compute_bind_group_layout,
+            &uniform_buffer,
+            &texture_view,
+            &body_buffer,
+            &accum_buffer,
+            &sky,
+        );
+
         let compute_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Compute Pipeline Layout"),
@@ -141,7 +199,10 @@ impl RayMarchPipeline {
             cache: None,
         });
 
-        // Render pipeline (fullscreen blit)
+        // Bloom chain between the compute pass and the composite
+        let post = PostProcess::new(device, &texture_view, width, height);
+
+        // Render pipeline (fullscreen composite)
         let render_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Fullscreen Blit Shader"),
             source: wgpu::ShaderSource::Wgsl(
@@ -149,33 +210,35 @@ impl RayMarchPipeline {
             ),
         });
 
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("Blit Sampler"),
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
-        });
-
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
         let render_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("Render Bind Group Layout"),
                 entries: &[
+                    // Ray-march output
+                    texture_entry(0),
+                    // Bloom
+                    texture_entry(1),
+                    // Post-processing uniforms
                     wgpu::BindGroupLayoutEntry {
-                        binding: 0,
+                        binding: 2,
                         visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
                         },
                         count: None,
                     },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
                 ],
             });
 
@@ -189,7 +252,11 @@ impl RayMarchPipeline {
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
+                    resource: wgpu::BindingResource::TextureView(post.bloom_view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: post.uniform_buffer.as_entire_binding(),
                 },
             ],
         });
@@ -233,11 +300,19 @@ impl RayMarchPipeline {
         Self {
             compute_pipeline,
             render_pipeline,
+            compute_bind_group_layout,
             compute_bind_group,
             render_bind_group,
+            post,
             uniform_buffer,
             body_buffer,
+            body_capacity: INITIAL_BODY_CAPACITY,
+            accum_buffer,
+            samples: 0,
+            accumulated_scene: Vec::new(),
+            sky,
             _output_texture: output_texture,
+            output_view: texture_view,
             texture_size: (width, height),
         }
     }
@@ -249,15 +324,122 @@ impl RayMarchPipeline {
         width: u32,
         height: u32,
     ) {
+        let sky = self.sky.clone();
         *self = Self::new(device, surface_format, width, height);
+        self.sky = sky;
+        self.rebuild_compute_bind_group(device);
+    }
+
+    /// Upload the background image sampled by background mode 2, or go back
+    /// to the black placeholder. Maps larger than the device allows are
+    /// shrunk to fit.
+    pub fn set_environment(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        map: Option<&EnvironmentMap>,
+    ) {
+        let max_size = device.limits().max_texture_dimension_2d;
+        let fitted = map.and_then(|map| map.fit_within(max_size));
+        if fitted.is_some() {
+            log::warn!("Environment map shrunk to fit {} texels", max_size);
+        }
+        let map = fitted.as_ref().or(map);
+        self.sky.environment_view = create_environment_view(device, map.map(|map| (queue, map)));
+        self.rebuild_compute_bind_group(device);
+        // The accumulation key does not see the image.
+        self.accumulated_scene.clear();
+    }
+
+    /// Upload the binned star catalogue drawn by background mode 3.
+    pub fn set_stars(&mut self, device: &wgpu::Device, bins: &StarBins) {
+        (self.sky.star_index, self.sky.stars) = create_star_buffers(device, bins);
+        self.rebuild_compute_bind_group(device);
+        self.accumulated_scene.clear();
+    }
+
+    fn rebuild_compute_bind_group(&mut self, device: &wgpu::Device) {
+        self.compute_bind_group = create_compute_bind_group(
+            device,
+            &self.compute_bind_group_layout,
+            &self.uniform_buffer,
+            &self.output_view,
+            &self.body_buffer,
+            &self.accum_buffer,
+            &self.sky,
+        );
     }
 
     pub fn update_uniforms(&self, queue: &wgpu::Queue, uniforms: &Uniforms) {
         queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[*uniforms]));
     }
 
-    pub fn update_bodies(&self, queue: &wgpu::Queue, bodies: &[GpuBody; MAX_BODIES]) {
-        queue.write_buffer(&self.body_buffer, 0, bytemuck::cast_slice(bodies));
+    /// Upload the scene's bodies, growing the body buffer (and recreating the
+    /// compute bind group) when they no longer fit.
+    pub fn update_bodies(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bodies: &[GpuBody],
+    ) {
+        if bodies.len() > self.body_capacity {
+            self.body_capacity = bodies.len().next_power_of_two();
+            self.body_buffer = create_body_buffer(device, self.body_capacity);
+            self.rebuild_compute_bind_group(device);
+        }
+        if !bodies.is_empty() {
+            queue.write_buffer(&self.body_buffer, 0, bytemuck::cast_slice(bodies));
+        }
+    }
+
+    /// Set up the next progressive sample in `uniforms`. Accumulation starts
+    /// over when `accumulate` is off or the uniforms or bodies differ from
+    /// the last frame's in anything but the animation time. Returns whether
+    /// the frame needs tracing: false once a still image has
+    /// `MAX_ACCUMULATED_SAMPLES`.
+    pub fn next_sample(
+        &mut self,
+        uniforms: &mut Uniforms,
+        bodies: &[GpuBody],
+        accumulate: bool,
+    ) -> bool {
+        let key = Uniforms {
+            time: 0.0,
+            jitter: [0.0; 2],
+            sample_index: 0,
+            ..*uniforms
+        };
+        let scene: Vec<u8> = bytemuck::bytes_of(&key)
+            .iter()
+            .chain(bytemuck::cast_slice::<GpuBody, u8>(bodies))
+            .copied()
+            .collect();
+        if !accumulate || scene != self.accumulated_scene {
+            self.samples = 0;
+            self.accumulated_scene = scene;
+        }
+        if self.samples >= MAX_ACCUMULATED_SAMPLES {
+            return false;
+        }
+        uniforms.sample_index = self.samples;
+        uniforms.jitter = sample_jitter(self.samples);
+        self.samples += 1;
+        true
+    }
+
+    /// Samples averaged in the current image.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn update_post(&mut self, queue: &wgpu::Queue, settings: &PostSettings) {
+        self.post.update(queue, settings);
+    }
+
+    /// Post-processing passes between `dispatch_compute` and
+    /// `render_fullscreen`.
+    pub fn dispatch_post(&self, encoder: &mut wgpu::CommandEncoder) {
+        self.post.dispatch(encoder);
     }
 
     pub fn dispatch_compute(&self, encoder: &mut wgpu::CommandEncoder) {
@@ -268,7 +450,7 @@ impl RayMarchPipeline {
         pass.set_pipeline(&self.compute_pipeline);
         pass.set_bind_group(0, &self.compute_bind_group, &[]);
         let (w, h) = self.texture_size;
-        pass.dispatch_workgroups((w + 7) / 8, (h + 7) / 8, 1);
+        pass.dispatch_workgroups(w.div_ceil(8), h.div_ceil(8), 1);
     }
 
     pub fn render_fullscreen(
@@ -294,23 +476,41 @@ impl RayMarchPipeline {
         pass.draw(0..3, 0..1);
     }
 
-    pub fn capture_screenshot(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Option<PathBuf> {
+    pub fn capture_screenshot(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: ImageFormat,
+    ) -> Option<PathBuf> {
         let dir = PathBuf::from("screenshots");
         std::fs::create_dir_all(&dir).ok()?;
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis();
-        let path = dir.join(format!("screenshot_{}.png", timestamp));
-        self.capture_screenshot_to(device, queue, &path)
+        let path = dir.join(format!("screenshot_{}.{}", timestamp, format.extension()));
+        self.capture_screenshot_to(device, queue, &path, format)
     }
 
-    pub fn capture_screenshot_to(&self, device: &wgpu::Device, queue: &wgpu::Queue, path: &std::path::Path) -> Option<PathBuf> {
+    pub fn capture_screenshot_to(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &std::path::Path,
+        format: ImageFormat,
+    ) -> Option<PathBuf> {
+        let (width, height) = self.texture_size;
+        let texels = self.read_output(device, queue)?;
+        format.save(&texels, width, height, self.post.settings(), path)
+    }
+
+    /// Read the Rgba16Float output texture back as row-major f32 RGBA texels.
+    pub fn read_output(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Vec<[f32; 4]>> {
         let (width, height) = self.texture_size;
         let bytes_per_pixel: u32 = 8; // Rgba16Float = 4 channels × 2 bytes
         let unpadded_bytes_per_row = width * bytes_per_pixel;
         let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
-        let padded_bytes_per_row = (unpadded_bytes_per_row + align - 1) / align * align;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
 
         let buffer_size = (padded_bytes_per_row * height) as u64;
         let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
@@ -362,42 +562,448 @@ impl RayMarchPipeline {
 
         let data = buffer_slice.get_mapped_range();
 
-        // Convert f16 RGBA to u8 RGBA
-        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        let mut texels = Vec::with_capacity((width * height) as usize);
         for row in 0..height {
             let row_start = (row * padded_bytes_per_row) as usize;
             let row_end = row_start + (width * bytes_per_pixel) as usize;
             let row_data = &data[row_start..row_end];
-            for pixel in row_data.chunks_exact(8) {
+            for pixel in row_data.as_chunks::<8>().0 {
                 let r = half::f16::from_le_bytes([pixel[0], pixel[1]]).to_f32();
                 let g = half::f16::from_le_bytes([pixel[2], pixel[3]]).to_f32();
                 let b = half::f16::from_le_bytes([pixel[4], pixel[5]]).to_f32();
                 let a = half::f16::from_le_bytes([pixel[6], pixel[7]]).to_f32();
-                pixels.push((r.clamp(0.0, 1.0) * 255.0) as u8);
-                pixels.push((g.clamp(0.0, 1.0) * 255.0) as u8);
-                pixels.push((b.clamp(0.0, 1.0) * 255.0) as u8);
-                pixels.push((a.clamp(0.0, 1.0) * 255.0) as u8);
+                texels.push([r, g, b, a]);
             }
         }
 
         drop(data);
         staging_buffer.unmap();
 
-        if let Some(parent) = path.parent() {
-            if !parent.as_os_str().is_empty() {
-                std::fs::create_dir_all(parent).ok();
-            }
+        Some(texels)
+    }
+}
+
+fn create_body_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Body Buffer"),
+        size: (capacity * std::mem::size_of::<GpuBody>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// What escaping rays see besides the procedural backgrounds.
+#[derive(Clone)]
+struct SkyResources {
+    /// Background image, a black texel until one is set.
+    environment_view: wgpu::TextureView,
+    environment_sampler: wgpu::Sampler,
+    star_index: wgpu::Buffer,
+    stars: wgpu::Buffer,
+}
+
+impl SkyResources {
+    fn new(device: &wgpu::Device) -> Self {
+        // Longitude wraps around; colatitude clamps.
+        let environment_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Environment Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let (star_index, stars) = create_star_buffers(device, &StarBins::empty());
+        Self {
+            environment_view: create_environment_view(device, None),
+            environment_sampler,
+            star_index,
+            stars,
         }
+    }
+}
 
-        match image::save_buffer(path, &pixels, width, height, image::ColorType::Rgba8) {
-            Ok(()) => {
-                log::info!("Screenshot saved to {}", path.display());
-                Some(path.to_path_buf())
-            }
-            Err(e) => {
-                log::error!("Failed to save screenshot: {}", e);
-                None
-            }
+fn create_star_buffers(device: &wgpu::Device, bins: &StarBins) -> (wgpu::Buffer, wgpu::Buffer) {
+    let index = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Star Index Buffer"),
+        contents: bytemuck::cast_slice(&bins.index),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    // Bindings cannot be empty; a zeroed star is never looked at.
+    let placeholder: [GpuStar; 1] = [bytemuck::Zeroable::zeroed()];
+    let stars = if bins.stars.is_empty() {
+        &placeholder[..]
+    } else {
+        &bins.stars
+    };
+    let stars = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Star Buffer"),
+        contents: bytemuck::cast_slice(stars),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    (index, stars)
+}
+
+/// Rgba16Float texture holding the map's `texture`, or a black texel.
+fn create_environment_view(
+    device: &wgpu::Device,
+    map: Option<(&wgpu::Queue, &EnvironmentMap)>,
+) -> wgpu::TextureView {
+    let image = map.map(|(_, map)| map.texture());
+    let (width, height) = image.as_ref().map_or((1, 1), |image| image.dimensions());
+    let descriptor = wgpu::TextureDescriptor {
+        label: Some("Environment Map"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba16Float,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    };
+    let texture = match (map, image) {
+        (Some((queue, _)), Some(image)) => {
+            let max = half::f16::MAX.to_f32();
+            let bytes: Vec<u8> = image
+                .pixels()
+                .flat_map(|p| [p[0], p[1], p[2], 1.0])
+                .map(|c| if c.is_nan() { 0.0 } else { c.clamp(0.0, max) })
+                .flat_map(|c| half::f16::from_f32(c).to_le_bytes())
+                .collect();
+            device.create_texture_with_data(
+                queue,
+                &descriptor,
+                wgpu::util::TextureDataOrder::LayerMajor,
+                &bytes,
+            )
         }
+        // New textures start zeroed, which is black.
+        _ => device.create_texture(&descriptor),
+    };
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_compute_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    output_view: &wgpu::TextureView,
+    body_buffer: &wgpu::Buffer,
+    accum_buffer: &wgpu::Buffer,
+    sky: &SkyResources,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Compute Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(output_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: body_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: accum_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(&sky.environment_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::Sampler(&sky.environment_sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: sky.star_index.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 7,
+                resource: sky.stars.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+/// File format for saved renders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageFormat {
+    /// Tonemapped 8-bit PNG.
+    #[default]
+    Png,
+    /// Linear HDR radiance as 32-bit float OpenEXR, for grading elsewhere.
+    Exr,
+}
+
+impl ImageFormat {
+    pub const ALL: [ImageFormat; 2] = [ImageFormat::Png, ImageFormat::Exr];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "PNG",
+            ImageFormat::Exr => "EXR (linear HDR)",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Exr => "exr",
+        }
+    }
+
+    /// The format whose extension `extension` is, ignoring case.
+    pub fn from_extension(extension: &str) -> Option<ImageFormat> {
+        Self::ALL
+            .into_iter()
+            .find(|format| extension.eq_ignore_ascii_case(format.extension()))
+    }
+
+    /// Write linear RGBA texels in this format. Only PNGs are
+    /// post-processed; EXRs keep the raw radiance.
+    pub fn save(
+        self,
+        texels: &[[f32; 4]],
+        width: u32,
+        height: u32,
+        post: &PostSettings,
+        path: &std::path::Path,
+    ) -> Option<PathBuf> {
+        match self {
+            ImageFormat::Png => save_png(texels, width, height, post, path),
+            ImageFormat::Exr => save_exr(texels, width, height, path),
+        }
+    }
+}
+
+/// Post-process linear RGBA texels as the window does and write them as an
+/// 8-bit PNG.
+pub fn save_png(
+    texels: &[[f32; 4]],
+    width: u32,
+    height: u32,
+    post: &PostSettings,
+    path: &std::path::Path,
+) -> Option<PathBuf> {
+    let mut pixels = Vec::with_capacity(texels.len() * 4);
+    for texel in post::apply(texels, width, height, post) {
+        for channel in texel {
+            pixels.push((channel.clamp(0.0, 1.0) * 255.0) as u8);
+        }
+    }
+
+    create_parent_dir(path);
+    match image::save_buffer(path, &pixels, width, height, image::ColorType::Rgba8) {
+        Ok(()) => {
+            log::info!("Screenshot saved to {}", path.display());
+            Some(path.to_path_buf())
+        }
+        Err(e) => {
+            log::error!("Failed to save screenshot: {}", e);
+            None
+        }
+    }
+}
+
+/// Write linear RGBA texels untouched as a 32-bit float OpenEXR image.
+pub fn save_exr(
+    texels: &[[f32; 4]],
+    width: u32,
+    height: u32,
+    path: &std::path::Path,
+) -> Option<PathBuf> {
+    let image = image::Rgba32FImage::from_raw(width, height, texels.as_flattened().to_vec())?;
+
+    create_parent_dir(path);
+    match image.save_with_format(path, image::ImageFormat::OpenExr) {
+        Ok(()) => {
+            log::info!("Screenshot saved to {}", path.display());
+            Some(path.to_path_buf())
+        }
+        Err(e) => {
+            log::error!("Failed to save screenshot: {}", e);
+            None
+        }
+    }
+}
+
+fn create_parent_dir(path: &std::path::Path) {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::gpu_test::{display_mismatch, test_gpu, GpuCase, MAX_MISMATCH};
+    use crate::renderer::reference::ReferenceTracer;
+    use crate::renderer::tonemap::Tonemapper;
+
+    /// A single black hole and its disk seen from slightly above.
+    fn scene(width: u32, height: u32) -> (Uniforms, Vec<GpuBody>) {
+        GpuCase {
+            camera_distance: 8.0,
+            camera_elevation: 1.4,
+            ..Default::default()
+        }
+        .scene(width, height)
+    }
+
+    #[test]
+    fn exr_keeps_linear_hdr_values() {
+        let texels = [[0.25, 1.0, 40.0, 1.0], [0.0, 3.5, 0.125, 1.0]];
+        let path = std::env::temp_dir().join("spacetime_playground_hdr_test.exr");
+        ImageFormat::Exr
+            .save(&texels, 2, 1, &PostSettings::default(), &path)
+            .unwrap();
+
+        let read = image::open(&path).unwrap().into_rgba32f();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(read.as_raw().as_slice(), texels.as_flattened());
+    }
+
+    #[test]
+    fn gpu_composite_matches_cpu_post_processing() {
+        let Some((device, queue)) = test_gpu() else {
+            return;
+        };
+
+        // 64 RGBA8 texels make an aligned 256-byte row for the readback.
+        let (width, height) = (64, 36);
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let mut pipeline = RayMarchPipeline::new(&device, format, width, height);
+        let (uniforms, bodies) = scene(width, height);
+        let settings = PostSettings {
+            exposure: 0.5,
+            tonemapper: Tonemapper::Agx,
+            bloom_strength: 0.8,
+            bloom_threshold: 0.5,
+        };
+        pipeline.update_bodies(&device, &queue, &bodies);
+        pipeline.update_uniforms(&queue, &uniforms);
+        pipeline.update_post(&queue, &settings);
+
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (width * height * 4) as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        pipeline.dispatch_compute(&mut encoder);
+        pipeline.dispatch_post(&mut encoder);
+        pipeline.render_fullscreen(&mut encoder, &target.create_view(&Default::default()));
+        encoder.copy_texture_to_buffer(
+            target.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(width * 4),
+                    rows_per_image: Some(height),
+                },
+            },
+            target.size(),
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+        readback.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+        device.poll(wgpu::Maintain::Wait);
+        let gpu = readback.slice(..).get_mapped_range().to_vec();
+
+        let hdr = pipeline.read_output(&device, &queue).unwrap();
+        let cpu = post::apply(&hdr, width, height, &settings);
+        let mismatched = gpu
+            .chunks(4)
+            .zip(&cpu)
+            .filter(|(g, c)| (0..3).any(|i| (g[i] as f32 / 255.0 - c[i]).abs() > 0.02))
+            .count();
+        assert!(
+            mismatched * 100 < cpu.len(),
+            "{} of {} pixels differ",
+            mismatched,
+            cpu.len()
+        );
+        // The disk is bright enough to bloom.
+        let plain = post::apply(
+            &hdr,
+            width,
+            height,
+            &PostSettings {
+                bloom_strength: 0.0,
+                ..settings
+            },
+        );
+        assert_ne!(plain, cpu);
+    }
+
+    #[test]
+    fn accumulated_samples_match_jittered_reference_and_reset_on_change() {
+        let Some((device, queue)) = test_gpu() else {
+            return;
+        };
+        let (width, height) = (64, 36);
+        let mut pipeline =
+            RayMarchPipeline::new(&device, wgpu::TextureFormat::Rgba8Unorm, width, height);
+        let (base, bodies) = scene(width, height);
+        pipeline.update_bodies(&device, &queue, &bodies);
+
+        for _ in 0..4 {
+            let mut uniforms = Uniforms { time: 1.5, ..base };
+            assert!(pipeline.next_sample(&mut uniforms, &bodies, true));
+            pipeline.update_uniforms(&queue, &uniforms);
+            let mut encoder = device.create_command_encoder(&Default::default());
+            pipeline.dispatch_compute(&mut encoder);
+            queue.submit(std::iter::once(encoder.finish()));
+        }
+        assert_eq!(pipeline.samples(), 4);
+
+        let display = |texels: &[[f32; 4]]| post::apply(texels, width, height, &Default::default());
+        let gpu = display(&pipeline.read_output(&device, &queue).unwrap());
+        let reference = ReferenceTracer::new(&Uniforms { time: 1.5, ..base }, &bodies);
+        let cpu = display(&reference.render_accumulated(width, height, 4));
+        let single = display(&reference.render(width, height));
+        // Like the single-sample comparison, allow edge pixels to differ.
+        assert!(display_mismatch(&gpu, &cpu) < MAX_MISMATCH);
+        assert!(display_mismatch(&gpu, &single) > display_mismatch(&gpu, &cpu));
+
+        // Moving the camera starts over; so does turning accumulation off.
+        let mut moved = Uniforms {
+            camera_pos: [0.0, 1.0, 8.0, 0.0],
+            ..base
+        };
+        pipeline.next_sample(&mut moved, &bodies, true);
+        assert_eq!((moved.sample_index, moved.jitter), (0, [0.0; 2]));
+        pipeline.next_sample(&mut moved, &bodies, true);
+        assert_eq!(moved.sample_index, 1);
+        pipeline.next_sample(&mut moved, &bodies, false);
+        assert_eq!(moved.sample_index, 0);
     }
 }
@@ -0,0 +1,491 @@
+//! Post-processing between the ray march and the display: exposure, bloom
+//! and tonemapping. The compute output stays linear HDR; `PostProcess` runs
+//! the bloom mip chain of `shaders/bloom.wgsl` on the GPU for the window,
+//! and `apply` reproduces the whole stage on the CPU for saved images.
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use wgpu::util::DeviceExt;
+
+use super::tonemap::Tonemapper;
+use super::uniforms::RenderMode;
+
+/// Levels in the bloom mip chain, the first at half resolution.
+pub const BLOOM_LEVELS: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PostSettings {
+    /// Exposure in stops; each one doubles the brightness.
+    pub exposure: f32,
+    pub tonemapper: Tonemapper,
+    /// Bloom added on top of the image, 0 for none.
+    pub bloom_strength: f32,
+    /// Exposed luminance above which pixels bloom.
+    pub bloom_threshold: f32,
+}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            tonemapper: Tonemapper::Aces,
+            bloom_strength: 0.0,
+            bloom_threshold: 1.0,
+        }
+    }
+}
+
+impl PostSettings {
+    /// Settings that show the render as it is.
+    pub const NONE: Self = Self {
+        exposure: 0.0,
+        tonemapper: Tonemapper::None,
+        bloom_strength: 0.0,
+        bloom_threshold: 1.0,
+    };
+
+    /// Settings to display `mode` with: these for the shaded image, none for
+    /// the false-colour views, so they keep the colours of their legends.
+    pub fn for_mode(&self, mode: RenderMode) -> Self {
+        match mode {
+            RenderMode::Shaded => *self,
+            _ => Self::NONE,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+pub struct PostUniforms {
+    /// Linear exposure multiplier.
+    pub exposure: f32,
+    pub tonemapper: u32,
+    pub bloom_strength: f32,
+    pub bloom_threshold: f32,
+    pub bloom_levels: u32,
+    pub _pad: [u32; 3],
+}
+
+impl From<&PostSettings> for PostUniforms {
+    fn from(settings: &PostSettings) -> Self {
+        Self {
+            exposure: settings.exposure.exp2(),
+            tonemapper: settings.tonemapper as u32,
+            bloom_strength: settings.bloom_strength,
+            bloom_threshold: settings.bloom_threshold,
+            bloom_levels: BLOOM_LEVELS as u32,
+            _pad: [0; 3],
+        }
+    }
+}
+
+/// Size of each bloom level for a `width × height` image.
+fn level_sizes(width: u32, height: u32) -> [(u32, u32); BLOOM_LEVELS] {
+    let mut size = (width, height);
+    std::array::from_fn(|_| {
+        size = (size.0.div_ceil(2).max(1), size.1.div_ceil(2).max(1));
+        size
+    })
+}
+
+// ── CPU implementation ───────────────────────────────────────────────────
+
+/// RGB image with the clamped and bilinear lookups of `bloom.wgsl`.
+struct Plane {
+    width: u32,
+    height: u32,
+    data: Vec<Vec3>,
+}
+
+impl Plane {
+    fn new(width: u32, height: u32, f: impl Fn(i32, i32) -> Vec3) -> Self {
+        let mut data = Vec::with_capacity((width * height) as usize);
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                data.push(f(x, y));
+            }
+        }
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    fn load(&self, x: i32, y: i32) -> Vec3 {
+        let x = x.clamp(0, self.width as i32 - 1) as u32;
+        let y = y.clamp(0, self.height as i32 - 1) as u32;
+        self.data[(y * self.width + x) as usize]
+    }
+
+    fn bilinear(&self, u: f32, v: f32) -> Vec3 {
+        let (px, py) = (u * self.width as f32 - 0.5, v * self.height as f32 - 0.5);
+        let (x0, y0) = (px.floor(), py.floor());
+        let (fx, fy) = (px - x0, py - y0);
+        let (x, y) = (x0 as i32, y0 as i32);
+        let top = self.load(x, y).lerp(self.load(x + 1, y), fx);
+        let bottom = self.load(x, y + 1).lerp(self.load(x + 1, y + 1), fx);
+        top.lerp(bottom, fy)
+    }
+
+    /// Tent-filtered half-resolution copy, as `downsample_at`.
+    fn downsample(&self, (width, height): (u32, u32), pass: impl Fn(Vec3) -> Vec3) -> Self {
+        let weight = |i: i32| if i == 0 || i == 3 { 0.125 } else { 0.375 };
+        Self::new(width, height, |x, y| {
+            let mut sum = Vec3::ZERO;
+            for j in 0..4 {
+                for i in 0..4 {
+                    let c = pass(self.load(2 * x + i - 1, 2 * y + j - 1));
+                    sum += weight(i) * weight(j) * c;
+                }
+            }
+            sum
+        })
+    }
+
+    /// This level plus `coarse` bilinearly upsampled onto it.
+    fn add_upsampled(&self, coarse: &Plane) -> Self {
+        let (w, h) = (self.width as f32, self.height as f32);
+        Self::new(self.width, self.height, |x, y| {
+            let (u, v) = ((x as f32 + 0.5) / w, (y as f32 + 0.5) / h);
+            self.load(x, y) + coarse.bilinear(u, v)
+        })
+    }
+}
+
+/// Sum of all bloom levels at half resolution, as the GPU chain leaves it.
+fn bloom(image: &Plane, uniforms: &PostUniforms) -> Plane {
+    let bright_pass = |c: Vec3| {
+        let e = c * uniforms.exposure;
+        let l = e.dot(Vec3::new(0.2126, 0.7152, 0.0722));
+        e * ((l - uniforms.bloom_threshold).max(0.0) / l.max(1e-4))
+    };
+    let sizes = level_sizes(image.width, image.height);
+    let mut down = vec![image.downsample(sizes[0], bright_pass)];
+    for &size in &sizes[1..] {
+        let next = down.last().unwrap().downsample(size, |c| c);
+        down.push(next);
+    }
+    let mut up = down.pop().unwrap();
+    while let Some(level) = down.pop() {
+        up = level.add_upsampled(&up);
+    }
+    up
+}
+
+/// Exposure, bloom and tonemapping of linear RGBA texels, giving the 0..1
+/// display values the window shows.
+pub fn apply(
+    texels: &[[f32; 4]],
+    width: u32,
+    height: u32,
+    settings: &PostSettings,
+) -> Vec<[f32; 4]> {
+    let uniforms = PostUniforms::from(settings);
+    let image = Plane::new(width, height, |x, y| {
+        let [r, g, b, _] = texels[(y as u32 * width + x as u32) as usize];
+        Vec3::new(r, g, b)
+    });
+    let glow = (settings.bloom_strength > 0.0).then(|| bloom(&image, &uniforms));
+
+    let (w, h) = (width as f32, height as f32);
+    let mut out = Vec::with_capacity(texels.len());
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+            let mut color = image.data[i] * uniforms.exposure;
+            if let Some(glow) = &glow {
+                let (u, v) = ((x as f32 + 0.5) / w, (y as f32 + 0.5) / h);
+                color += settings.bloom_strength * glow.bilinear(u, v) / BLOOM_LEVELS as f32;
+            }
+            let c = settings.tonemapper.apply(color);
+            out.push([c.x, c.y, c.z, texels[i][3]]);
+        }
+    }
+    out
+}
+
+// ── GPU implementation ───────────────────────────────────────────────────
+
+/// Bloom mip chain over the ray-march output, plus the uniforms the
+/// fullscreen composite reads.
+pub struct PostProcess {
+    pub uniform_buffer: wgpu::Buffer,
+    prefilter: wgpu::ComputePipeline,
+    downsample: wgpu::ComputePipeline,
+    upsample: wgpu::ComputePipeline,
+    /// Downsampled levels, then the upsampled sums for all but the coarsest.
+    _textures: Vec<wgpu::Texture>,
+    bloom_view: wgpu::TextureView,
+    /// Pipeline, bind group and output size of each pass, in order.
+    passes: Vec<(Pass, wgpu::BindGroup, (u32, u32))>,
+    settings: PostSettings,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Pass {
+    Prefilter,
+    Downsample,
+    Upsample,
+}
+
+impl PostProcess {
+    pub fn new(
+        device: &wgpu::Device,
+        hdr_view: &wgpu::TextureView,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let uniforms = PostUniforms::from(&PostSettings::default());
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Post Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Bloom Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../../shaders/bloom.wgsl").into()),
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bloom Bind Group Layout"),
+            entries: &[
+                // Level being read
+                texture_entry(0),
+                // Coarser sum, for upsampling
+                texture_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba16Float,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Bloom Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Bloom Pipeline"),
+                layout: Some(&layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        let sizes = level_sizes(width, height);
+        let create_level = |&(width, height): &(u32, u32)| {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Bloom Level"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba16Float,
+                usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            (texture, view)
+        };
+        let down: Vec<_> = sizes.iter().map(create_level).collect();
+        let up: Vec<_> = sizes[..BLOOM_LEVELS - 1].iter().map(create_level).collect();
+
+        let bind_group =
+            |src: &wgpu::TextureView, coarse: &wgpu::TextureView, dst: &wgpu::TextureView| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Bloom Bind Group"),
+                    layout: &bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(src),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(coarse),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: wgpu::BindingResource::TextureView(dst),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: uniform_buffer.as_entire_binding(),
+                        },
+                    ],
+                })
+            };
+
+        let mut passes = vec![(
+            Pass::Prefilter,
+            bind_group(hdr_view, hdr_view, &down[0].1),
+            sizes[0],
+        )];
+        for i in 1..BLOOM_LEVELS {
+            let src = &down[i - 1].1;
+            passes.push((Pass::Downsample, bind_group(src, src, &down[i].1), sizes[i]));
+        }
+        for i in (0..BLOOM_LEVELS - 1).rev() {
+            let coarse = if i == BLOOM_LEVELS - 2 {
+                &down[i + 1].1
+            } else {
+                &up[i + 1].1
+            };
+            passes.push((
+                Pass::Upsample,
+                bind_group(&down[i].1, coarse, &up[i].1),
+                sizes[i],
+            ));
+        }
+
+        let bloom_view = up[0].0.create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            uniform_buffer,
+            prefilter: pipeline("prefilter"),
+            downsample: pipeline("downsample"),
+            upsample: pipeline("upsample"),
+            _textures: down
+                .into_iter()
+                .chain(up)
+                .map(|(texture, _)| texture)
+                .collect(),
+            bloom_view,
+            passes,
+            settings: PostSettings::default(),
+        }
+    }
+
+    /// Half-resolution sum of the bloom levels.
+    pub fn bloom_view(&self) -> &wgpu::TextureView {
+        &self.bloom_view
+    }
+
+    /// Settings of the last `update`.
+    pub fn settings(&self) -> &PostSettings {
+        &self.settings
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, settings: &PostSettings) {
+        self.settings = *settings;
+        let uniforms = PostUniforms::from(settings);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    /// Run the bloom chain, if bloom is on.
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.settings.bloom_strength <= 0.0 {
+            return;
+        }
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Bloom Pass"),
+            timestamp_writes: None,
+        });
+        for (kind, bind_group, (w, h)) in &self.passes {
+            pass.set_pipeline(match kind {
+                Pass::Prefilter => &self.prefilter,
+                Pass::Downsample => &self.downsample,
+                Pass::Upsample => &self.upsample,
+            });
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.dispatch_workgroups(w.div_ceil(8), h.div_ceil(8), 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::uniforms::FATE_COLORS;
+
+    #[test]
+    fn bloom_spreads_only_light_above_the_threshold() {
+        let (width, height) = (64, 48);
+        let dim = vec![[0.5, 0.5, 0.5, 1.0]; (width * height) as usize];
+        let mut hot = dim.clone();
+        hot[(24 * width + 32) as usize] = [200.0, 100.0, 50.0, 1.0];
+        let plain = PostSettings {
+            tonemapper: Tonemapper::Reinhard,
+            ..PostSettings::default()
+        };
+        let bloom = PostSettings {
+            bloom_strength: 1.0,
+            ..plain
+        };
+        let at = |image: &[[f32; 4]], x: u32, y: u32| image[(y * width + x) as usize];
+
+        // Nothing is above the threshold, so bloom changes nothing.
+        assert_eq!(
+            apply(&dim, width, height, &bloom),
+            apply(&dim, width, height, &plain)
+        );
+        assert!((at(&apply(&dim, width, height, &plain), 2, 2)[0] - 1.0 / 3.0).abs() < 1e-6);
+
+        // The hot pixel glows onto its neighbours, keeping its hue.
+        let near = at(&apply(&hot, width, height, &bloom), 35, 24);
+        assert!(near[0] > 0.36, "no glow next to the hot pixel: {:?}", near);
+        assert!(near[0] > near[1] && near[1] > near[2]);
+
+        // Exposure is in stops: +1 doubles the background to 1, mapped to 1/2.
+        let brighter = PostSettings {
+            exposure: 1.0,
+            ..plain
+        };
+        assert!((at(&apply(&dim, width, height, &brighter), 2, 2)[0] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn false_colour_views_are_shown_as_rendered() {
+        let (width, height) = (5, 4);
+        let fates: Vec<_> = (0..width * height)
+            .map(|i| {
+                let [r, g, b] = FATE_COLORS[(i % 5) as usize];
+                [r, g, b, 1.0]
+            })
+            .collect();
+        let settings = PostSettings {
+            exposure: 2.0,
+            tonemapper: Tonemapper::Agx,
+            bloom_strength: 1.0,
+            bloom_threshold: 0.2,
+        };
+        let shown = |mode| apply(&fates, width, height, &settings.for_mode(mode));
+        assert_eq!(shown(RenderMode::Fate), fates);
+        assert_ne!(shown(RenderMode::Shaded), fates);
+    }
+}
@@ -0,0 +1,1782 @@
+//! CPU reference implementation of `shaders/ray_march.wgsl` in f64.
+//!
+//! Takes the same `Uniforms` and `GpuBody` inputs as the compute shader and
+//! follows it step for step (ray setup, fixed RK4 or adaptive Dormand–Prince
+//! integration, termination tests,
+//! thin or volumetric disk and background), so any pixel of the GPU image
+//! can be reproduced and checked on the CPU without f32 round-off.
+
+use std::sync::Arc;
+
+use glam::{DVec2, DVec3, DVec4};
+
+use super::environment::EnvironmentMap;
+use super::observer::{aberrate, doppler_factor};
+use super::sky::{blackbody, dir_to_spherical};
+use super::stars::{star_sigma, StarBins};
+use super::uniforms::{
+    ramp_color, redshift_color, sample_jitter, DiskModel, Integrator, RenderMode, Uniforms,
+    BODY_COLORS, DISK_ORDER_COLORS, FATE_COLORS,
+};
+use crate::metrics::geodesic::NullRay;
+use crate::metrics::{self, GeodesicModel, Metric};
+use crate::simulation::GpuBody;
+
+const PI: f64 = std::f64::consts::PI;
+const ESCAPE_RADIUS: f64 = 50.0;
+const MAX_NEARBY: usize = 32;
+/// Bodies whose disk image orders are told apart; later ones share a count.
+const MAX_ORDER_BODIES: usize = 512;
+const DISK_EXTENT: f64 = 4.0;
+const DISK_SAMPLES_PER_HEIGHT: f64 = 2.0;
+const MAX_DISK_SAMPLES: u32 = 16;
+const MIN_TRANSMITTANCE: f64 = 1e-3;
+
+#[derive(Debug, Clone, Copy)]
+struct RefBody {
+    position: DVec3,
+    rs: f64,
+    disk_inner: f64,
+    disk_outer: f64,
+    spin: f64,
+    isco: f64,
+    disk_normal: DVec3,
+}
+
+impl From<&GpuBody> for RefBody {
+    fn from(body: &GpuBody) -> Self {
+        Self {
+            position: DVec3::new(
+                body.position[0] as f64,
+                body.position[1] as f64,
+                body.position[2] as f64,
+            ),
+            rs: body.rs as f64,
+            disk_inner: body.disk_inner as f64,
+            disk_outer: body.disk_outer as f64,
+            spin: body.spin as f64,
+            isco: body.isco as f64,
+            disk_normal: vec3(body.disk_normal),
+        }
+    }
+}
+
+/// How a traced ray ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RayFate {
+    /// Crossed the horizon of the body with this index.
+    Captured(usize),
+    /// Left the escape radius of every body.
+    Escaped,
+    /// Ran out of steps; the shader then decides by whether the ray is
+    /// heading away from all bodies.
+    OutOfSteps { heading_away: bool },
+    /// Lost nearly all its light behind bright disk images or in
+    /// volumetric disk gas.
+    Absorbed,
+}
+
+impl RayFate {
+    /// Whether the shader shades this ray with the background.
+    pub fn reaches_sky(self) -> bool {
+        matches!(
+            self,
+            RayFate::Escaped | RayFate::OutOfSteps { heading_away: true }
+        )
+    }
+
+    /// Index into `FATE_COLORS`.
+    pub fn index(self) -> usize {
+        match self {
+            RayFate::Escaped => 0,
+            RayFate::Captured(_) => 1,
+            RayFate::OutOfSteps { heading_away: true } => 2,
+            RayFate::OutOfSteps {
+                heading_away: false,
+            } => 3,
+            RayFate::Absorbed => 4,
+        }
+    }
+}
+
+/// Result of tracing a single ray.
+#[derive(Debug, Clone)]
+pub struct RayTrace {
+    /// Linear HDR colour, as written to the output texture.
+    pub color: DVec3,
+    pub fate: RayFate,
+    /// Number of integration steps taken, including rejected ones.
+    pub steps: u32,
+    /// Adaptive steps rejected by the error control.
+    pub rejected_steps: u32,
+    pub final_position: DVec3,
+    /// Final direction (or covariant momentum on the Kerr–Schild path).
+    pub final_direction: DVec3,
+    /// Body index and world-space point of each crossing of a body's disk
+    /// plane, in order; a disk seen at the k-th crossing of its own plane is
+    /// its image of order k.
+    pub disk_crossings: Vec<(usize, DVec3)>,
+    /// Where the ray first meets a disk, if it does.
+    pub disk_hit: Option<DiskHit>,
+    /// Fraction of the light from behind that gets through the disk.
+    pub transmittance: f64,
+}
+
+/// The first point where a ray meets disk light: a thin disk image or the
+/// first volumetric gas sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiskHit {
+    /// Index of the body the disk belongs to.
+    pub body: usize,
+    pub position: DVec3,
+    /// Distance from the body in its disk plane.
+    pub radius: f64,
+    /// Frequency ratio g = ν_obs/ν_emit of the disk light there.
+    pub redshift: f64,
+}
+
+/// f64 mirror of the ray-march compute shader.
+pub struct ReferenceTracer {
+    uniforms: Uniforms,
+    bodies: Vec<RefBody>,
+    /// Spacetime of each body, which decides when a ray is captured or has
+    /// escaped.
+    metrics: Arc<[Box<dyn Metric>]>,
+    /// Background image of mode 2; black without one, like the GPU's
+    /// placeholder.
+    environment: Option<Arc<EnvironmentMap>>,
+    /// Binned catalogue of background mode 3; an empty sky without one.
+    stars: Option<Arc<StarBins>>,
+}
+
+impl ReferenceTracer {
+    pub fn new(uniforms: &Uniforms, bodies: &[GpuBody]) -> Self {
+        let count = (uniforms.num_bodies as usize).min(bodies.len());
+        let bodies: Vec<RefBody> = bodies[..count].iter().map(RefBody::from).collect();
+        Self {
+            uniforms: *uniforms,
+            metrics: bodies
+                .iter()
+                .map(|b| metrics::black_hole(b.rs, b.spin))
+                .collect(),
+            bodies,
+            environment: None,
+            stars: None,
+        }
+    }
+
+    /// Sample `environment` where background mode 2 shows the sky.
+    pub fn with_environment(self, environment: Option<Arc<EnvironmentMap>>) -> Self {
+        Self {
+            environment,
+            ..self
+        }
+    }
+
+    /// Draw these catalogue stars where background mode 3 shows the sky.
+    pub fn with_stars(self, stars: Option<Arc<StarBins>>) -> Self {
+        Self { stars, ..self }
+    }
+
+    /// Render a full image as row-major RGBA texels, like the output texture.
+    pub fn render(&self, width: u32, height: u32) -> Vec<[f32; 4]> {
+        let mut texels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let c = self.trace_pixel(x, y, width, height).color;
+                texels.push([c.x as f32, c.y as f32, c.z as f32, 1.0]);
+            }
+        }
+        texels
+    }
+
+    /// Average of `samples` renders with the jitter the GPU uses for each
+    /// progressive sample.
+    pub fn render_accumulated(&self, width: u32, height: u32, samples: u32) -> Vec<[f32; 4]> {
+        let mut sum = vec![[0.0_f32; 4]; (width * height) as usize];
+        for i in 0..samples.max(1) {
+            let tracer = Self {
+                uniforms: Uniforms {
+                    jitter: sample_jitter(i),
+                    ..self.uniforms
+                },
+                bodies: self.bodies.clone(),
+                metrics: self.metrics.clone(),
+                environment: self.environment.clone(),
+                stars: self.stars.clone(),
+            };
+            for (total, texel) in sum.iter_mut().zip(tracer.render(width, height)) {
+                for c in 0..4 {
+                    total[c] += texel[c];
+                }
+            }
+        }
+        let n = samples.max(1) as f32;
+        sum.into_iter().map(|texel| texel.map(|c| c / n)).collect()
+    }
+
+    /// Primary ray through pixel (x, y) of a `width × height` image, offset
+    /// from the centre by the uniforms' jitter, in the frame of the static
+    /// observer at the camera.
+    pub fn pixel_ray(&self, x: u32, y: u32, width: u32, height: u32) -> DVec3 {
+        let (w, h) = (width as f64, height as f64);
+        let [jx, jy] = self.uniforms.jitter.map(|j| j as f64);
+        let ndc = DVec2::new(
+            (x as f64 + 0.5 + jx - w * 0.5) / (w * 0.5),
+            -(y as f64 + 0.5 + jy - h * 0.5) / (h * 0.5),
+        );
+        let aspect = w / h;
+        let half_fov = (self.uniforms.fov as f64 * 0.5).tan();
+        let local = (vec3(self.uniforms.camera_forward)
+            + ndc.x * aspect * half_fov * vec3(self.uniforms.camera_right)
+            + ndc.y * half_fov * vec3(self.uniforms.camera_up))
+        .normalize();
+        aberrate(local, vec3(self.uniforms.observer_velocity))
+    }
+
+    pub fn trace_pixel(&self, x: u32, y: u32, width: u32, height: u32) -> RayTrace {
+        let dir = self.pixel_ray(x, y, width, height);
+        self.trace_ray(vec3(self.uniforms.camera_pos), dir)
+    }
+
+    /// Trace a ray from `origin` along the unit direction `dir`, seen by an
+    /// observer moving with the camera's velocity.
+    pub fn trace_ray(&self, origin: DVec3, dir: DVec3) -> RayTrace {
+        self.trace(origin, dir, None)
+    }
+
+    /// Trace a ray like `trace_ray`, also returning its path: `origin` and
+    /// the position after every accepted step.
+    pub fn trace_path(&self, origin: DVec3, dir: DVec3) -> (RayTrace, Vec<DVec3>) {
+        let mut path = vec![origin];
+        let trace = self.trace(origin, dir, Some(&mut path));
+        (trace, path)
+    }
+
+    fn trace(&self, origin: DVec3, dir: DVec3, mut path: Option<&mut Vec<DVec3>>) -> RayTrace {
+        let u = &self.uniforms;
+        let doppler = doppler_factor(dir, vec3(u.observer_velocity));
+        let mut pos = origin;
+        let mut vel = dir;
+
+        let use_kerr = u.geodesic_model == GeodesicModel::KerrSchild as u32;
+        if use_kerr {
+            vel = self.kerr_initial_momentum(pos, dir);
+        }
+
+        // The null-geodesic model follows body 0 through the `Metric` trait,
+        // with Christoffel symbols taken numerically rather than from the
+        // shader's hand-contracted expression.
+        let use_null = u.geodesic_model == GeodesicModel::NullGeodesic as u32;
+        let body0 = self.bodies.first().copied();
+        let null_metric = body0
+            .filter(|_| use_null)
+            .map(|b| metrics::black_hole(b.rs, b.spin));
+        let mut state = RayState {
+            x: pos.extend(0.0),
+            v: vel.extend(0.0),
+        };
+        let mut observed_frequency = 1.0 / self.potential_redshift(pos);
+        if let (Some(metric), Some(b0)) = (&null_metric, body0) {
+            let ray = NullRay::launch(metric.as_ref(), pos - b0.position, dir);
+            observed_frequency = ray.static_frequency(metric.as_ref());
+            state.v = from_event(ray.k);
+        }
+        let mut split = self.split_field(&state, 0.0);
+        let split_far = u.body_cutoff > 0.0 && !use_null;
+
+        let adaptive = u.integrator == Integrator::AdaptiveRk45 as u32;
+        let (min_step, max_step) = (u.min_step as f64, u.max_step as f64);
+        let mut h = u.step_size as f64;
+        let mut d_first = RayState::default();
+        if adaptive {
+            h = h.clamp(min_step, max_step);
+            d_first = self.ray_derivs(&state, null_metric.as_deref(), &split);
+        }
+        let mut fate = None;
+
+        let mut disk_color_accum = DVec3::ZERO;
+        let mut disk_crossings = Vec::new();
+        let mut disk_hit = None;
+        let mut plane_crossings = vec![0; self.bodies.len().min(MAX_ORDER_BODIES)];
+        let volumetric = u.disk_enabled == 1 && u.disk_model == DiskModel::Volumetric as u32;
+        let disk_order = u.render_mode == RenderMode::DiskOrder as u32;
+        let mut transmittance = 1.0;
+
+        let mut grid_accum_color = DVec3::ZERO;
+        let mut grid_accum_alpha = 0.0;
+        let grid_line_color = DVec3::new(0.1, 0.6, 0.8);
+        let grid_step_alpha = 0.12;
+
+        let mut steps = 0;
+        let mut rejected_steps = 0;
+        while steps < u.max_steps {
+            if let Some(body) = self.check_capture(pos) {
+                fate = Some(RayFate::Captured(body));
+                break;
+            }
+            if self.check_escape(pos) {
+                fate = Some(RayFate::Escaped);
+                break;
+            }
+
+            let pos_before = pos;
+            let h_taken = h;
+            steps += 1;
+
+            if split_far {
+                split = self.split_field(&state, 2.0 * h * vel.length().max(1.0));
+                if adaptive {
+                    d_first = self.ray_derivs(&state, null_metric.as_deref(), &split);
+                }
+            }
+            let derivs = |s: &RayState| self.ray_derivs(s, null_metric.as_deref(), &split);
+            if adaptive {
+                let (next, err, d_next) = dormand_prince(&derivs, &state, &d_first, h);
+                let err = self.step_error(&state, &err).max(1e-10);
+                let factor = 0.9 * err.powf(-0.2);
+                if err > 1.0 && h > min_step {
+                    h = (h * factor.max(0.2)).max(min_step);
+                    rejected_steps += 1;
+                    continue;
+                }
+                (state, d_first) = (next, d_next);
+                h = (h * factor.min(5.0)).clamp(min_step, max_step);
+            } else {
+                state = rk4(&derivs, &state, h);
+            }
+            (pos, vel) = (state.x.truncate(), state.v.truncate());
+            if let Some(path) = &mut path {
+                path.push(pos);
+            }
+
+            if u.grid_enabled == 1 {
+                let weight = if adaptive {
+                    h_taken / u.step_size as f64
+                } else {
+                    1.0
+                };
+                let line_alpha =
+                    (grid_strength_at_pos(pos_before) * grid_step_alpha * weight).min(1.0);
+                grid_accum_color += (1.0 - grid_accum_alpha) * line_alpha * grid_line_color;
+                grid_accum_alpha += (1.0 - grid_accum_alpha) * line_alpha;
+            }
+
+            if volumetric {
+                let n = self.disk_samples(pos_before, pos);
+                let ds = (pos - pos_before).length() / n.max(1) as f64;
+                for j in 0..n {
+                    let p = pos_before.lerp(pos, (j as f64 + 0.5) / n as f64);
+                    let emission = self.emission(
+                        p,
+                        &state,
+                        null_metric.as_deref(),
+                        observed_frequency,
+                        doppler,
+                    );
+                    for (b, body) in self.bodies.iter().enumerate() {
+                        let (absorption, heat) = self.disk_gas(body, p);
+                        if absorption > 0.0 {
+                            disk_hit.get_or_insert_with(|| self.disk_hit(b, p, &emission));
+                            let mut source = self.disk_color_for_body(p, body, &emission, heat);
+                            if disk_order {
+                                source = order_color(plane_crossings[b.min(MAX_ORDER_BODIES - 1)]);
+                            }
+                            let absorbed = 1.0 - (-absorption * ds).exp();
+                            disk_color_accum += transmittance * absorbed * source;
+                            transmittance *= 1.0 - absorbed;
+                        }
+                    }
+                }
+            }
+
+            if u.disk_enabled == 1 {
+                for (b, body) in self.bodies.iter().enumerate() {
+                    let h_before = (pos_before - body.position).dot(body.disk_normal);
+                    let h_after = (pos - body.position).dot(body.disk_normal);
+                    if h_before * h_after >= 0.0 {
+                        continue;
+                    }
+                    let slot = b.min(MAX_ORDER_BODIES - 1);
+                    let t_cross = h_before.abs() / (h_before.abs() + h_after.abs());
+                    let cross_pos = pos_before + t_cross * (pos - pos_before);
+                    let r_disk = disk_coords(cross_pos - body.position, body.disk_normal).x;
+                    if !volumetric && r_disk > body.disk_inner && r_disk < body.disk_outer {
+                        let emission = self.emission(
+                            cross_pos,
+                            &state,
+                            null_metric.as_deref(),
+                            observed_frequency,
+                            doppler,
+                        );
+                        disk_hit.get_or_insert_with(|| self.disk_hit(b, cross_pos, &emission));
+                        let mut layer = self.disk_color_for_body(cross_pos, body, &emission, 1.0);
+                        let opacity = layer.max_element().clamp(0.0, 1.0);
+                        if disk_order {
+                            layer = order_color(plane_crossings[slot]);
+                        }
+                        disk_color_accum += transmittance * opacity * layer;
+                        transmittance *= 1.0 - opacity;
+                    }
+                    plane_crossings[slot] += 1;
+                    disk_crossings.push((b, cross_pos));
+                }
+            }
+
+            if transmittance < MIN_TRANSMITTANCE {
+                fate = Some(RayFate::Absorbed);
+                break;
+            }
+        }
+
+        let fate = fate.unwrap_or_else(|| RayFate::OutOfSteps {
+            heading_away: self
+                .bodies
+                .iter()
+                .all(|body| vel.dot(pos - body.position) >= 0.0),
+        });
+
+        let sky = || {
+            let angles = dir_to_spherical(vel.normalize());
+            self.background(angles.x, angles.y) * doppler.powf(u.intensity_exponent as f64)
+        };
+        let over_grid = |behind: DVec3| {
+            if u.grid_enabled == 1 {
+                grid_accum_color + (1.0 - grid_accum_alpha) * behind
+            } else {
+                behind
+            }
+        };
+
+        let dimmed_sky = || {
+            if fate.reaches_sky() {
+                dimmed(sky())
+            } else {
+                DVec3::ZERO
+            }
+        };
+
+        let mode = RenderMode::ALL
+            .into_iter()
+            .find(|&mode| mode as u32 == u.render_mode)
+            .unwrap_or_default();
+        let color = match mode {
+            RenderMode::Steps => rgb(ramp_color(steps as f32 / u.max_steps.max(1) as f32)),
+            RenderMode::Deflection if fate.reaches_sky() => {
+                let cos_angle = dir.dot(vel.normalize()).clamp(-1.0, 1.0);
+                rgb(ramp_color((cos_angle.acos() / PI) as f32))
+            }
+            RenderMode::Deflection => DVec3::ZERO,
+            RenderMode::Fate => rgb(FATE_COLORS[fate.index()]),
+            RenderMode::Redshift => match disk_hit {
+                Some(hit) => rgb(redshift_color(hit.redshift as f32)),
+                None => dimmed_sky(),
+            },
+            RenderMode::Body => match fate {
+                RayFate::Captured(b) => rgb(BODY_COLORS[b % BODY_COLORS.len()]),
+                _ => dimmed_sky(),
+            },
+            RenderMode::Shaded | RenderMode::DiskOrder if fate.reaches_sky() => {
+                let behind = if disk_order { dimmed(sky()) } else { sky() };
+                over_grid(disk_color_accum + transmittance * behind)
+            }
+            RenderMode::Shaded | RenderMode::DiskOrder => disk_color_accum,
+        };
+
+        RayTrace {
+            color,
+            fate,
+            steps,
+            rejected_steps,
+            final_position: pos,
+            final_direction: vel,
+            disk_crossings,
+            disk_hit,
+            transmittance,
+        }
+    }
+
+    // ── Integration ──────────────────────────────────────────────────────
+
+    /// Bodies within `reach` of a step from `s`, and the far field held over
+    /// that step, as gathered by `gather_nearby` in the shader. Without a
+    /// cutoff every body is near.
+    fn split_field(&self, s: &RayState, reach: f64) -> FieldSplit {
+        let cutoff = self.uniforms.body_cutoff as f64;
+        if cutoff <= 0.0 {
+            return FieldSplit {
+                near: (0..self.bodies.len()).collect(),
+                far: RayState::default(),
+            };
+        }
+        let (pos, vel) = (s.x.truncate(), s.v.truncate());
+        let kerr = self.uniforms.geodesic_model == GeodesicModel::KerrSchild as u32;
+        let mut split = FieldSplit::default();
+        for (i, body) in self.bodies.iter().enumerate() {
+            let near = (pos - body.position).length() < cutoff * body.rs + reach;
+            if near && split.near.len() < MAX_NEARBY {
+                split.near.push(i);
+            } else if kerr {
+                let (dx, dp) = kerr_body_derivs(body, pos, vel);
+                split.far.x += dx.extend(0.0);
+                split.far.v += dp.extend(0.0);
+            } else {
+                split.far.v += body_acceleration(body, pos, vel).extend(0.0);
+            }
+        }
+        split
+    }
+
+    fn gravitational_acceleration(&self, pos: DVec3, vel: DVec3, split: &FieldSplit) -> DVec3 {
+        let mut accel = split.far.v.truncate();
+        for &i in &split.near {
+            accel += body_acceleration(&self.bodies[i], pos, vel);
+        }
+        accel
+    }
+
+    fn kerr_derivs(&self, pos: DVec3, p: DVec3, split: &FieldSplit) -> (DVec3, DVec3) {
+        let mut dx = p + split.far.x.truncate();
+        let mut dp = split.far.v.truncate();
+        for &i in &split.near {
+            let (body_dx, body_dp) = kerr_body_derivs(&self.bodies[i], pos, p);
+            dx += body_dx;
+            dp += body_dp;
+        }
+        (dx, dp)
+    }
+
+    /// Derivative of the shared integration state under the active model.
+    fn ray_derivs(
+        &self,
+        s: &RayState,
+        null_metric: Option<&dyn metrics::Metric>,
+        split: &FieldSplit,
+    ) -> RayState {
+        let (pos, vel) = (s.x.truncate(), s.v.truncate());
+        if let (Some(metric), Some(b0)) = (null_metric, self.bodies.first()) {
+            let d = pos - b0.position;
+            let ray = NullRay {
+                x: [s.x.w, d.x, d.y, d.z],
+                k: to_event(s.v),
+            };
+            return RayState {
+                x: s.v,
+                v: from_event(ray.acceleration(metric)),
+            };
+        }
+        if self.uniforms.geodesic_model == GeodesicModel::KerrSchild as u32 {
+            let (dx, dp) = self.kerr_derivs(pos, vel, split);
+            return RayState {
+                x: dx.extend(0.0),
+                v: dp.extend(0.0),
+            };
+        }
+        RayState {
+            x: vel.extend(0.0),
+            v: self.gravitational_acceleration(pos, vel, split).extend(0.0),
+        }
+    }
+
+    /// Step error relative to the tolerance, measured as in the shader.
+    fn step_error(&self, s: &RayState, err: &RayState) -> f64 {
+        let pos = s.x.truncate();
+        let r_near = self
+            .bodies
+            .iter()
+            .map(|body| (pos - body.position).length())
+            .fold(1e30, f64::min);
+        let e_x = err.x.truncate().length() / r_near.max(1e-3);
+        let e_v = err.v.truncate().length() / s.v.truncate().length().max(1e-6);
+        e_x.max(e_v) / self.uniforms.tolerance as f64
+    }
+
+    /// Scale a launch direction so the Kerr–Schild momentum is null.
+    pub fn kerr_initial_momentum(&self, pos: DVec3, dir: DVec3) -> DVec3 {
+        let (mut qa, mut qb, mut qc) = (1.0, 0.0, 1.0);
+        for body in &self.bodies {
+            let delta = pos - body.position;
+            if delta.length_squared() < 1e-8 {
+                continue;
+            }
+            let field = kerr_field(delta, dir, body.rs, body.spin);
+            let ln = field.lp - 1.0;
+            qa -= field.f * ln * ln;
+            qb += field.f * ln;
+            qc += field.f;
+        }
+        let k = (qb + (qb * qb + qa * qc).max(0.0).sqrt()) / qa.max(1e-4);
+        k * dir
+    }
+
+    fn check_capture(&self, pos: DVec3) -> Option<usize> {
+        self.bodies
+            .iter()
+            .zip(self.metrics.iter())
+            .position(|(body, metric)| metric.is_inside_horizon(&body_event(pos, body)))
+    }
+
+    fn check_escape(&self, pos: DVec3) -> bool {
+        self.bodies
+            .iter()
+            .zip(self.metrics.iter())
+            .all(|(body, metric)| metric.has_escaped(&body_event(pos, body), ESCAPE_RADIUS))
+    }
+
+    // ── Shading ──────────────────────────────────────────────────────────
+
+    fn background(&self, theta: f64, phi: f64) -> DVec3 {
+        if self.uniforms.background_mode == 3 {
+            let sigma = star_sigma(self.uniforms.fov as f64, self.uniforms.resolution[1] as f64);
+            self.stars
+                .as_ref()
+                .map_or(DVec3::ZERO, |stars| stars.radiance(theta, phi, sigma))
+        } else if self.uniforms.background_mode == 2 {
+            self.environment
+                .as_ref()
+                .map_or(DVec3::ZERO, |map| map.sample(theta, phi))
+        } else if self.uniforms.background_mode == 1 {
+            starfield(theta, phi)
+        } else {
+            checkerboard(theta, phi)
+        }
+    }
+
+    fn potential_redshift(&self, pos: DVec3) -> f64 {
+        let grav_potential: f64 = self
+            .bodies
+            .iter()
+            .filter_map(|b| {
+                let dist = (pos - b.position).length();
+                (dist > 0.01).then(|| b.rs / dist)
+            })
+            .sum();
+        (1.0 - grav_potential).max(0.001).sqrt()
+    }
+
+    /// How disk light leaving `pos` along the ray at `state` is seen.
+    /// `observed_frequency` is the camera's static-observer frequency per
+    /// unit photon energy and `doppler` the shift of the moving camera
+    /// against that observer.
+    fn emission(
+        &self,
+        pos: DVec3,
+        state: &RayState,
+        null_metric: Option<&dyn metrics::Metric>,
+        observed_frequency: f64,
+        doppler: f64,
+    ) -> Emission {
+        let time = self.uniforms.time as f64;
+        match (null_metric, self.bodies.first()) {
+            (Some(metric), Some(b0)) => {
+                let d = pos - b0.position;
+                let at_pos = NullRay {
+                    x: [state.x.w, d.x, d.y, d.z],
+                    k: to_event(state.v),
+                };
+                let p = at_pos.covariant(metric);
+                let emitted_frequency = at_pos.static_frequency(metric);
+                Emission {
+                    time: time - state.x.w,
+                    momentum: DVec4::new(p[1], p[2], p[3], p[0]),
+                    grav_redshift: observed_frequency / emitted_frequency.max(1e-4),
+                    doppler,
+                }
+            }
+            _ => {
+                let is_kerr = self.uniforms.geodesic_model == GeodesicModel::KerrSchild as u32;
+                let p = if is_kerr {
+                    state.v.truncate()
+                } else {
+                    state.v.truncate().normalize()
+                };
+                Emission {
+                    time,
+                    momentum: p.extend(-1.0),
+                    grav_redshift: self.potential_redshift(pos) * observed_frequency,
+                    doppler,
+                }
+            }
+        }
+    }
+
+    /// Frequency ratio ν_obs/ν_emit from the orbital motion of `body`'s disk
+    /// gas at `pos`, relative to a static emitter; see `orbital_doppler` in
+    /// the shader.
+    fn orbital_doppler(&self, body: &RefBody, pos: DVec3, p: DVec4) -> f64 {
+        let n = body.disk_normal;
+        let delta = pos - body.position;
+        let rho = disk_coords(delta, n).x;
+        let m = 0.5 * body.rs;
+        let a = body.spin * m * n.y;
+        let r = (rho * rho - a * a).max(0.0).sqrt().max(1.01 * body.rs);
+        let a_sqrt_m = a * m.sqrt();
+        let r32 = r * r.sqrt();
+        let omega = m.sqrt() / (r32 + a_sqrt_m);
+        let u_t = (r32 + a_sqrt_m)
+            / (r.powf(0.75) * (r32 - 3.0 * m * r.sqrt() + 2.0 * a_sqrt_m).max(1e-4).sqrt());
+        let lapse = (1.0 - body.rs / r).max(1e-4).sqrt();
+        let p_phi = n.cross(delta).dot(p.truncate());
+        1.0 / (lapse * u_t * (1.0 - omega * p_phi / p.w).max(1e-3))
+    }
+
+    /// Absorption coefficient of `body`'s volumetric disk gas at `pos`, and
+    /// its heating; see `disk_gas` in the shader.
+    fn disk_gas(&self, body: &RefBody, pos: DVec3) -> (f64, f64) {
+        let coords = disk_coords(pos - body.position, body.disk_normal);
+        let r = coords.x;
+        let height = self.uniforms.disk_scale_height as f64 * r;
+        let z = coords.z / height.max(1e-6);
+        if r <= body.disk_inner || r >= body.disk_outer || z.abs() > DISK_EXTENT {
+            return (0.0, 0.0);
+        }
+        let edges = smoothstep(body.disk_inner, body.disk_inner + 0.5 * body.rs, r)
+            * (1.0 - smoothstep(body.disk_outer - body.rs, body.disk_outer, r));
+        let column = edges * body.disk_inner / r;
+        let density = column * (-0.5 * z * z).exp() / ((2.0 * PI).sqrt() * height);
+        (
+            self.uniforms.disk_optical_depth as f64 * density,
+            1.0 + 0.25 * z * z,
+        )
+    }
+
+    /// Density samples needed along the chord `a` → `b`, 0 if it misses all
+    /// disk gas.
+    fn disk_samples(&self, a: DVec3, b: DVec3) -> u32 {
+        let len = (b - a).length();
+        let scale_height = self.uniforms.disk_scale_height as f64;
+        let mut n: f64 = 0.0;
+        for body in &self.bodies {
+            let da = disk_coords(a - body.position, body.disk_normal);
+            let db = disk_coords(b - body.position, body.disk_normal);
+            let (ra, rb) = (da.x, db.x);
+            let r_max = ra.max(rb).min(body.disk_outer);
+            let r_min = ra.min(rb) - len;
+            let z_max = DISK_EXTENT * scale_height * r_max;
+            let above = da.z * db.z > 0.0 && da.z.abs().min(db.z.abs()) > z_max;
+            if ra.max(rb) > body.disk_inner && r_min < body.disk_outer && !above {
+                let h_min = scale_height * r_min.max(body.disk_inner);
+                n = n.max(len * DISK_SAMPLES_PER_HEIGHT / h_min);
+            }
+        }
+        (n.ceil() as u32).min(MAX_DISK_SAMPLES)
+    }
+
+    fn disk_detail(&self, r: f64, azimuth: f64, rs: f64, disk_inner: f64, time: f64) -> f64 {
+        let rn = r / rs;
+        let mut detail = 1.0;
+
+        detail *= 0.95 + 0.05 * (rn * 6.0).sin();
+        detail *= 0.97 + 0.03 * (rn * 15.0 + 2.0).sin();
+
+        let spiral = (azimuth * 2.0 - rn * 4.0 + time * 0.2).sin();
+        detail *= 0.96 + 0.04 * spiral;
+
+        let turb = fbm(DVec2::new(rn * 4.0, azimuth * 3.0 / PI));
+        detail *= 0.88 + 0.12 * turb;
+
+        let inner_turb = fbm(DVec2::new(azimuth * 5.0 / PI + time * 0.15, rn * 10.0));
+        let inner_weight = (-(rn - disk_inner / rs).max(0.0) * 1.5).exp();
+        detail += inner_turb * inner_weight * 0.2;
+
+        detail.max(0.0)
+    }
+
+    /// Frequency ratio g = ν_obs/ν_emit of `body`'s disk light at `pos`,
+    /// from the camera's motion and whichever shifts are enabled.
+    fn redshift(&self, pos: DVec3, body: &RefBody, emission: &Emission) -> f64 {
+        let mut g = emission.doppler;
+        if self.uniforms.doppler_beaming == 1 {
+            g *= self.orbital_doppler(body, pos, emission.momentum);
+        }
+        if self.uniforms.gravitational_redshift == 1 {
+            g *= emission.grav_redshift;
+        }
+        g
+    }
+
+    /// Disk hit on body `b`'s disk at `pos`.
+    fn disk_hit(&self, b: usize, pos: DVec3, emission: &Emission) -> DiskHit {
+        let body = &self.bodies[b];
+        DiskHit {
+            body: b,
+            position: pos,
+            radius: disk_coords(pos - body.position, body.disk_normal).x,
+            redshift: self.redshift(pos, body, emission),
+        }
+    }
+
+    fn disk_color_for_body(
+        &self,
+        pos: DVec3,
+        body: &RefBody,
+        emission: &Emission,
+        heat: f64,
+    ) -> DVec3 {
+        let u = &self.uniforms;
+        let time = emission.time;
+        let coords = disk_coords(pos - body.position, body.disk_normal);
+        let r = coords.x;
+        let azimuth = coords.y + time * 0.5;
+
+        let r_isco = body.isco;
+        let luminosity_at = |r: f64| (1.0 / (r * r)) * (1.0 - (r_isco / r).sqrt());
+        let luminosity = if r > r_isco { luminosity_at(r) } else { 0.0 };
+        let r_peak = r_isco * 49.0 / 36.0;
+        let luminosity = luminosity / luminosity_at(r_peak).max(0.001);
+
+        let g = self.redshift(pos, body, emission);
+        let t_normalized = (body.disk_inner / r).clamp(0.0, 1.0).powf(0.75);
+        let temp = (1500.0 + (6500.0 - 1500.0) * t_normalized) * heat * g;
+        let detail = self.disk_detail(r, azimuth, body.rs, body.disk_inner, time);
+        let col = blackbody(temp) * luminosity * detail * 3.0 * g.powf(u.intensity_exponent as f64);
+
+        let outer_fade = 1.0 - smoothstep(body.disk_outer - body.rs, body.disk_outer, r);
+        col * outer_fade
+    }
+}
+
+/// Disk light leaving a point along the ray.
+struct Emission {
+    /// When the light left, u.time unless light-travel delay is tracked.
+    time: f64,
+    /// Covariant momentum (p_i, p_t) of the backwards-traced ray.
+    momentum: DVec4,
+    /// Observed/emitted frequency ratio for a static emitter.
+    grav_redshift: f64,
+    /// Frequency the moving camera measures over the static observer's.
+    doppler: f64,
+}
+
+/// Integration state shared by all geodesic models; see `RayState` in the shader.
+#[derive(Debug, Clone, Copy, Default)]
+struct RayState {
+    x: DVec4,
+    v: DVec4,
+}
+
+impl RayState {
+    /// self + Σ h cᵢ dᵢ
+    fn advance(&self, h: f64, terms: &[(f64, &RayState)]) -> RayState {
+        let mut out = *self;
+        for (c, d) in terms {
+            out.x += h * c * d.x;
+            out.v += h * c * d.v;
+        }
+        out
+    }
+}
+
+/// Bodies summed at every integrator stage of a step, and the derivative
+/// contribution of the rest, frozen at the start of the step.
+#[derive(Debug, Clone, Default)]
+struct FieldSplit {
+    near: Vec<usize>,
+    far: RayState,
+}
+
+/// (t, x, y, z) components, as used by `metrics`, to a state vector with t in w.
+fn from_event(e: [f64; 4]) -> DVec4 {
+    DVec4::new(e[1], e[2], e[3], e[0])
+}
+
+fn to_event(v: DVec4) -> [f64; 4] {
+    [v.w, v.x, v.y, v.z]
+}
+
+/// World position as an event in the frame centred on `body`.
+fn body_event(pos: DVec3, body: &RefBody) -> [f64; 4] {
+    let delta = pos - body.position;
+    [0.0, delta.x, delta.y, delta.z]
+}
+
+fn rk4(derivs: &impl Fn(&RayState) -> RayState, s: &RayState, h: f64) -> RayState {
+    let d1 = derivs(s);
+    let d2 = derivs(&s.advance(0.5 * h, &[(1.0, &d1)]));
+    let d3 = derivs(&s.advance(0.5 * h, &[(1.0, &d2)]));
+    let d4 = derivs(&s.advance(h, &[(1.0, &d3)]));
+    s.advance(h / 6.0, &[(1.0, &d1), (2.0, &d2), (2.0, &d3), (1.0, &d4)])
+}
+
+/// One Dormand–Prince 5(4) step, returning the new state, the error estimate
+/// and the derivative at the new state.
+fn dormand_prince(
+    derivs: &impl Fn(&RayState) -> RayState,
+    s: &RayState,
+    d1: &RayState,
+    h: f64,
+) -> (RayState, RayState, RayState) {
+    let d2 = derivs(&s.advance(h, &[(0.2, d1)]));
+    let d3 = derivs(&s.advance(h, &[(3.0 / 40.0, d1), (9.0 / 40.0, &d2)]));
+    let d4 = derivs(&s.advance(
+        h,
+        &[(44.0 / 45.0, d1), (-56.0 / 15.0, &d2), (32.0 / 9.0, &d3)],
+    ));
+    let d5 = derivs(&s.advance(
+        h,
+        &[
+            (19372.0 / 6561.0, d1),
+            (-25360.0 / 2187.0, &d2),
+            (64448.0 / 6561.0, &d3),
+            (-212.0 / 729.0, &d4),
+        ],
+    ));
+    let d6 = derivs(&s.advance(
+        h,
+        &[
+            (9017.0 / 3168.0, d1),
+            (-355.0 / 33.0, &d2),
+            (46732.0 / 5247.0, &d3),
+            (49.0 / 176.0, &d4),
+            (-5103.0 / 18656.0, &d5),
+        ],
+    ));
+    let next = s.advance(
+        h,
+        &[
+            (35.0 / 384.0, d1),
+            (500.0 / 1113.0, &d3),
+            (125.0 / 192.0, &d4),
+            (-2187.0 / 6784.0, &d5),
+            (11.0 / 84.0, &d6),
+        ],
+    );
+    let d7 = derivs(&next);
+    let err = RayState::default().advance(
+        h,
+        &[
+            (71.0 / 57600.0, d1),
+            (-71.0 / 16695.0, &d3),
+            (71.0 / 1920.0, &d4),
+            (-17253.0 / 339200.0, &d5),
+            (22.0 / 525.0, &d6),
+            (-1.0 / 40.0, &d7),
+        ],
+    );
+    (next, err, d7)
+}
+
+fn vec3(v: [f32; 4]) -> DVec3 {
+    DVec3::new(v[0] as f64, v[1] as f64, v[2] as f64)
+}
+
+// ── Kerr–Schild field (see the matching section of ray_march.wgsl) ─────
+
+struct KerrField {
+    f: f64,
+    grad_f: DVec3,
+    lp: f64,
+    grad_lp: DVec3,
+    l: DVec3,
+}
+
+fn to_ks(v: DVec3) -> DVec3 {
+    DVec3::new(v.z, v.x, v.y)
+}
+
+fn from_ks(v: DVec3) -> DVec3 {
+    DVec3::new(v.y, v.z, v.x)
+}
+
+fn kerr_radius(delta: DVec3, a: f64) -> f64 {
+    if a == 0.0 {
+        return delta.length();
+    }
+    let b = 0.5 * (delta.length_squared() - a * a);
+    (b + (b * b + a * a * delta.y * delta.y).sqrt())
+        .max(0.0)
+        .sqrt()
+}
+
+fn kerr_field(delta_world: DVec3, p_world: DVec3, rs: f64, spin: f64) -> KerrField {
+    let q = to_ks(delta_world);
+    let p = to_ks(p_world);
+    let a = spin * 0.5 * rs;
+    let a2 = a * a;
+
+    let r = kerr_radius(delta_world, a);
+    let r2 = r * r;
+    let big_r2 = q.length_squared();
+
+    let d = (2.0 * r2 - big_r2 + a2).max(1e-6);
+    let dr = DVec3::new(q.x * r / d, q.y * r / d, q.z * (r2 + a2) / (r * d));
+
+    let n = r2 * r2 + a2 * q.z * q.z;
+    let f = rs * r2 * r / n;
+    let grad_f = rs
+        * r2
+        * ((3.0 * a2 * q.z * q.z - r2 * r2) * dr - DVec3::new(0.0, 0.0, 2.0 * a2 * r * q.z))
+        / (n * n);
+
+    let big_a = r2 + a2;
+    let u1 = q.x * p.x + q.y * p.y;
+    let u2 = q.y * p.x - q.x * p.y;
+    let l = DVec3::new(
+        (r * q.x + a * q.y) / big_a,
+        (r * q.y - a * q.x) / big_a,
+        q.z / r,
+    );
+    let lp = 1.0 + l.dot(p);
+
+    let num = r * u1 + a * u2;
+    let du1 = DVec3::new(p.x, p.y, 0.0);
+    let du2 = DVec3::new(-p.y, p.x, 0.0);
+    let grad_planar =
+        ((dr * u1 + r * du1 + a * du2) * big_a - num * 2.0 * r * dr) / (big_a * big_a);
+    let grad_axial = (DVec3::new(0.0, 0.0, p.z * r) - q.z * p.z * dr) / r2;
+
+    KerrField {
+        f,
+        grad_f: from_ks(grad_f),
+        lp,
+        grad_lp: from_ks(grad_planar + grad_axial),
+        l: from_ks(l),
+    }
+}
+
+/// Pseudo-Newtonian pull of one body.
+fn body_acceleration(body: &RefBody, pos: DVec3, vel: DVec3) -> DVec3 {
+    let delta = pos - body.position;
+    let r = delta.length();
+    if r < body.rs * 0.5 {
+        return DVec3::ZERO;
+    }
+    let r2 = r * r;
+    let r5 = r2 * r2 * r;
+    let l2 = delta.cross(vel).length_squared();
+    -1.5 * body.rs / r5 * l2 * delta
+}
+
+/// One body's terms of Hamilton's equations in Kerr–Schild form.
+fn kerr_body_derivs(body: &RefBody, pos: DVec3, p: DVec3) -> (DVec3, DVec3) {
+    let delta = pos - body.position;
+    if delta.length_squared() < 1e-8 {
+        return (DVec3::ZERO, DVec3::ZERO);
+    }
+    let field = kerr_field(delta, p, body.rs, body.spin);
+    (
+        -field.f * field.lp * field.l,
+        0.5 * field.lp * field.lp * field.grad_f + field.f * field.lp * field.grad_lp,
+    )
+}
+
+// ── Hash / noise ─────────────────────────────────────────────────────────
+
+/// Radius, azimuth and height of `delta` in the frame of a disk plane with
+/// unit normal `n`; see `disk_coords` in the shader.
+fn disk_coords(delta: DVec3, n: DVec3) -> DVec3 {
+    let mut e1 = DVec3::X - n.x * n;
+    if e1.length_squared() < 1e-6 {
+        e1 = DVec3::Z - n.z * n;
+    }
+    let e1 = e1.normalize();
+    let e2 = e1.cross(n);
+    let height = delta.dot(n);
+    let in_plane = delta - height * n;
+    DVec3::new(
+        in_plane.length(),
+        in_plane.dot(e2).atan2(in_plane.dot(e1)),
+        height,
+    )
+}
+
+fn fract(x: f64) -> f64 {
+    x - x.floor()
+}
+
+fn hash21(p: DVec2) -> f64 {
+    let mut p3 = DVec3::new(
+        fract(p.x * 0.1031),
+        fract(p.y * 0.1030),
+        fract(p.x * 0.0973),
+    );
+    p3 += p3.dot(DVec3::new(p3.y + 33.33, p3.z + 33.33, p3.x + 33.33));
+    fract((p3.x + p3.y) * p3.z)
+}
+
+fn hash22(p: DVec2) -> DVec2 {
+    DVec2::new(hash21(p), hash21(p + DVec2::new(127.1, 311.7)))
+}
+
+fn value_noise(p: DVec2) -> f64 {
+    let i = p.floor();
+    let f = p - i;
+    let sm = f * f * (3.0 - 2.0 * f);
+
+    let a = hash21(i);
+    let b = hash21(i + DVec2::new(1.0, 0.0));
+    let c = hash21(i + DVec2::new(0.0, 1.0));
+    let d = hash21(i + DVec2::new(1.0, 1.0));
+
+    let ab = a + (b - a) * sm.x;
+    let cd = c + (d - c) * sm.x;
+    ab + (cd - ab) * sm.y
+}
+
+fn fbm(p: DVec2) -> f64 {
+    let mut val = 0.0;
+    let mut amp = 0.5;
+    let mut pos = p;
+    for _ in 0..4 {
+        val += amp * value_noise(pos);
+        pos = pos * 2.3 + DVec2::new(1.7, 3.2);
+        amp *= 0.5;
+    }
+    val
+}
+
+/// False colour of disk image `order`.
+fn order_color(order: usize) -> DVec3 {
+    rgb(DISK_ORDER_COLORS[order.min(3)])
+}
+
+fn rgb([r, g, b]: [f32; 3]) -> DVec3 {
+    DVec3::new(r as f64, g as f64, b as f64)
+}
+
+/// Sky reduced to a dim grey behind false colours.
+fn dimmed(color: DVec3) -> DVec3 {
+    DVec3::splat(0.2 * color.dot(DVec3::new(0.2126, 0.7152, 0.0722)))
+}
+
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// ── Background ───────────────────────────────────────────────────────────
+
+fn checkerboard(theta: f64, phi: f64) -> DVec3 {
+    let checks = 20.0;
+    let cx = (phi / (2.0 * PI) * checks).floor();
+    let cy = (theta / PI * checks).floor();
+    if (cx + cy) % 2.0 < 0.5 {
+        DVec3::new(0.1, 0.1, 0.3)
+    } else {
+        DVec3::new(0.9, 0.9, 1.0)
+    }
+}
+
+fn starfield(theta: f64, phi: f64) -> DVec3 {
+    let mut col = DVec3::new(0.003, 0.003, 0.006);
+
+    let uv = DVec2::new(phi / (2.0 * PI), theta / PI);
+    let grid_size = 80.0;
+    let cell = (uv * grid_size).floor();
+    let cell_uv = uv * grid_size - cell;
+
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            let offset_cell = DVec2::new(dx as f64, dy as f64);
+            let neighbor = cell + offset_cell;
+            let offset = hash22(neighbor) - cell_uv + offset_cell;
+
+            let aspect = (theta + 0.001).sin();
+            let dist = DVec2::new(offset.x * aspect, offset.y).length() * grid_size;
+
+            let brightness = hash21(neighbor + DVec2::new(42.0, 17.0));
+            if brightness > 0.7 {
+                let star_bright = (brightness - 0.7) / 0.3;
+                let glow = (-dist * dist * 12.0).exp() * star_bright;
+
+                let color_hash = hash21(neighbor + DVec2::new(91.0, 53.0));
+                let star_color = if color_hash < 0.12 {
+                    DVec3::new(0.85, 0.92, 1.0)
+                } else if color_hash < 0.35 {
+                    DVec3::new(1.0, 0.95, 0.85)
+                } else if color_hash < 0.55 {
+                    DVec3::new(1.0, 0.85, 0.6)
+                } else if color_hash < 0.72 {
+                    DVec3::new(1.0, 0.7, 0.4)
+                } else if color_hash < 0.85 {
+                    DVec3::new(1.0, 0.5, 0.3)
+                } else {
+                    DVec3::new(1.0, 0.98, 0.95)
+                };
+
+                col += star_color * glow;
+            }
+        }
+    }
+
+    col
+}
+
+fn grid_strength_at_pos(p: DVec3) -> f64 {
+    let spacing = 12.0;
+    let line_width = 0.12;
+    let wrap = |v: f64| fract(v / spacing + 0.5) - 0.5;
+
+    let to_line_x = spacing * DVec2::new(wrap(p.y), wrap(p.z)).length();
+    let to_line_y = spacing * DVec2::new(wrap(p.x), wrap(p.z)).length();
+    let to_line_z = spacing * DVec2::new(wrap(p.x), wrap(p.y)).length();
+
+    let line_x = 1.0 - smoothstep(0.0, line_width, to_line_x);
+    let line_y = 1.0 - smoothstep(0.0, line_width, to_line_y);
+    let line_z = 1.0 - smoothstep(0.0, line_width, to_line_z);
+
+    (line_x + line_y + line_z).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::kerr::KerrParams;
+    use crate::metrics::schwarzschild::SchwarzschildParams;
+    use crate::metrics::Metric;
+    use crate::renderer::camera::OrbitalCamera;
+    use crate::renderer::gpu_test::{assert_matches_gpu, GpuCase, Sky};
+    use crate::renderer::stars::{SkyLocation, Star, StarCatalog};
+    use crate::renderer::uniforms::DiskSettings;
+    use crate::simulation::{Body, Preset, Simulation};
+
+    fn single_body(spin: f32) -> Vec<GpuBody> {
+        let mut sim = Simulation::new(Preset::Single);
+        sim.bodies[0].spin = spin;
+        sim.gpu_bodies().to_vec()
+    }
+
+    fn tracer(bodies: &[GpuBody], model: GeodesicModel, step_size: f32) -> ReferenceTracer {
+        let uniforms = Uniforms {
+            num_bodies: 1,
+            max_steps: 20_000,
+            step_size,
+            disk_enabled: 0,
+            geodesic_model: model as u32,
+            integrator: Integrator::FixedRk4 as u32,
+            ..Default::default()
+        };
+        ReferenceTracer::new(&uniforms, bodies)
+    }
+
+    /// Smallest impact parameter (to `tol`) whose ray, launched along +z at
+    /// x = sign·b from far away, reaches the sky.
+    fn escape_threshold(tracer: &ReferenceTracer, sign: f64, tol: f64) -> f64 {
+        let (mut lo, mut hi) = (1.0, 5.0);
+        while hi - lo > tol {
+            let b = 0.5 * (lo + hi);
+            let origin = DVec3::new(sign * b, 0.0, -45.0);
+            if tracer.trace_ray(origin, DVec3::Z).fate.reaches_sky() {
+                hi = b;
+            } else {
+                lo = b;
+            }
+        }
+        0.5 * (lo + hi)
+    }
+
+    #[test]
+    fn schwarzschild_capture_threshold_matches_critical_impact_parameter() {
+        let bodies = single_body(0.0);
+        let expected = SchwarzschildParams { rs: 1.0 }.critical_impact_parameter();
+        let tracer = tracer(&bodies, GeodesicModel::PseudoNewtonian, 0.01);
+        let b = escape_threshold(&tracer, 1.0, 1e-4);
+        assert!(
+            (b - expected).abs() / expected < 2e-3,
+            "capture threshold {b} vs b_crit {expected}"
+        );
+    }
+
+    #[test]
+    fn kerr_schild_at_zero_spin_matches_critical_impact_parameter() {
+        let bodies = single_body(0.0);
+        let expected = SchwarzschildParams { rs: 1.0 }.critical_impact_parameter();
+        let tracer = tracer(&bodies, GeodesicModel::KerrSchild, 0.01);
+        let b = escape_threshold(&tracer, 1.0, 1e-4);
+        // Launching at r = 45 rescales the momentum slightly, so b is only
+        // conserved to O(rs/r) in the launch direction.
+        assert!(
+            (b - expected).abs() / expected < 1e-2,
+            "capture threshold {b} vs b_crit {expected}"
+        );
+    }
+
+    #[test]
+    fn kerr_schild_at_zero_spin_bends_rays_like_schwarzschild() {
+        let bodies = single_body(0.0);
+        let schwarzschild = tracer(&bodies, GeodesicModel::PseudoNewtonian, 0.01);
+        let kerr = tracer(&bodies, GeodesicModel::KerrSchild, 0.01);
+        // Captured, wound almost all the way round (b_crit ≈ 2.6), and weakly
+        // bent; off the equatorial plane so no axis is special.
+        for b in [2.0, 2.7, 3.5, 5.0, 8.0] {
+            let origin = DVec3::new(0.6 * b, 0.8 * b, -45.0);
+            let expected = schwarzschild.trace_ray(origin, DVec3::Z);
+            let actual = kerr.trace_ray(origin, DVec3::Z);
+            assert_eq!(actual.fate.index(), expected.fate.index(), "b = {b}");
+            if expected.fate.reaches_sky() {
+                let angle = actual
+                    .final_direction
+                    .normalize()
+                    .angle_between(expected.final_direction.normalize());
+                // The Kerr–Schild direction is the covariant momentum, which
+                // differs from the velocity by O(rs/r) at the escape radius.
+                assert!(angle < 1e-2, "b = {b}: final directions {angle} rad apart");
+            }
+        }
+    }
+
+    #[test]
+    fn kerr_prograde_and_retrograde_thresholds_bracket_schwarzschild() {
+        let spin = 0.9;
+        let bodies = single_body(spin as f32);
+        let tracer = tracer(&bodies, GeodesicModel::KerrSchild, 0.01);
+        // A ray at x = -b moving along +z has angular momentum along +y,
+        // co-rotating with positive spin.
+        let prograde = escape_threshold(&tracer, -1.0, 1e-3);
+        let retrograde = escape_threshold(&tracer, 1.0, 1e-3);
+
+        let expected_pro = KerrParams { rs: 1.0, spin }.critical_impact_parameter();
+        let expected_retro = KerrParams {
+            rs: 1.0,
+            spin: -spin,
+        }
+        .critical_impact_parameter();
+        assert!(
+            (prograde - expected_pro).abs() / expected_pro < 2e-2,
+            "prograde {prograde} vs {expected_pro}"
+        );
+        assert!(
+            (retrograde - expected_retro).abs() / expected_retro < 2e-2,
+            "retrograde {retrograde} vs {expected_retro}"
+        );
+    }
+
+    #[test]
+    fn adaptive_integrator_matches_fixed_step_with_fewer_steps() {
+        let bodies = single_body(0.0);
+        let fixed = tracer(&bodies, GeodesicModel::PseudoNewtonian, 0.01);
+        let mut adaptive = tracer(&bodies, GeodesicModel::PseudoNewtonian, 0.1);
+        adaptive.uniforms.integrator = Integrator::AdaptiveRk45 as u32;
+        adaptive.uniforms.tolerance = 1e-6;
+
+        let expected = SchwarzschildParams { rs: 1.0 }.critical_impact_parameter();
+        let b = escape_threshold(&adaptive, 1.0, 1e-4);
+        assert!(
+            (b - expected).abs() / expected < 2e-3,
+            "capture threshold {b} vs b_crit {expected}"
+        );
+
+        // A strongly deflected ray passing just outside the photon sphere
+        let origin = DVec3::new(1.05 * expected, 0.0, -45.0);
+        let reference = fixed.trace_ray(origin, DVec3::Z);
+        let trace = adaptive.trace_ray(origin, DVec3::Z);
+        assert_eq!(trace.fate, reference.fate);
+        let angle = trace
+            .final_direction
+            .angle_between(reference.final_direction);
+        assert!(angle < 1e-3, "exit directions differ by {angle} rad");
+        assert!(
+            trace.steps * 10 < reference.steps,
+            "{} adaptive steps vs {} fixed",
+            trace.steps,
+            reference.steps
+        );
+    }
+
+    #[test]
+    fn reference_matches_gpu_output() {
+        for integrator in Integrator::ALL {
+            let mut sim = Simulation::new(Preset::Single);
+            sim.bodies.push(Body::new(
+                glam::Vec3::new(4.0, 0.5, -3.0),
+                glam::Vec3::ZERO,
+                0.6,
+            ));
+            assert_matches_gpu(GpuCase {
+                sim,
+                integrator,
+                ..Default::default()
+            });
+        }
+    }
+
+    /// A black hole at the origin with its disk in the y = 0 plane, under
+    /// 39 light bodies off to the side whose disk planes lie between it and
+    /// the cameras of the tests.
+    fn stacked_disk_planes() -> Simulation {
+        let mut sim = Simulation::new(Preset::Single);
+        let hole = sim.bodies[0].clone();
+        sim.bodies = (0..39)
+            .map(|i| {
+                let position = glam::Vec3::new(-30.0, 0.5 + 0.05 * i as f32, 0.0);
+                Body::new(position, glam::Vec3::ZERO, 0.01)
+            })
+            .chain([hole])
+            .collect();
+        sim
+    }
+
+    #[test]
+    fn disk_order_is_counted_per_body_beyond_the_nearby_limit() {
+        let sim = stacked_disk_planes();
+        let uniforms = Uniforms {
+            num_bodies: sim.bodies.len() as u32,
+            render_mode: RenderMode::DiskOrder as u32,
+            ..Default::default()
+        };
+        let tracer = ReferenceTracer::new(&uniforms, &sim.gpu_bodies());
+        // Straight down onto the hole's disk through every other plane: the
+        // disk is still seen directly.
+        let trace = tracer.trace_ray(DVec3::new(6.0, 10.0, 0.0), DVec3::NEG_Y);
+        assert_eq!(trace.disk_crossings.len(), 40);
+        let direct = rgb(DISK_ORDER_COLORS[0]).normalize();
+        let color = trace.color.normalize();
+        assert!(color.abs_diff_eq(direct, 1e-6), "{color:?}");
+    }
+
+    #[test]
+    fn reference_matches_gpu_output_for_disk_order_of_many_bodies() {
+        assert_matches_gpu(GpuCase {
+            sim: stacked_disk_planes(),
+            render_mode: RenderMode::DiskOrder,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn reference_matches_gpu_output_for_kerr_and_null_geodesics() {
+        let mut kerr = Simulation::new(Preset::Single);
+        kerr.bodies[0].spin = 0.8;
+        assert_matches_gpu(GpuCase {
+            sim: kerr,
+            ..Default::default()
+        });
+        assert_matches_gpu(GpuCase {
+            exact_geodesics: true,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn reference_matches_gpu_output_for_many_bodies_with_cutoff() {
+        // A ring of 12 bodies, more than the body buffer starts with, some
+        // spinning so both integration models are exercised.
+        for spin in [0.0, 0.6] {
+            let mut sim = Simulation::new(Preset::Single);
+            sim.bodies = (0..12)
+                .map(|i| {
+                    let angle = i as f32 * std::f32::consts::TAU / 12.0;
+                    let radius = 4.0 + (i % 3) as f32 * 3.0;
+                    let position = glam::Vec3::new(radius * angle.cos(), 0.0, radius * angle.sin());
+                    Body::new(position, glam::Vec3::ZERO, 0.3 + 0.05 * (i % 4) as f32)
+                })
+                .collect();
+            sim.bodies[3].spin = spin;
+            assert_matches_gpu(GpuCase {
+                sim,
+                body_cutoff: 20.0,
+                ..Default::default()
+            });
+        }
+    }
+
+    #[test]
+    fn reference_matches_gpu_output_for_volumetric_disk() {
+        let mut sim = Simulation::new(Preset::Single);
+        sim.bodies.push(Body::new(
+            glam::Vec3::new(4.0, 0.5, -3.0),
+            glam::Vec3::ZERO,
+            0.6,
+        ));
+        assert_matches_gpu(GpuCase {
+            sim,
+            disk: DiskSettings {
+                model: DiskModel::Volumetric,
+                ..DiskSettings::default()
+            },
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn reference_matches_gpu_output_for_moving_observer() {
+        assert_matches_gpu(GpuCase {
+            observer_velocity: [0.3, -0.1, 0.5, 0.0],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn reference_matches_gpu_output_for_environment_maps() {
+        // Smooth gradients, tinted per cube face so a wrong face shows.
+        let gradient = |w: u32, h: u32, tint: f32| {
+            image::Rgb32FImage::from_fn(w, h, |x, y| {
+                let (u, v) = (x as f32 / w as f32, y as f32 / h as f32);
+                image::Rgb([u, v, tint + 0.5 * (6.0 * u).sin().abs()])
+            })
+        };
+        let equirect = EnvironmentMap::equirectangular(gradient(64, 32, 0.2)).unwrap();
+        // Shaped like the cubemap atlas, which must not fool the shader.
+        let panorama = EnvironmentMap::equirectangular(gradient(48, 32, 0.2)).unwrap();
+        let cubemap =
+            EnvironmentMap::cubemap(std::array::from_fn(|i| gradient(16, 16, 0.3 * i as f32)))
+                .unwrap();
+        for map in [equirect, panorama, cubemap] {
+            assert_matches_gpu(GpuCase {
+                sky: Sky::Image(Arc::new(map)),
+                ..Default::default()
+            });
+        }
+    }
+
+    #[test]
+    fn reference_matches_gpu_output_for_star_catalog() {
+        // Stars every few degrees, bright enough to show at any magnitude,
+        // so most of the sky around the shadow is lit by one or another.
+        let catalog = StarCatalog {
+            stars: (0..20_000)
+                .map(|i| Star {
+                    ra: (i as f64 * 137.508) % 360.0,
+                    dec: (1.0 - 2.0 * (i as f64 + 0.5) / 20_000.0)
+                        .asin()
+                        .to_degrees(),
+                    magnitude: (i % 4) as f64 - 1.0,
+                    color_index: (i % 5) as f64 * 0.4 - 0.2,
+                })
+                .collect(),
+        };
+        let bins = catalog.bin(SkyLocation::default());
+        assert_matches_gpu(GpuCase {
+            sky: Sky::Stars(Arc::new(bins)),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn volumetric_disk_absorbs_its_column_depth() {
+        let bodies = single_body(0.0);
+        let mut tracer = tracer(&bodies, GeodesicModel::PseudoNewtonian, 0.05);
+        tracer.uniforms.disk_enabled = 1;
+        tracer.uniforms.disk_optical_depth = 1.5;
+
+        tracer.uniforms.disk_model = DiskModel::Volumetric as u32;
+
+        // Straight down through the gas at about 8 rs. The column there is
+        // inner / R of the inner edge's, R being where the lensed ray
+        // crosses the plane.
+        let trace = tracer.trace_ray(DVec3::new(8.0, 20.0, 0.0), -DVec3::Y);
+        assert!(trace.fate.reaches_sky(), "{:?}", trace.fate);
+        let (_, crossing) = trace.disk_crossings[0];
+        let r = DVec2::new(crossing.x, crossing.z).length();
+        let expected = (-1.5 * 3.0 / r).exp();
+        assert!(
+            (trace.transmittance - expected).abs() < 0.005,
+            "{} vs {}",
+            trace.transmittance,
+            expected
+        );
+        assert!(trace.color.max_element() > 0.0);
+
+        // Edge-on through the inner flow, the gas is opaque.
+        let trace = tracer.trace_ray(DVec3::new(-45.0, 0.0, 4.0), DVec3::X);
+        assert_eq!(trace.fate, RayFate::Absorbed);
+        assert!(trace.transmittance < MIN_TRANSMITTANCE);
+    }
+
+    #[test]
+    fn orbital_doppler_matches_special_relativity_in_the_static_frame() {
+        // Gas at 6 M orbits at v = √(M / (r - 2M)) through the static frame,
+        // moving along -z at +x for the untilted disk.
+        let bodies = single_body(0.0);
+        let tracer = tracer(&bodies, GeodesicModel::PseudoNewtonian, 0.05);
+        let body = &tracer.bodies[0];
+        let (m, r) = (0.5_f64, 3.0);
+        let v = (m / (r - 2.0 * m)).sqrt();
+        let gamma = 1.0 / (1.0 - v * v).sqrt();
+        let lapse = (1.0 - 2.0 * m / r).sqrt();
+        let pos = DVec3::new(r, 0.0, 0.0);
+
+        // Light leaving along -z, straight at an observer the gas approaches.
+        // Traced backwards, the ray's p_i is locally unit for p_t = -1.
+        let toward = DVec4::new(0.0, 0.0, 1.0 / lapse, -1.0);
+        let g = tracer.orbital_doppler(body, pos, toward);
+        assert!((g - 1.0 / (gamma * (1.0 - v))).abs() < 1e-9, "{g}");
+        let away = DVec4::new(0.0, 0.0, -1.0 / lapse, -1.0);
+        let g = tracer.orbital_doppler(body, pos, away);
+        assert!((g - 1.0 / (gamma * (1.0 + v))).abs() < 1e-9, "{g}");
+        // Light leaving at right angles only sees time dilation.
+        let g = tracer.orbital_doppler(body, pos, DVec4::new(0.0, 1.0, 0.0, -1.0));
+        assert!((g - 1.0 / gamma).abs() < 1e-9, "{g}");
+    }
+
+    #[test]
+    fn orbital_doppler_of_a_spinning_hole_matches_the_kerr_metric() {
+        // Gas on the equatorial orbit at Boyer–Lindquist r = 3 rs, co- and
+        // counter-rotating with the hole, against p·u for the static emitter
+        // and the gas worked out with the Kerr–Schild metric itself.
+        let r = 3.0;
+        let p = DVec4::new(0.3, 0.0, 1.2, -1.0);
+        for spin in [0.5, -0.5] {
+            let bodies = single_body(spin as f32);
+            let tracer = tracer(&bodies, GeodesicModel::KerrSchild, 0.05);
+            let kerr = KerrParams { rs: 1.0, spin };
+            let (m, a) = (kerr.mass(), kerr.a());
+            let rho = (r * r + a * a).sqrt();
+            let g = kerr.metric_tensor(&[0.0, rho, 0.0, 0.0]);
+
+            // Ω = √M / (r^{3/2} + a√M), about ∂_φ = y × δ
+            let omega = m.sqrt() / (r * r.sqrt() + a * m.sqrt());
+            let xi = [0.0, 0.0, 0.0, -rho];
+            let g_t_xi: f64 = (0..4).map(|i| g[0][i] * xi[i]).sum();
+            let g_xi_xi: f64 = (0..4)
+                .flat_map(|i| (0..4).map(move |j| (i, j)))
+                .map(|(i, j)| xi[i] * g[i][j] * xi[j])
+                .sum();
+            let u_t = 1.0 / (-(g[0][0] + 2.0 * omega * g_t_xi + omega * omega * g_xi_xi)).sqrt();
+            let u_gas = [u_t, 0.0, 0.0, -omega * u_t * rho];
+            let u_static = [1.0 / (-g[0][0]).sqrt(), 0.0, 0.0, 0.0];
+            // The light's momentum; the backward-traced ray's p_i are reversed
+            let light = [p.w, -p.x, -p.y, -p.z];
+            let dot = |u: [f64; 4]| -> f64 { (0..4).map(|i| light[i] * u[i]).sum() };
+            let expected = dot(u_static) / dot(u_gas);
+
+            let got = tracer.orbital_doppler(&tracer.bodies[0], DVec3::new(rho, 0.0, 0.0), p);
+            assert!((got - expected).abs() < 1e-9, "χ = {spin}: {got}");
+        }
+    }
+
+    #[test]
+    fn tilted_disk_matches_the_rotated_equatorial_disk() {
+        // The pseudo-Newtonian field is spherically symmetric, so a disk
+        // turned onto the yz-plane, seen by a ray turned with it, absorbs
+        // exactly as much as the untilted one.
+        let untilted = single_body(0.0);
+        let mut tilted = untilted.clone();
+        tilted[0].disk_normal = [1.0, 0.0, 0.0, 0.0];
+        let volumetric = |bodies: &[GpuBody]| {
+            let mut tracer = tracer(bodies, GeodesicModel::PseudoNewtonian, 0.05);
+            tracer.uniforms.disk_enabled = 1;
+            tracer.uniforms.disk_model = DiskModel::Volumetric as u32;
+            tracer
+        };
+        let expected = volumetric(&untilted).trace_ray(DVec3::new(8.0, 20.0, 0.0), -DVec3::Y);
+        let trace = volumetric(&tilted).trace_ray(DVec3::new(20.0, -8.0, 0.0), -DVec3::X);
+
+        assert_eq!(trace.disk_crossings.len(), 1, "{:?}", trace.disk_crossings);
+        let (body, crossing) = trace.disk_crossings[0];
+        assert_eq!(body, 0);
+        assert!(crossing.x.abs() < 1e-9);
+        assert!((trace.transmittance - expected.transmittance).abs() < 1e-9);
+        assert!(trace.transmittance < 0.9);
+    }
+
+    #[test]
+    fn single_disk_image_is_mixed_over_the_sky_by_its_opacity() {
+        // Straight down through the faint outer disk and on to the sky.
+        let bodies = single_body(0.0);
+        let trace = |disk_enabled: u32| {
+            let mut tracer = tracer(&bodies, GeodesicModel::PseudoNewtonian, 0.05);
+            tracer.uniforms.disk_enabled = disk_enabled;
+            tracer.trace_ray(DVec3::new(13.0, 20.0, 1.0), -DVec3::Y)
+        };
+        let (with_disk, sky) = (trace(1), trace(0).color);
+        assert_eq!(with_disk.fate, RayFate::Escaped);
+        assert_eq!(with_disk.disk_crossings.len(), 1);
+
+        // The disk's brightest channel is its opacity, so a single image is
+        // mix(sky, disk, opacity).
+        let opacity = 1.0 - with_disk.transmittance;
+        let disk = (with_disk.color - with_disk.transmittance * sky) / opacity;
+        assert!(opacity > 0.05 && opacity < 0.95, "{opacity}");
+        assert!((disk.max_element() - opacity).abs() < 1e-9, "{disk}");
+    }
+
+    #[test]
+    fn disk_images_beyond_the_first_crossing_are_drawn() {
+        // Just below the shadow of a disk seen nearly edge-on, the ray
+        // misses the near side of the disk and shows the underside of the
+        // far side, wrapped around the hole: the secondary image.
+        let bodies = single_body(0.0);
+        let mut camera = OrbitalCamera::new(25.0, 0.5, 1.52);
+        camera.fov = 0.5;
+        let to4 = |v: glam::Vec3| [v.x, v.y, v.z, 0.0];
+        let uniforms = Uniforms {
+            camera_pos: to4(camera.position()),
+            camera_forward: to4(camera.forward()),
+            camera_up: to4(camera.up()),
+            camera_right: to4(camera.right()),
+            fov: camera.fov,
+            render_mode: RenderMode::DiskOrder as u32,
+            ..Default::default()
+        };
+        let trace = ReferenceTracer::new(&uniforms, &bodies).trace_pixel(240, 225, 480, 270);
+
+        let radius = |p: DVec3| DVec2::new(p.x, p.z).length();
+        assert_eq!(trace.disk_crossings.len(), 2, "{:?}", trace.disk_crossings);
+        assert!(radius(trace.disk_crossings[0].1) > bodies[0].disk_outer as f64);
+        assert!(radius(trace.disk_crossings[1].1) < bodies[0].disk_outer as f64);
+        // The disk there is bright enough to hide the sky entirely.
+        assert_eq!(trace.fate, RayFate::Absorbed);
+        assert!(
+            (trace.color - order_color(1)).length() < 1e-9,
+            "{}",
+            trace.color
+        );
+    }
+
+    #[test]
+    fn diagnostic_views_show_how_each_ray_ended() {
+        let bodies = single_body(0.0);
+        let color = |mode: RenderMode, max_steps: u32| {
+            let mut tracer = tracer(&bodies, GeodesicModel::PseudoNewtonian, 0.05);
+            tracer.uniforms.render_mode = mode as u32;
+            tracer.uniforms.max_steps = max_steps;
+            tracer
+                .trace_ray(DVec3::new(0.0, 0.0, -45.0), DVec3::Z)
+                .color
+        };
+
+        // Straight into the hole: captured by body 0...
+        assert_eq!(color(RenderMode::Fate, 20_000), rgb(FATE_COLORS[1]));
+        assert_eq!(color(RenderMode::Body, 20_000), rgb(BODY_COLORS[0]));
+        // ...unless the steps run out first, with the ray still heading in.
+        assert_eq!(color(RenderMode::Fate, 3), rgb(FATE_COLORS[3]));
+        assert_eq!(color(RenderMode::Steps, 3), rgb(ramp_color(1.0)));
+        assert_eq!(color(RenderMode::Deflection, 20_000), DVec3::ZERO);
+    }
+
+    #[test]
+    fn reference_matches_gpu_output_for_diagnostic_views() {
+        for render_mode in &RenderMode::ALL[2..] {
+            let mut sim = Simulation::new(Preset::Single);
+            sim.bodies.push(Body::new(
+                glam::Vec3::new(4.0, 0.5, -3.0),
+                glam::Vec3::ZERO,
+                0.6,
+            ));
+            assert_matches_gpu(GpuCase {
+                sim,
+                render_mode: *render_mode,
+                ..Default::default()
+            });
+        }
+    }
+
+    #[test]
+    fn body_cutoff_freezes_far_bodies_over_each_step() {
+        let bodies = single_body(0.0);
+        let mut cut = tracer(&bodies, GeodesicModel::KerrSchild, 0.01);
+        cut.uniforms.body_cutoff = 3.0;
+        let full = tracer(&bodies, GeodesicModel::KerrSchild, 0.01);
+
+        // A ray passing 6 rs away never has the body within reach...
+        let origin = DVec3::new(6.0 * bodies[0].rs as f64, 0.0, -45.0);
+        let start = RayState {
+            x: origin.extend(0.0),
+            v: cut.kerr_initial_momentum(origin, DVec3::Z).extend(0.0),
+        };
+        assert!(cut.split_field(&start, 0.1).near.is_empty());
+
+        // ...yet is still lensed, because its field is only held fixed
+        // across each step rather than dropped.
+        let a = cut.trace_ray(origin, DVec3::Z);
+        let b = full.trace_ray(origin, DVec3::Z);
+        assert!(b.final_direction.x < -0.1, "{}", b.final_direction);
+        assert!(
+            (a.final_direction - b.final_direction).length() < 1e-3,
+            "{} vs {}",
+            a.final_direction,
+            b.final_direction
+        );
+    }
+}
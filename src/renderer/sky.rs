@@ -0,0 +1,56 @@
+//! Sky angles and blackbody colours shared by the CPU reference tracer, the
+//! environment maps and the star catalogue, mirroring the helpers of the
+//! same names in `shaders/ray_march.wgsl`.
+
+use glam::{DVec2, DVec3};
+
+const PI: f64 = std::f64::consts::PI;
+
+/// Polar angle from +y and azimuth of `dir`, as used to look up the sky.
+pub fn dir_to_spherical(dir: DVec3) -> DVec2 {
+    let theta = (dir.y / dir.length()).clamp(-1.0, 1.0).acos();
+    let phi = dir.z.atan2(dir.x) + PI;
+    DVec2::new(theta, phi)
+}
+
+/// Unit direction with polar angle `theta` from +y and azimuth `phi`,
+/// inverting `dir_to_spherical`.
+pub fn sky_direction(theta: f64, phi: f64) -> DVec3 {
+    let (sin_theta, cos_theta) = theta.sin_cos();
+    let (sin_phi, cos_phi) = (phi - PI).sin_cos();
+    DVec3::new(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi)
+}
+
+/// Approximate linear RGB of a blackbody at `temp` kelvin, brightest
+/// channel at 1.
+pub fn blackbody(temp: f64) -> DVec3 {
+    let t = temp / 100.0;
+    let (r, g) = if t <= 66.0 {
+        (1.0, (0.39008 * t.ln() - 0.63184).clamp(0.0, 1.0))
+    } else {
+        (
+            (1.2929 * (t - 60.0).powf(-0.1332)).clamp(0.0, 1.0),
+            (1.1298 * (t - 60.0).powf(-0.0755)).clamp(0.0, 1.0),
+        )
+    };
+    let b = if t >= 66.0 {
+        1.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        (0.5432 * (t - 10.0).ln() - 1.1962).clamp(0.0, 1.0)
+    };
+    DVec3::new(r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sky_direction_inverts_dir_to_spherical() {
+        let dir = DVec3::new(0.3, -0.4, -0.5).normalize();
+        let angles = dir_to_spherical(dir);
+        assert!(sky_direction(angles.x, angles.y).abs_diff_eq(dir, 1e-12));
+    }
+}
@@ -0,0 +1,404 @@
+//! Real star catalogues for the sky background.
+//!
+//! A catalogue (right ascension, declination, V magnitude and B−V colour
+//! index per star) is turned to the scene's axes for a chosen sky location of
+//! the black hole and sorted into bins of roughly equal size on the sky, so
+//! the shader only looks at the few stars near each escaping ray. Stars are
+//! drawn as small Gaussians with brightness 10^(−0.4 m) and the colour of a
+//! blackbody at the temperature their colour index implies.
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use bytemuck::{Pod, Zeroable};
+use glam::DVec3;
+
+use super::sky::{blackbody, dir_to_spherical, sky_direction};
+
+const PI: f64 = std::f64::consts::PI;
+
+/// Rows of bins from the +y pole to the −y pole. Mirrored, with
+/// `STAR_CUTOFF` and `STAR_WIDTH`, in `shaders/ray_march.wgsl`.
+pub const STAR_ROWS: usize = 256;
+
+/// Peak radiance of a magnitude 0 star.
+pub const STAR_PEAK: f64 = 20.0;
+
+/// Star Gaussians are cut off at this many standard deviations.
+pub const STAR_CUTOFF: f64 = 3.0;
+
+/// Standard deviation of a star Gaussian in pixels at the image centre.
+pub const STAR_WIDTH: f64 = 0.6;
+
+/// Colour index of stars the catalogue gives none for, about the Sun's.
+const DEFAULT_COLOR_INDEX: f64 = 0.65;
+
+/// Where the black hole is on Earth's sky, in degrees. The scene is turned
+/// so that the camera looking down −z, with +y up, sees the sky around this
+/// point with north up and east to the left, as from Earth.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkyLocation {
+    pub ra: f64,
+    pub dec: f64,
+}
+
+impl Default for SkyLocation {
+    /// Sagittarius A*, the black hole at the centre of the Milky Way.
+    fn default() -> Self {
+        Self {
+            ra: 266.417,
+            dec: -29.008,
+        }
+    }
+}
+
+impl SkyLocation {
+    /// The scene direction of the equatorial unit vector `v`.
+    pub fn to_scene(self, v: DVec3) -> DVec3 {
+        let los = equatorial(self.ra, self.dec);
+        let north = (DVec3::Z - DVec3::Z.dot(los) * los)
+            .try_normalize()
+            .unwrap_or(DVec3::X);
+        let (x, y, z) = (los.cross(north), north, -los);
+        DVec3::new(v.dot(x), v.dot(y), v.dot(z))
+    }
+}
+
+/// Equatorial unit vector towards right ascension `ra` and declination `dec`
+/// in degrees: x towards the vernal equinox, z towards the north pole.
+pub fn equatorial(ra: f64, dec: f64) -> DVec3 {
+    let (ra, dec) = (ra.to_radians(), dec.to_radians());
+    DVec3::new(dec.cos() * ra.cos(), dec.cos() * ra.sin(), dec.sin())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Star {
+    /// Right ascension in degrees.
+    pub ra: f64,
+    /// Declination in degrees.
+    pub dec: f64,
+    /// Apparent visual magnitude.
+    pub magnitude: f64,
+    /// B−V colour index.
+    pub color_index: f64,
+}
+
+impl Star {
+    /// Linear RGB of a blackbody at the star's temperature, scaled to unit
+    /// luminance so the magnitude alone sets how bright it looks.
+    pub fn color(&self) -> DVec3 {
+        // Ballesteros (2012)
+        let bv = self.color_index;
+        let temp = 4600.0 * (1.0 / (0.92 * bv + 1.7) + 1.0 / (0.92 * bv + 0.62));
+        let rgb = blackbody(temp.clamp(1000.0, 40000.0));
+        rgb / rgb.dot(DVec3::new(0.2126, 0.7152, 0.0722))
+    }
+
+    pub fn brightness(&self) -> f64 {
+        STAR_PEAK * 10f64.powf(-0.4 * self.magnitude)
+    }
+}
+
+#[derive(Debug)]
+pub enum CatalogError {
+    Io(io::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::Io(e) => write!(f, "{}", e),
+            CatalogError::Invalid(reason) => write!(f, "invalid star catalogue: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+impl From<io::Error> for CatalogError {
+    fn from(e: io::Error) -> Self {
+        CatalogError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StarCatalog {
+    pub stars: Vec<Star>,
+}
+
+impl StarCatalog {
+    pub fn load(path: &Path) -> Result<Self, CatalogError> {
+        Self::from_csv(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse a CSV catalogue with a header row naming its columns:
+    /// `ra_deg` (degrees) or `ra` (hours, as in the HYG database), `dec`
+    /// (degrees), `mag`, and optionally `ci` or `bv` for the B−V index.
+    /// Other columns are ignored, so HYG files load as they are.
+    pub fn from_csv(text: &str) -> Result<Self, CatalogError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty());
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| CatalogError::Invalid("empty file".into()))?;
+        let columns: Vec<String> = split(header).map(|c| c.to_lowercase()).collect();
+        let column = |names: &[&str]| columns.iter().position(|c| names.contains(&c.as_str()));
+        let missing = |name: &str| CatalogError::Invalid(format!("no '{}' column", name));
+        let (ra, ra_scale) = match column(&["ra_deg"]) {
+            Some(i) => (i, 1.0),
+            None => (column(&["ra"]).ok_or_else(|| missing("ra"))?, 15.0),
+        };
+        let dec = column(&["dec", "dec_deg"]).ok_or_else(|| missing("dec"))?;
+        let mag = column(&["mag", "vmag"]).ok_or_else(|| missing("mag"))?;
+        let color_index = column(&["ci", "bv", "b_v"]);
+
+        let mut stars = Vec::new();
+        for (n, line) in lines {
+            let fields: Vec<&str> = split(line).collect();
+            let number = |i: usize, name: &str| -> Result<f64, CatalogError> {
+                fields
+                    .get(i)
+                    .and_then(|f| f.parse().ok())
+                    .filter(|x: &f64| x.is_finite())
+                    .ok_or_else(|| {
+                        CatalogError::Invalid(format!("line {}: bad or missing {}", n + 1, name))
+                    })
+            };
+            let star = Star {
+                ra: number(ra, "ra")? * ra_scale,
+                dec: number(dec, "dec")?,
+                magnitude: number(mag, "mag")?,
+                color_index: color_index
+                    .and_then(|i| fields.get(i))
+                    .and_then(|f| f.parse().ok())
+                    .filter(|x: &f64| x.is_finite())
+                    .unwrap_or(DEFAULT_COLOR_INDEX),
+            };
+            // HYG lists the Sun first.
+            if star.magnitude > -5.0 {
+                stars.push(star);
+            }
+        }
+        Ok(Self { stars })
+    }
+
+    /// The stars turned to scene directions for `sky` and binned for the
+    /// shader.
+    pub fn bin(&self, sky: SkyLocation) -> StarBins {
+        let offsets = row_offsets();
+        let cell_count = offsets[STAR_ROWS] as usize;
+        let mut binned: Vec<(u32, GpuStar)> = self
+            .stars
+            .iter()
+            .map(|star| {
+                let dir = sky.to_scene(equatorial(star.ra, star.dec));
+                let angles = dir_to_spherical(dir);
+                let row = ((angles.x / row_height()) as usize).min(STAR_ROWS - 1);
+                let columns = offsets[row + 1] - offsets[row];
+                let column = ((angles.y / (2.0 * PI) * columns as f64) as u32).min(columns - 1);
+                let color = star.color();
+                let gpu = GpuStar {
+                    direction: dir.as_vec3().to_array(),
+                    brightness: star.brightness() as f32,
+                    color: [color.x as f32, color.y as f32, color.z as f32, 0.0],
+                };
+                (offsets[row] + column, gpu)
+            })
+            .collect();
+        binned.sort_by_key(|(cell, _)| *cell);
+
+        // Row offsets, then where each cell's stars start.
+        let mut index = offsets;
+        let mut next = 0;
+        for cell in 0..=cell_count as u32 {
+            while next < binned.len() && binned[next].0 < cell {
+                next += 1;
+            }
+            index.push(next as u32);
+        }
+        StarBins {
+            index,
+            stars: binned.into_iter().map(|(_, star)| star).collect(),
+        }
+    }
+}
+
+/// Fields of a CSV line, unquoted.
+fn split(line: &str) -> impl Iterator<Item = &str> {
+    line.split(',').map(|f| f.trim().trim_matches('"'))
+}
+
+fn row_height() -> f64 {
+    PI / STAR_ROWS as f64
+}
+
+/// First bin of each row and, last, the total number of bins. Rows have as
+/// many bins as fit around their mid-latitude at about the row height apart.
+fn row_offsets() -> Vec<u32> {
+    let mut offsets = Vec::with_capacity(STAR_ROWS + 1);
+    let mut total = 0;
+    for row in 0..STAR_ROWS {
+        offsets.push(total);
+        let mid = (row as f64 + 0.5) * row_height();
+        total += ((2 * STAR_ROWS) as f64 * mid.sin()).ceil().max(1.0) as u32;
+    }
+    offsets.push(total);
+    offsets
+}
+
+/// Gaussian width of stars on the sky, in radians, for an image `height`
+/// pixels tall with vertical field of view `fov`.
+pub fn star_sigma(fov: f64, height: f64) -> f64 {
+    STAR_WIDTH * 2.0 * (0.5 * fov).tan() / height
+}
+
+/// A star as the shader reads it.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+pub struct GpuStar {
+    pub direction: [f32; 3],
+    pub brightness: f32,
+    pub color: [f32; 4],
+}
+
+/// Stars sorted by bin, with `index` holding the first bin of each of the
+/// `STAR_ROWS` rows and the bin count, then the first star of each bin and
+/// the star count.
+#[derive(Debug, Clone)]
+pub struct StarBins {
+    pub index: Vec<u32>,
+    pub stars: Vec<GpuStar>,
+}
+
+impl StarBins {
+    pub fn empty() -> Self {
+        StarCatalog::default().bin(SkyLocation::default())
+    }
+
+    /// Sum of the star Gaussians of width `sigma` (radians) seen in the
+    /// direction with polar angle `theta` and azimuth `phi`, visiting the
+    /// bins the shader visits.
+    pub fn radiance(&self, theta: f64, phi: f64, sigma: f64) -> DVec3 {
+        let dir = sky_direction(theta, phi);
+        // Angle subtended by the cutoff chord
+        let reach = 2.0 * (0.5 * STAR_CUTOFF * sigma).min(1.0).asin();
+        // Widest azimuth span of the cap within `reach`; all of it near a pole
+        let half_width = if theta > reach && theta < PI - reach {
+            (reach.sin() / theta.sin()).min(1.0).asin()
+        } else {
+            PI
+        };
+        let first_row = ((theta - reach) / row_height()).floor().max(0.0) as usize;
+        let last_row = (((theta + reach) / row_height()).floor() as usize).min(STAR_ROWS - 1);
+        let mut sum = DVec3::ZERO;
+        for row in first_row..=last_row {
+            let start = self.index[row] as i64;
+            let columns = self.index[row + 1] as i64 - start;
+            let lo = ((phi - half_width) / (2.0 * PI) * columns as f64).floor() as i64;
+            let hi = ((phi + half_width) / (2.0 * PI) * columns as f64).floor() as i64;
+            let (lo, hi) = if hi - lo + 1 >= columns {
+                (0, columns - 1)
+            } else {
+                (lo, hi)
+            };
+            for column in lo..=hi {
+                let cell = STAR_ROWS + 1 + (start + column.rem_euclid(columns)) as usize;
+                for star in &self.stars[self.index[cell] as usize..self.index[cell + 1] as usize] {
+                    sum += star_glow(star, dir, sigma);
+                }
+            }
+        }
+        sum
+    }
+}
+
+fn star_glow(star: &GpuStar, dir: DVec3, sigma: f64) -> DVec3 {
+    let to_star = DVec3::from_array(star.direction.map(|x| x as f64));
+    // Chord length, which is the angle to well within f32 precision
+    let d = (dir - to_star).length();
+    if d >= STAR_CUTOFF * sigma {
+        return DVec3::ZERO;
+    }
+    let color = DVec3::new(
+        star.color[0] as f64,
+        star.color[1] as f64,
+        star.color[2] as f64,
+    );
+    color * star.brightness as f64 * (-0.5 * (d / sigma).powi(2)).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sky_location_puts_north_up_and_east_left() {
+        let sky = SkyLocation {
+            ra: 83.8,
+            dec: 22.0,
+        };
+        let centre = sky.to_scene(equatorial(sky.ra, sky.dec));
+        assert!(centre.abs_diff_eq(DVec3::NEG_Z, 1e-12));
+        let north = sky.to_scene(equatorial(sky.ra, sky.dec + 1.0));
+        assert!(north.y > 0.0 && north.x.abs() < 1e-12);
+        let east = sky.to_scene(equatorial(sky.ra + 1.0, sky.dec));
+        assert!(east.x < 0.0);
+    }
+
+    #[test]
+    fn binned_lookup_finds_every_star_in_reach() {
+        // A spiral of stars from pole to pole, seen from next to every
+        // seventh of them.
+        let catalog = StarCatalog {
+            stars: (0..2000)
+                .map(|i| Star {
+                    ra: (i as f64 * 37.3) % 360.0,
+                    dec: -90.0 + 180.0 * (i as f64 + 0.5) / 2000.0,
+                    magnitude: (i % 7) as f64,
+                    color_index: (i % 5) as f64 * 0.4 - 0.2,
+                })
+                .collect(),
+        };
+        let sky = SkyLocation::default();
+        let bins = catalog.bin(sky);
+        assert_eq!(bins.stars.len(), 2000);
+        let sigma = 0.01;
+        for (i, star) in catalog.stars.iter().enumerate().step_by(7) {
+            let dir = sky.to_scene(equatorial(star.ra + 0.5, star.dec + 0.3));
+            let angles = dir_to_spherical(dir);
+            let brute: DVec3 = bins.stars.iter().map(|s| star_glow(s, dir, sigma)).sum();
+            let binned = bins.radiance(angles.x, angles.y, sigma);
+            assert!(binned.abs_diff_eq(brute, 1e-9), "star {}", i);
+        }
+        // Just past the azimuth seam, with a reach of several bins
+        let dir = DVec3::new(-1.0, 0.0, 1e-4).normalize();
+        let angles = dir_to_spherical(dir);
+        let brute: DVec3 = bins.stars.iter().map(|s| star_glow(s, dir, 0.2)).sum();
+        assert!(bins
+            .radiance(angles.x, angles.y, 0.2)
+            .abs_diff_eq(brute, 1e-9));
+    }
+
+    #[test]
+    fn hyg_style_csv_loads_with_hours_and_skips_the_sun() {
+        let csv = "\"id\",\"proper\",\"ra\",\"dec\",\"mag\",\"ci\"\n\
+                   0,Sol,0.0,0.0,-26.7,0.656\n\
+                   32263,Sirius,6.752481,-16.716116,-1.44,0.009\n\
+                   1,,0.5,10.0,9.1,\n";
+        let catalog = StarCatalog::from_csv(csv).unwrap();
+        assert_eq!(catalog.stars.len(), 2);
+        let sirius = catalog.stars[0];
+        assert!((sirius.ra - 101.287).abs() < 1e-3);
+        assert_eq!(catalog.stars[1].color_index, DEFAULT_COLOR_INDEX);
+        // Hot stars are bluer than cool ones; Sirius outshines the faint one.
+        assert!(sirius.color().z > catalog.stars[1].color().z);
+        assert!(sirius.brightness() / catalog.stars[1].brightness() > 1000.0);
+
+        assert!(StarCatalog::from_csv("ra,dec\n1,2\n").is_err());
+        assert!(StarCatalog::from_csv("ra,dec,mag\n1,x,2\n").is_err());
+    }
+}
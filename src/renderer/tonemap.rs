@@ -0,0 +1,118 @@
+//! Display transforms from linear HDR radiance to 0..1 display values. The
+//! window applies the same curves in `shaders/fullscreen.wgsl`, selected by
+//! `Tonemapper as u32`.
+
+use glam::{Mat3, Vec3};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tonemapper {
+    /// Clamp to 0..1.
+    None = 0,
+    /// ACES filmic curve (Narkowicz fit).
+    #[default]
+    Aces = 1,
+    /// x / (1 + x): never clips, but flattens highlights.
+    Reinhard = 2,
+    /// AgX: desaturates towards white instead of skewing hue as it clips.
+    Agx = 3,
+}
+
+impl Tonemapper {
+    pub const ALL: [Tonemapper; 4] = [
+        Tonemapper::Aces,
+        Tonemapper::Agx,
+        Tonemapper::Reinhard,
+        Tonemapper::None,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tonemapper::None => "None",
+            Tonemapper::Aces => "ACES",
+            Tonemapper::Reinhard => "Reinhard",
+            Tonemapper::Agx => "AgX",
+        }
+    }
+
+    pub fn apply(self, x: Vec3) -> Vec3 {
+        let mapped = match self {
+            Tonemapper::None => x,
+            Tonemapper::Aces => aces(x),
+            Tonemapper::Reinhard => x / (Vec3::ONE + x.max(Vec3::ZERO)),
+            Tonemapper::Agx => agx(x),
+        };
+        mapped.clamp(Vec3::ZERO, Vec3::ONE)
+    }
+}
+
+fn aces(x: Vec3) -> Vec3 {
+    let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+    (x * (a * x + b)) / (x * (c * x + d) + e)
+}
+
+/// Minimal AgX (Wrensch's fit of Sobotka's curve), returned linear.
+fn agx(x: Vec3) -> Vec3 {
+    const MIN_EV: f32 = -12.473_93;
+    const MAX_EV: f32 = 4.026_069;
+    let inset = Mat3::from_cols_array(&[
+        0.842_479_06,
+        0.042_328_24,
+        0.042_375_655,
+        0.078_433_6,
+        0.878_468_6,
+        0.078_433_6,
+        0.079_223_745,
+        0.079_166_13,
+        0.879_143,
+    ]);
+    let outset = Mat3::from_cols_array(&[
+        1.196_879,
+        -0.052_896_85,
+        -0.052_971_635,
+        -0.098_020_88,
+        1.151_903_1,
+        -0.098_043_45,
+        -0.099_029_74,
+        -0.098_961_18,
+        1.151_073_7,
+    ]);
+
+    let v = inset * x.max(Vec3::splat(1e-10));
+    let v = Vec3::from_array(v.to_array().map(f32::log2))
+        .clamp(Vec3::splat(MIN_EV), Vec3::splat(MAX_EV));
+    let v = (v - MIN_EV) / (MAX_EV - MIN_EV);
+    // Sixth-order fit of the AgX contrast curve
+    let v2 = v * v;
+    let v4 = v2 * v2;
+    let v =
+        15.5 * v4 * v2 - 40.14 * v4 * v + 31.96 * v4 - 6.868 * v2 * v + 0.4298 * v2 + 0.1191 * v
+            - 0.00232;
+    (outset * v).max(Vec3::ZERO).powf(2.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tonemappers_are_monotonic_from_black() {
+        for tonemapper in Tonemapper::ALL {
+            let mut last = tonemapper.apply(Vec3::ZERO).x;
+            assert!(last < 0.01, "{}: black maps to {}", tonemapper.name(), last);
+            for i in 1..200 {
+                let x = 0.01 * 1.05_f32.powi(i);
+                let y = tonemapper.apply(Vec3::splat(x)).x;
+                assert!(y >= last - 1e-6, "{} dips at {}", tonemapper.name(), x);
+                last = y;
+            }
+            assert!(
+                last > 0.9,
+                "{}: white reaches only {}",
+                tonemapper.name(),
+                last
+            );
+        }
+    }
+}
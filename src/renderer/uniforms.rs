@@ -1,4 +1,7 @@
 use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
+
+use super::environment::Projection;
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Pod, Zeroable)]
@@ -16,7 +19,37 @@ pub struct Uniforms {
     pub background_mode: u32,
     pub time: f32,
     pub grid_enabled: u32,
-    pub _padding: [f32; 2],
+    pub geodesic_model: u32,
+    pub integrator: u32,
+    pub tolerance: f32,
+    pub min_step: f32,
+    pub max_step: f32,
+    /// Bodies farther than this many Schwarzschild radii from a step have
+    /// their field evaluated once per step instead of at every integrator
+    /// stage; 0 evaluates every body at every stage.
+    pub body_cutoff: f32,
+    /// Offset of this sample's ray from the pixel centre, in pixels.
+    pub jitter: [f32; 2],
+    /// Samples already accumulated for this image; 0 starts afresh.
+    pub sample_index: u32,
+    pub disk_model: u32,
+    /// Volumetric disk: scale height over radius, H/R.
+    pub disk_scale_height: f32,
+    /// Volumetric disk: vertical optical depth through the inner edge.
+    pub disk_optical_depth: f32,
+    pub render_mode: u32,
+    /// Disk light is boosted by the gas's orbital motion (beaming), 1 or 0.
+    pub doppler_beaming: u32,
+    /// Disk light loses energy climbing out to the camera, 1 or 0.
+    pub gravitational_redshift: u32,
+    /// Disk intensity scales as gⁿ with g = ν_obs / ν_emit.
+    pub intensity_exponent: f32,
+    /// `environment::Projection` of the background image.
+    pub environment_projection: u32,
+    pub _pad: u32,
+    /// Camera velocity relative to the local static observer, as a
+    /// fraction of c; w unused.
+    pub observer_velocity: [f32; 4],
 }
 
 impl Default for Uniforms {
@@ -35,7 +68,288 @@ impl Default for Uniforms {
             background_mode: 0,
             time: 0.0,
             grid_enabled: 0,
-            _padding: [0.0; 2],
+            geodesic_model: 0,
+            integrator: Integrator::FixedRk4 as u32,
+            tolerance: 1e-4,
+            min_step: 0.005,
+            max_step: 2.0,
+            body_cutoff: 0.0,
+            jitter: [0.0; 2],
+            sample_index: 0,
+            disk_model: DiskModel::Thin as u32,
+            disk_scale_height: 0.1,
+            disk_optical_depth: 2.0,
+            render_mode: RenderMode::Shaded as u32,
+            doppler_beaming: 1,
+            gravitational_redshift: 1,
+            intensity_exponent: 4.0,
+            environment_projection: Projection::Equirectangular as u32,
+            _pad: 0,
+            observer_velocity: [0.0; 4],
+        }
+    }
+}
+
+/// Sub-pixel offset of progressive sample `index`: the pixel centre first,
+/// then the Halton (2, 3) sequence, which covers the pixel evenly however
+/// many samples are taken.
+pub fn sample_jitter(index: u32) -> [f32; 2] {
+    if index == 0 {
+        return [0.0; 2];
+    }
+    [halton(index, 2) - 0.5, halton(index, 3) - 0.5]
+}
+
+fn halton(mut index: u32, base: u32) -> f32 {
+    let (mut result, mut scale) = (0.0, 1.0);
+    while index > 0 {
+        scale /= base as f32;
+        result += scale * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// What the ray march draws, mirrored by the `MODE_*` constants in the shader.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    /// The lensed disk and sky.
+    #[default]
+    Shaded = 0,
+    /// Each image of the disk in the false colour of its order (how many
+    /// times the ray crossed the disk plane before), over a dimmed sky.
+    DiskOrder = 1,
+    /// Integration steps the ray took, including rejected adaptive steps,
+    /// as a fraction of `max_steps` on `ramp_color`.
+    Steps = 2,
+    /// Angle between the camera ray and its final direction, 0 to 180°,
+    /// on `ramp_color`; rays that never reach the sky are black.
+    Deflection = 3,
+    /// How the ray ended, in `FATE_COLORS`.
+    Fate = 4,
+    /// Frequency ratio g = ν_obs/ν_emit where the ray first meets a disk, in
+    /// `redshift_color`, over a dimmed sky.
+    Redshift = 5,
+    /// The body whose horizon the ray crossed, in `BODY_COLORS`, over a
+    /// dimmed sky.
+    Body = 6,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 7] = [
+        RenderMode::Shaded,
+        RenderMode::DiskOrder,
+        RenderMode::Steps,
+        RenderMode::Deflection,
+        RenderMode::Fate,
+        RenderMode::Redshift,
+        RenderMode::Body,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderMode::Shaded => "Shaded",
+            RenderMode::DiskOrder => "Disk image order",
+            RenderMode::Steps => "Step count",
+            RenderMode::Deflection => "Deflection",
+            RenderMode::Fate => "Ray fate",
+            RenderMode::Redshift => "Disk redshift",
+            RenderMode::Body => "Capturing body",
+        }
+    }
+
+    /// Name on the command line.
+    pub fn flag(self) -> &'static str {
+        match self {
+            RenderMode::Shaded => "shaded",
+            RenderMode::DiskOrder => "disk-order",
+            RenderMode::Steps => "steps",
+            RenderMode::Deflection => "deflection",
+            RenderMode::Fate => "fate",
+            RenderMode::Redshift => "redshift",
+            RenderMode::Body => "body",
+        }
+    }
+}
+
+/// False colours of disk images of order 0 (direct), 1, 2 and 3 or more,
+/// as `order_color` in the shader.
+pub const DISK_ORDER_COLORS: [[f32; 3]; 4] = [
+    [1.0, 0.25, 0.1],
+    [0.2, 1.0, 0.2],
+    [0.2, 0.4, 1.0],
+    [1.0, 0.3, 1.0],
+];
+
+/// False colours of ray fates, as `fate_color` in the shader: escaped,
+/// captured by a horizon, out of steps heading away (drawn as escaped), out
+/// of steps heading inwards (drawn as captured), and absorbed by the disk.
+pub const FATE_COLORS: [[f32; 3]; 5] = [
+    [0.2, 0.4, 1.0],
+    [0.5, 0.0, 0.0],
+    [1.0, 0.9, 0.1],
+    [1.0, 0.3, 1.0],
+    [1.0, 0.5, 0.1],
+];
+
+/// False colours of the capturing body, repeating past the sixth, as
+/// `body_color` in the shader.
+pub const BODY_COLORS: [[f32; 3]; 6] = [
+    [1.0, 0.25, 0.1],
+    [0.2, 1.0, 0.2],
+    [0.2, 0.4, 1.0],
+    [1.0, 0.9, 0.1],
+    [1.0, 0.3, 1.0],
+    [0.1, 0.9, 0.9],
+];
+
+/// Stops of the dark blue to red ramp of the step count and deflection
+/// views, evenly spaced over 0..1.
+const RAMP_STOPS: [[f32; 3]; 5] = [
+    [0.05, 0.05, 0.3],
+    [0.1, 0.4, 1.0],
+    [0.1, 0.9, 0.6],
+    [0.95, 0.9, 0.1],
+    [1.0, 0.15, 0.05],
+];
+
+/// Colour of `t` in 0..1 on the diagnostic ramp, as `ramp_color` in the
+/// shader.
+pub fn ramp_color(t: f32) -> [f32; 3] {
+    let x = t.clamp(0.0, 1.0) * (RAMP_STOPS.len() - 1) as f32;
+    let i = (x as usize).min(RAMP_STOPS.len() - 2);
+    let f = x - i as f32;
+    let (a, b) = (RAMP_STOPS[i], RAMP_STOPS[i + 1]);
+    [0, 1, 2].map(|c| a[c] + (b[c] - a[c]) * f)
+}
+
+/// Colour of frequency ratio `g`: white when unshifted, fading to red for
+/// g ≤ 1/2 and to blue for g ≥ 2, as `redshift_color` in the shader.
+pub fn redshift_color(g: f32) -> [f32; 3] {
+    const RED: [f32; 3] = [1.0, 0.15, 0.05];
+    const BLUE: [f32; 3] = [0.1, 0.3, 1.0];
+    let t = g.max(1e-6).log2().clamp(-1.0, 1.0);
+    let end = if t < 0.0 { RED } else { BLUE };
+    end.map(|c| 1.0 + (c - 1.0) * t.abs())
+}
+
+/// Accretion disk model, mirrored by the `DISK_*` constants in the shader.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskModel {
+    /// Infinitely thin emitting sheet in the equatorial plane.
+    #[default]
+    Thin = 0,
+    /// Gas spread above and below the plane that emits and absorbs along
+    /// the bent ray, so the inner flow puffs up and hides what is behind it.
+    Volumetric = 1,
+}
+
+impl DiskModel {
+    pub const ALL: [DiskModel; 2] = [DiskModel::Thin, DiskModel::Volumetric];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DiskModel::Thin => "Thin",
+            DiskModel::Volumetric => "Volumetric",
+        }
+    }
+}
+
+/// User-facing accretion disk controls, shared by the app and headless renders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiskSettings {
+    pub model: DiskModel,
+    /// Gaussian scale height of the volumetric disk over radius, H/R.
+    pub scale_height: f32,
+    /// Optical depth straight through the volumetric disk at its inner
+    /// edge; the column thins out as 1/R further out.
+    pub optical_depth: f32,
+    /// Shift and boost disk light by the orbital motion of the gas.
+    pub doppler_beaming: bool,
+    /// Shift and dim disk light by the climb out of the potential well.
+    pub gravitational_redshift: bool,
+    /// Scale specific intensity as g³ instead of bolometric intensity as g⁴.
+    pub specific_intensity: bool,
+}
+
+impl DiskSettings {
+    /// Exponent n of the gⁿ intensity scaling.
+    pub fn intensity_exponent(&self) -> f32 {
+        if self.specific_intensity {
+            3.0
+        } else {
+            4.0
+        }
+    }
+}
+
+impl Default for DiskSettings {
+    fn default() -> Self {
+        let u = Uniforms::default();
+        Self {
+            model: DiskModel::Thin,
+            scale_height: u.disk_scale_height,
+            optical_depth: u.disk_optical_depth,
+            doppler_beaming: true,
+            gravitational_redshift: true,
+            specific_intensity: false,
+        }
+    }
+}
+
+/// Ray integration scheme, mirrored by the `INTEGRATOR_*` constants in the shader.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Integrator {
+    /// Classical RK4 with a constant `step_size`.
+    #[serde(rename = "rk4")]
+    FixedRk4 = 0,
+    /// Dormand–Prince 5(4) with embedded error control.
+    #[serde(rename = "rk45")]
+    AdaptiveRk45 = 1,
+}
+
+impl Integrator {
+    pub const ALL: [Integrator; 2] = [Integrator::FixedRk4, Integrator::AdaptiveRk45];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Integrator::FixedRk4 => "Fixed RK4",
+            Integrator::AdaptiveRk45 => "Adaptive RK45",
+        }
+    }
+}
+
+/// User-facing integration controls, shared by the app and headless renders.
+#[derive(Debug, Clone, Copy)]
+pub struct IntegrationSettings {
+    pub integrator: Integrator,
+    /// Step budget per ray; rejected adaptive steps count too.
+    pub max_steps: u32,
+    /// Fixed step, or the first trial step of the adaptive integrator.
+    pub step_size: f32,
+    /// Allowed local error per step, relative to the distance to the nearest body.
+    pub tolerance: f32,
+    pub min_step: f32,
+    pub max_step: f32,
+    /// Far-body cutoff in Schwarzschild radii, 0 for none.
+    pub body_cutoff: f32,
+}
+
+impl Default for IntegrationSettings {
+    fn default() -> Self {
+        let u = Uniforms::default();
+        Self {
+            integrator: Integrator::FixedRk4,
+            max_steps: u.max_steps,
+            step_size: u.step_size,
+            tolerance: u.tolerance,
+            min_step: u.min_step,
+            max_step: u.max_step,
+            body_cutoff: u.body_cutoff,
         }
     }
 }
@@ -0,0 +1,563 @@
+//! Versioned, human-editable scene files (TOML) holding everything needed to
+//! reproduce a shot: bodies, simulation state, camera and render settings.
+//!
+//! ```toml
+//! version = 1
+//!
+//! [simulation]
+//! time = 0.0
+//! pn_order = "2.5pn"
+//!
+//! [camera]
+//! distance = 15.0
+//!
+//! [[body]]
+//! position = [3.0, 0.0, 0.0]
+//! velocity = [0.0, 0.0, 0.15]
+//! rs = 0.5
+//!
+//! [[keyframe]]
+//! time = 0.0
+//! distance = 15.0
+//! ```
+//!
+//! Every table and field except `version` is optional and falls back to the
+//! defaults of a fresh app. Numbers are stored as f64 decimals so values typed
+//! into the UI read back as typed.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::animation::Timeline;
+use crate::post_newtonian::PnOrder;
+use crate::renderer::camera::OrbitalCamera;
+use crate::renderer::post::PostSettings;
+use crate::renderer::stars::SkyLocation;
+use crate::renderer::tonemap::Tonemapper;
+use crate::renderer::uniforms::{DiskModel, DiskSettings, IntegrationSettings, Integrator};
+use crate::simulation::{Body, Simulation};
+
+/// Current scene format version. Bump it when a field changes meaning, and
+/// keep loading older versions.
+pub const SCENE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub version: u32,
+    #[serde(default)]
+    pub simulation: SimulationSettings,
+    #[serde(default)]
+    pub camera: CameraSettings,
+    #[serde(default)]
+    pub render: RenderSettings,
+    #[serde(default, rename = "body")]
+    pub bodies: Vec<BodySettings>,
+    /// Camera animation, rendered with `--frames`.
+    #[serde(
+        default,
+        rename = "keyframe",
+        skip_serializing_if = "Timeline::is_empty"
+    )]
+    pub timeline: Timeline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulationSettings {
+    pub time: f64,
+    pub paused: bool,
+    pub speed: f64,
+    pub pn_order: PnOrder,
+}
+
+impl Default for SimulationSettings {
+    fn default() -> Self {
+        Self {
+            time: 0.0,
+            paused: true,
+            speed: 1.0,
+            pn_order: PnOrder::Newtonian,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    pub distance: f64,
+    pub azimuth: f64,
+    pub elevation: f64,
+    pub target: [f64; 3],
+    pub fov: f64,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self::from(&OrbitalCamera::new(10.0, 0.5, 1.2))
+    }
+}
+
+impl From<&OrbitalCamera> for CameraSettings {
+    fn from(camera: &OrbitalCamera) -> Self {
+        Self {
+            distance: decimal(camera.distance),
+            azimuth: decimal(camera.azimuth),
+            elevation: decimal(camera.elevation),
+            target: vector(camera.target),
+            fov: decimal(camera.fov),
+        }
+    }
+}
+
+impl CameraSettings {
+    pub fn apply(&self, camera: &mut OrbitalCamera) {
+        camera.distance = self.distance as f32;
+        camera.azimuth = self.azimuth as f32;
+        camera.elevation = self.elevation as f32;
+        camera.target = Vec3::from_array(self.target.map(|x| x as f32));
+        camera.fov = self.fov as f32;
+    }
+}
+
+/// Background shown where rays escape, mirroring `Uniforms::background_mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Background {
+    Checker,
+    Stars,
+    /// `RenderSettings::background_image`.
+    Image,
+    /// `RenderSettings::star_catalog`.
+    Catalog,
+}
+
+impl Background {
+    pub fn from_mode(mode: u32) -> Self {
+        match mode {
+            0 => Background::Checker,
+            2 => Background::Image,
+            3 => Background::Catalog,
+            _ => Background::Stars,
+        }
+    }
+
+    pub fn mode(self) -> u32 {
+        match self {
+            Background::Checker => 0,
+            Background::Stars => 1,
+            Background::Image => 2,
+            Background::Catalog => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    pub integrator: Integrator,
+    pub max_steps: u32,
+    pub step_size: f64,
+    pub tolerance: f64,
+    pub min_step: f64,
+    pub max_step: f64,
+    /// Far-body cutoff in Schwarzschild radii, 0 for none.
+    pub body_cutoff: f64,
+    pub background: Background,
+    /// Equirectangular image or cubemap directory for `Background::Image`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_image: Option<PathBuf>,
+    /// Star catalogue CSV for `Background::Catalog`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub star_catalog: Option<PathBuf>,
+    /// Right ascension of the black hole on the sky, in degrees.
+    pub sky_ra: f64,
+    /// Declination of the black hole on the sky, in degrees.
+    pub sky_dec: f64,
+    pub grid: bool,
+    pub disk: bool,
+    pub disk_model: DiskModel,
+    /// Volumetric disk scale height over radius.
+    pub disk_scale_height: f64,
+    /// Volumetric disk optical depth at the inner edge.
+    pub disk_optical_depth: f64,
+    pub doppler_beaming: bool,
+    pub gravitational_redshift: bool,
+    /// Scale disk intensity as g³ rather than g⁴.
+    pub specific_intensity: bool,
+    pub exact_geodesics: bool,
+    /// Exposure in stops.
+    pub exposure: f64,
+    pub tonemap: Tonemapper,
+    /// Bloom strength, 0 for none.
+    pub bloom: f64,
+    pub bloom_threshold: f64,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self::new(
+            &IntegrationSettings::default(),
+            &DiskSettings::default(),
+            &PostSettings::default(),
+        )
+    }
+}
+
+impl RenderSettings {
+    /// Integration, disk and post-processing settings with the remaining
+    /// fields at their defaults.
+    pub fn new(
+        integration: &IntegrationSettings,
+        disk: &DiskSettings,
+        post: &PostSettings,
+    ) -> Self {
+        Self {
+            integrator: integration.integrator,
+            max_steps: integration.max_steps,
+            step_size: decimal(integration.step_size),
+            tolerance: decimal(integration.tolerance),
+            min_step: decimal(integration.min_step),
+            max_step: decimal(integration.max_step),
+            body_cutoff: decimal(integration.body_cutoff),
+            background: Background::Stars,
+            background_image: None,
+            star_catalog: None,
+            sky_ra: SkyLocation::default().ra,
+            sky_dec: SkyLocation::default().dec,
+            grid: false,
+            disk: true,
+            disk_model: disk.model,
+            disk_scale_height: decimal(disk.scale_height),
+            disk_optical_depth: decimal(disk.optical_depth),
+            doppler_beaming: disk.doppler_beaming,
+            gravitational_redshift: disk.gravitational_redshift,
+            specific_intensity: disk.specific_intensity,
+            exact_geodesics: false,
+            exposure: decimal(post.exposure),
+            tonemap: post.tonemapper,
+            bloom: decimal(post.bloom_strength),
+            bloom_threshold: decimal(post.bloom_threshold),
+        }
+    }
+
+    pub fn integration(&self) -> IntegrationSettings {
+        IntegrationSettings {
+            integrator: self.integrator,
+            max_steps: self.max_steps,
+            step_size: self.step_size as f32,
+            tolerance: self.tolerance as f32,
+            min_step: self.min_step as f32,
+            max_step: self.max_step as f32,
+            body_cutoff: self.body_cutoff as f32,
+        }
+    }
+
+    pub fn disk_settings(&self) -> DiskSettings {
+        DiskSettings {
+            model: self.disk_model,
+            scale_height: self.disk_scale_height as f32,
+            optical_depth: self.disk_optical_depth as f32,
+            doppler_beaming: self.doppler_beaming,
+            gravitational_redshift: self.gravitational_redshift,
+            specific_intensity: self.specific_intensity,
+        }
+    }
+
+    pub fn sky(&self) -> SkyLocation {
+        SkyLocation {
+            ra: self.sky_ra,
+            dec: self.sky_dec,
+        }
+    }
+
+    pub fn post(&self) -> PostSettings {
+        PostSettings {
+            exposure: self.exposure as f32,
+            tonemapper: self.tonemap,
+            bloom_strength: self.bloom as f32,
+            bloom_threshold: self.bloom_threshold as f32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BodySettings {
+    pub position: [f64; 3],
+    pub velocity: [f64; 3],
+    pub rs: f64,
+    /// Kerr spin a/M along +y.
+    pub spin: f64,
+    pub disk_inner_mult: f64,
+    pub disk_outer_mult: f64,
+    /// Normal of the disk plane; normalized on load.
+    pub disk_normal: [f64; 3],
+}
+
+impl Default for BodySettings {
+    fn default() -> Self {
+        Self::from(&Body::new(Vec3::ZERO, Vec3::ZERO, 1.0))
+    }
+}
+
+impl From<&Body> for BodySettings {
+    fn from(body: &Body) -> Self {
+        Self {
+            position: vector(body.position),
+            velocity: vector(body.velocity),
+            rs: decimal(body.rs),
+            spin: decimal(body.spin),
+            disk_inner_mult: decimal(body.disk_inner_mult),
+            disk_outer_mult: decimal(body.disk_outer_mult),
+            disk_normal: vector(body.disk_normal),
+        }
+    }
+}
+
+impl From<&BodySettings> for Body {
+    fn from(settings: &BodySettings) -> Self {
+        Body {
+            position: Vec3::from_array(settings.position.map(|x| x as f32)),
+            velocity: Vec3::from_array(settings.velocity.map(|x| x as f32)),
+            rs: settings.rs as f32,
+            spin: settings.spin as f32,
+            disk_inner_mult: settings.disk_inner_mult as f32,
+            disk_outer_mult: settings.disk_outer_mult as f32,
+            disk_normal: Vec3::from_array(settings.disk_normal.map(|x| x as f32))
+                .normalize_or(Vec3::Y),
+            trail: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// Written by a newer version of the app.
+    UnsupportedVersion(u32),
+    Invalid(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "{}", e),
+            SceneError::Parse(e) => write!(f, "invalid scene file: {}", e),
+            SceneError::Serialize(e) => write!(f, "could not serialize scene: {}", e),
+            SceneError::UnsupportedVersion(v) => write!(
+                f,
+                "scene version {} is newer than the supported version {}",
+                v, SCENE_VERSION
+            ),
+            SceneError::Invalid(reason) => write!(f, "invalid scene: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(e: io::Error) -> Self {
+        SceneError::Io(e)
+    }
+}
+
+impl Scene {
+    /// Snapshot of the running app.
+    pub fn capture(
+        simulation: &Simulation,
+        camera: &OrbitalCamera,
+        render: RenderSettings,
+    ) -> Self {
+        Self {
+            version: SCENE_VERSION,
+            simulation: SimulationSettings {
+                time: simulation.time,
+                paused: simulation.paused,
+                speed: decimal(simulation.speed),
+                pn_order: simulation.pn_order,
+            },
+            camera: CameraSettings::from(camera),
+            render,
+            bodies: simulation.bodies.iter().map(BodySettings::from).collect(),
+            timeline: Timeline::default(),
+        }
+    }
+
+    /// Replace the simulation's bodies and state with the scene's. Camera and
+    /// render settings are left to the caller.
+    pub fn restore(&self, simulation: &mut Simulation) {
+        simulation.load_bodies(self.bodies.iter().map(Body::from).collect());
+        simulation.time = self.simulation.time;
+        simulation.paused = self.simulation.paused;
+        simulation.speed = self.simulation.speed as f32;
+        simulation.pn_order = self.simulation.pn_order;
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, SceneError> {
+        let scene: Scene = toml::from_str(text).map_err(SceneError::Parse)?;
+        if scene.version > SCENE_VERSION {
+            return Err(SceneError::UnsupportedVersion(scene.version));
+        }
+        for (i, body) in scene.bodies.iter().enumerate() {
+            if body.rs.is_nan() || body.rs <= 0.0 {
+                return Err(SceneError::Invalid(format!("body {} has rs <= 0", i)));
+            }
+            if body.spin.abs() >= 1.0 {
+                return Err(SceneError::Invalid(format!("body {} has |spin| >= 1", i)));
+            }
+            let normal = body.disk_normal;
+            if normal.iter().any(|x| !x.is_finite()) || normal.iter().all(|&x| x == 0.0) {
+                return Err(SceneError::Invalid(format!(
+                    "body {} has a zero or non-finite disk normal",
+                    i
+                )));
+            }
+        }
+        scene.timeline.validate().map_err(SceneError::Invalid)?;
+        Ok(scene)
+    }
+
+    pub fn to_toml(&self) -> Result<String, SceneError> {
+        toml::to_string_pretty(self).map_err(SceneError::Serialize)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SceneError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Write the scene, creating parent directories as needed. A timeline
+    /// `from_toml` would reject is refused.
+    pub fn save(&self, path: &Path) -> Result<(), SceneError> {
+        self.timeline.validate().map_err(SceneError::Invalid)?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+}
+
+/// Widen through the shortest decimal form, so 0.1_f32 is written as 0.1
+/// rather than 0.10000000149011612.
+fn decimal(x: f32) -> f64 {
+    x.to_string().parse().unwrap_or(x as f64)
+}
+
+fn vector(v: Vec3) -> [f64; 3] {
+    v.to_array().map(decimal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::Keyframe;
+    use crate::simulation::Preset;
+
+    #[test]
+    fn captured_scene_round_trips_through_toml() {
+        let mut simulation = Simulation::new(Preset::Triple);
+        simulation.bodies[1].spin = 0.7;
+        simulation.bodies[2].disk_normal = Vec3::new(0.6, 0.8, 0.0);
+        for _ in 0..50 {
+            simulation.step(0.016);
+        }
+        let mut camera = OrbitalCamera::new(12.5, 0.3, 1.4);
+        camera.target = Vec3::new(1.0, 0.0, -2.0);
+        let render = RenderSettings {
+            grid: true,
+            background: Background::Checker,
+            ..RenderSettings::default()
+        };
+
+        let mut scene = Scene::capture(&simulation, &camera, render);
+        scene.timeline.insert(Keyframe {
+            time: 2.5,
+            sim_time: 40.0,
+            camera: scene.camera,
+        });
+        let text = scene.to_toml().unwrap();
+        assert!(text.contains("step_size = 0.1\n"), "{text}");
+        assert!(text.contains("[[keyframe]]\ntime = 2.5\n"), "{text}");
+        let loaded = Scene::from_toml(&text).unwrap();
+        assert_eq!(loaded, scene);
+
+        let mut restored = Simulation::new(Preset::Single);
+        loaded.restore(&mut restored);
+        assert_eq!(restored.preset, None);
+        assert_eq!(restored.time, simulation.time);
+        for (a, b) in restored.bodies.iter().zip(&simulation.bodies) {
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.velocity, b.velocity);
+            assert_eq!(a.spin, b.spin);
+            assert!(a.disk_normal.abs_diff_eq(b.disk_normal, 1e-6));
+        }
+        let mut reloaded_camera = OrbitalCamera::new(1.0, 0.0, 0.0);
+        loaded.camera.apply(&mut reloaded_camera);
+        assert_eq!(reloaded_camera.position(), camera.position());
+    }
+
+    #[test]
+    fn minimal_scene_uses_defaults_and_newer_versions_are_rejected() {
+        let scene = Scene::from_toml("version = 1\n[[body]]\nrs = 2.0\n").unwrap();
+        assert_eq!(scene.bodies.len(), 1);
+        assert_eq!(scene.bodies[0].disk_outer_mult, 15.0);
+        assert_eq!(scene.bodies[0].disk_normal, [0.0, 1.0, 0.0]);
+        assert_eq!(scene.camera, CameraSettings::default());
+        assert_eq!(scene.render, RenderSettings::default());
+
+        assert!(matches!(
+            Scene::from_toml("version = 2"),
+            Err(SceneError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            Scene::from_toml("version = 1\n[[body]]\nrs = 0.0\n"),
+            Err(SceneError::Invalid(_))
+        ));
+        assert!(matches!(
+            Scene::from_toml("version = 1\n[[body]]\ndisk_normal = [0.0, 0.0, 0.0]\n"),
+            Err(SceneError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn scene_with_clashing_keyframes_is_not_saved() {
+        let mut scene = Scene::capture(
+            &Simulation::new(Preset::Single),
+            &OrbitalCamera::new(10.0, 0.0, 1.0),
+            RenderSettings::default(),
+        );
+        for time in [0.0, 1.0] {
+            scene.timeline.insert(Keyframe {
+                time,
+                sim_time: 0.0,
+                camera: scene.camera,
+            });
+        }
+        let mut clash = scene.timeline.keyframes()[1];
+        clash.time = 0.0;
+        scene.timeline.update(1, clash);
+
+        let path = std::env::temp_dir().join("clashing_keyframes/scene.toml");
+        assert!(matches!(scene.save(&path), Err(SceneError::Invalid(_))));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn bundled_scenes_load() {
+        let scene = Scene::from_toml(include_str!("../scenes/binary_inspiral.toml")).unwrap();
+        assert_eq!(scene.bodies.len(), 2);
+        assert_eq!(scene.simulation.pn_order, PnOrder::TwoAndHalfPn);
+        assert_eq!(scene.timeline.duration(), 8.0);
+
+        let cluster = Scene::from_toml(include_str!("../scenes/cluster.toml")).unwrap();
+        assert_eq!(cluster.bodies.len(), 40);
+        assert_eq!(cluster.render.body_cutoff, 40.0);
+    }
+}
@@ -1,44 +1,155 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use glam::Vec3;
+
+use crate::gravitational_waves::{self, Observer};
+use crate::inspector::Inspection;
+use crate::metrics::schwarzschild::SchwarzschildParams;
+use crate::metrics::Metric;
+use crate::post_newtonian::PnOrder;
+use crate::remnant::C_KM_S;
 use crate::renderer::camera::OrbitalCamera;
-use crate::renderer::pipeline::RayMarchPipeline;
-use crate::renderer::uniforms::Uniforms;
-use crate::simulation::{Preset, Simulation};
+use crate::renderer::environment::EnvironmentMap;
+use crate::renderer::observer::{self, Motion, ObserverFrame, STOP_RADIUS};
+use crate::renderer::pipeline::{ImageFormat, RayMarchPipeline};
+use crate::renderer::post::PostSettings;
+use crate::renderer::reference::ReferenceTracer;
+use crate::renderer::stars::{SkyLocation, StarBins, StarCatalog};
+use crate::renderer::tonemap::Tonemapper;
+use crate::renderer::uniforms::{
+    sample_jitter, DiskModel, DiskSettings, IntegrationSettings, Integrator, RenderMode, Uniforms,
+};
+use crate::scene::Scene;
+use crate::simulation::{GpuBody, Preset, Simulation};
 
 pub struct ScreenshotConfig {
     pub preset: Preset,
+    /// Bodies and simulation state from a scene file, replacing `preset`.
+    pub scene: Option<Scene>,
     pub width: u32,
     pub height: u32,
     pub camera_distance: f32,
     pub camera_azimuth: f32,
     pub camera_elevation: f32,
     pub camera_fov: f32,
-    pub max_steps: u32,
-    pub step_size: f32,
+    pub camera_target: Vec3,
+    pub integration: IntegrationSettings,
     pub background_mode: u32,
+    /// Equirectangular image or cubemap directory for background mode 2.
+    pub background_image: Option<PathBuf>,
+    /// Star catalogue CSV for background mode 3.
+    pub star_catalog: Option<PathBuf>,
+    /// Where the black hole sits on the catalogue's sky.
+    pub sky: SkyLocation,
+    pub disk_enabled: bool,
+    pub disk: DiskSettings,
+    pub grid_enabled: bool,
+    /// Shaded image or a diagnostic view.
+    pub render_mode: RenderMode,
     pub output: PathBuf,
+    /// Tonemapped PNG or linear HDR EXR.
+    pub format: ImageFormat,
+    /// Static, free-falling or orbiting camera around body 0, started from
+    /// the camera position.
+    pub camera_observer: observer::Observer,
+    /// Exposure, tonemapping and bloom for PNGs.
+    pub post: PostSettings,
+    /// Jittered samples averaged per pixel.
+    pub samples: u32,
     pub sim_time: f32,
+    /// Overrides every body's spin.
+    pub spin: Option<f32>,
+    /// Overrides the preset's post-Newtonian order.
+    pub pn_order: Option<PnOrder>,
+    /// Gravitational-wave observer for the strain exports.
+    pub observer: Observer,
+    /// Write the strain recorded over `sim_time` as CSV here.
+    pub strain_csv: Option<PathBuf>,
+    /// Write the strain recorded over `sim_time` as a chirp WAV here.
+    pub strain_wav: Option<PathBuf>,
+    pub exact_geodesics: bool,
+    /// Render with the f64 CPU reference tracer instead of the GPU.
+    pub cpu_reference: bool,
+    /// Print the reference trace of this pixel instead of saving an image.
+    pub trace_pixel: Option<(u32, u32)>,
+    /// Render the scene's camera keyframes as an image sequence in this directory.
+    pub frames: Option<PathBuf>,
+    /// Frame rate of the sequence.
+    pub fps: f64,
 }
 
 impl Default for ScreenshotConfig {
     fn default() -> Self {
         Self {
             preset: Preset::Single,
+            scene: None,
             width: 1920,
             height: 1080,
             camera_distance: 10.0,
             camera_azimuth: 0.5,
             camera_elevation: 1.2,
             camera_fov: 1.0,
-            max_steps: 600,
-            step_size: 0.1,
+            camera_target: Vec3::ZERO,
+            integration: IntegrationSettings::default(),
             background_mode: 1,
+            background_image: None,
+            star_catalog: None,
+            sky: SkyLocation::default(),
+            disk_enabled: true,
+            disk: DiskSettings::default(),
+            grid_enabled: false,
+            render_mode: RenderMode::Shaded,
             output: PathBuf::from("screenshot.png"),
+            format: ImageFormat::Png,
+            camera_observer: observer::Observer::default(),
+            post: PostSettings::default(),
+            samples: 1,
             sim_time: 0.0,
+            spin: None,
+            pn_order: None,
+            observer: Observer::default(),
+            strain_csv: None,
+            strain_wav: None,
+            exact_geodesics: false,
+            cpu_reference: false,
+            trace_pixel: None,
+            frames: None,
+            fps: 30.0,
         }
     }
 }
 
+/// Output path and image format from `--output` and `--format`. An output
+/// extension decides the format and must agree with `--format`; without
+/// one the path gets the format's extension.
+fn output_format(
+    output: Option<PathBuf>,
+    format: Option<ImageFormat>,
+) -> Result<(PathBuf, ImageFormat), String> {
+    let mut path = output.unwrap_or_else(|| PathBuf::from("screenshot"));
+    let Some(extension) = path.extension() else {
+        let format = format.unwrap_or_default();
+        path.set_extension(format.extension());
+        return Ok((path, format));
+    };
+    let extension = extension.to_string_lossy();
+    let Some(implied) = ImageFormat::from_extension(&extension) else {
+        return Err(format!(
+            "Unsupported output extension '.{}'. Options: .png, .exr",
+            extension
+        ));
+    };
+    match format {
+        Some(format) if format != implied => Err(format!(
+            "--output {} does not match --format {}",
+            path.display(),
+            format.extension()
+        )),
+        _ => Ok((path, implied)),
+    }
+}
+
 pub fn parse_args() -> Option<ScreenshotConfig> {
     let args: Vec<String> = std::env::args().collect();
     if !args.iter().any(|a| a == "--screenshot") {
@@ -53,13 +164,46 @@ pub fn parse_args() -> Option<ScreenshotConfig> {
             .and_then(|i| args.get(i + 1).cloned())
     };
 
+    // Scene settings come first so that explicit flags override them.
+    if let Some(path) = get_val("--scene") {
+        if get_val("--preset").is_some() {
+            eprintln!("--scene and --preset cannot be combined");
+            std::process::exit(1);
+        }
+        let scene = Scene::load(std::path::Path::new(&path)).unwrap_or_else(|e| {
+            eprintln!("Failed to load scene {}: {}", path, e);
+            std::process::exit(1);
+        });
+        let camera = &scene.camera;
+        config.camera_distance = camera.distance as f32;
+        config.camera_azimuth = camera.azimuth as f32;
+        config.camera_elevation = camera.elevation as f32;
+        config.camera_fov = camera.fov as f32;
+        config.camera_target = Vec3::from_array(camera.target.map(|x| x as f32));
+        config.integration = scene.render.integration();
+        config.background_mode = scene.render.background.mode();
+        config.background_image = scene.render.background_image.clone();
+        config.star_catalog = scene.render.star_catalog.clone();
+        config.sky = scene.render.sky();
+        config.disk_enabled = scene.render.disk;
+        config.disk = scene.render.disk_settings();
+        config.grid_enabled = scene.render.grid;
+        config.exact_geodesics = scene.render.exact_geodesics;
+        config.post = scene.render.post();
+        config.scene = Some(scene);
+    }
+
     if let Some(p) = get_val("--preset") {
         config.preset = match p.as_str() {
             "single" => Preset::Single,
             "binary" => Preset::Binary,
             "triple" => Preset::Triple,
+            "misaligned" => Preset::Misaligned,
             _ => {
-                eprintln!("Unknown preset '{}'. Options: single, binary, triple", p);
+                eprintln!(
+                    "Unknown preset '{}'. Options: single, binary, triple, misaligned",
+                    p
+                );
                 std::process::exit(1);
             }
         };
@@ -83,124 +227,346 @@ pub fn parse_args() -> Option<ScreenshotConfig> {
     if let Some(v) = get_val("--camera-fov") {
         config.camera_fov = v.parse().expect("Invalid --camera-fov");
     }
+    if let Some(v) = get_val("--camera-motion") {
+        config.camera_observer.motion = match Motion::ALL.into_iter().find(|m| m.flag() == v) {
+            Some(motion) => motion,
+            None => {
+                let options: Vec<_> = Motion::ALL.iter().map(|m| m.flag()).collect();
+                eprintln!(
+                    "Unknown camera motion '{}'. Options: {}",
+                    v,
+                    options.join(", ")
+                );
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = get_val("--motion-radius") {
+        config.camera_observer.radius = v.parse().expect("Invalid --motion-radius");
+        // Moving cameras only follow non-rotating bodies
+        let min_radius = match config.camera_observer.motion {
+            Motion::Orbit => SchwarzschildParams { rs: 1.0 }.photon_sphere_radius(),
+            _ => STOP_RADIUS,
+        };
+        if config.camera_observer.radius <= min_radius {
+            eprintln!("--motion-radius must be above {} rs", min_radius);
+            std::process::exit(1);
+        }
+    }
+    if let Some(v) = get_val("--proper-time") {
+        config.camera_observer.proper_time = v.parse().expect("Invalid --proper-time");
+    }
+    if let Some(v) = get_val("--proper-time-scale") {
+        config.camera_observer.time_scale = v.parse().expect("Invalid --proper-time-scale");
+    }
+    if let Some(v) = get_val("--integrator") {
+        config.integration.integrator = match v.as_str() {
+            "rk4" => Integrator::FixedRk4,
+            "rk45" => Integrator::AdaptiveRk45,
+            _ => {
+                eprintln!("Unknown integrator '{}'. Options: rk4, rk45", v);
+                std::process::exit(1);
+            }
+        };
+    }
     if let Some(v) = get_val("--max-steps") {
-        config.max_steps = v.parse().expect("Invalid --max-steps");
+        config.integration.max_steps = v.parse().expect("Invalid --max-steps");
     }
     if let Some(v) = get_val("--step-size") {
-        config.step_size = v.parse().expect("Invalid --step-size");
+        config.integration.step_size = v.parse().expect("Invalid --step-size");
+    }
+    if let Some(v) = get_val("--tolerance") {
+        config.integration.tolerance = v.parse().expect("Invalid --tolerance");
+    }
+    if let Some(v) = get_val("--min-step") {
+        config.integration.min_step = v.parse().expect("Invalid --min-step");
+    }
+    if let Some(v) = get_val("--max-step") {
+        config.integration.max_step = v.parse().expect("Invalid --max-step");
+    }
+    if let Some(v) = get_val("--body-cutoff") {
+        config.integration.body_cutoff = v.parse().expect("Invalid --body-cutoff");
+    }
+    if config.integration.min_step > config.integration.max_step {
+        eprintln!("--min-step must not exceed --max-step");
+        std::process::exit(1);
     }
     if let Some(v) = get_val("--background") {
         config.background_mode = match v.as_str() {
             "checker" => 0,
             "stars" => 1,
+            "image" => 2,
+            "catalog" => 3,
             _ => v.parse().expect("Invalid --background"),
         };
     }
-    if let Some(v) = get_val("--output") {
-        config.output = PathBuf::from(v);
+    if let Some(v) = get_val("--background-image") {
+        config.background_image = Some(PathBuf::from(v));
+        config.background_mode = 2;
+    }
+    if config.background_mode == 2 && config.background_image.is_none() {
+        eprintln!("--background image needs --background-image <path>");
+        std::process::exit(1);
+    }
+    if let Some(v) = get_val("--star-catalog") {
+        config.star_catalog = Some(PathBuf::from(v));
+        config.background_mode = 3;
+    }
+    if config.background_mode == 3 && config.star_catalog.is_none() {
+        eprintln!("--background catalog needs --star-catalog <path>");
+        std::process::exit(1);
+    }
+    if let Some(v) = get_val("--sky-ra") {
+        config.sky.ra = v.parse().expect("Invalid --sky-ra");
+    }
+    if let Some(v) = get_val("--sky-dec") {
+        config.sky.dec = v.parse().expect("Invalid --sky-dec");
+        if config.sky.dec.abs() > 90.0 {
+            eprintln!("--sky-dec must be between -90 and 90");
+            std::process::exit(1);
+        }
+    }
+    if let Some(v) = get_val("--disk-model") {
+        config.disk.model = match v.as_str() {
+            "thin" => DiskModel::Thin,
+            "volumetric" => DiskModel::Volumetric,
+            _ => {
+                eprintln!("Unknown disk model '{}'. Options: thin, volumetric", v);
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = get_val("--disk-scale-height") {
+        config.disk.scale_height = v.parse().expect("Invalid --disk-scale-height");
+        if config.disk.scale_height <= 0.0 {
+            eprintln!("--disk-scale-height must be positive");
+            std::process::exit(1);
+        }
+    }
+    if let Some(v) = get_val("--disk-optical-depth") {
+        config.disk.optical_depth = v.parse().expect("Invalid --disk-optical-depth");
     }
+    config.disk.doppler_beaming &= !args.iter().any(|a| a == "--no-beaming");
+    config.disk.gravitational_redshift &= !args.iter().any(|a| a == "--no-redshift");
+    config.disk.specific_intensity |= args.iter().any(|a| a == "--specific-intensity");
+    if let Some(v) = get_val("--render-mode") {
+        config.render_mode = match RenderMode::ALL.into_iter().find(|m| m.flag() == v) {
+            Some(mode) => mode,
+            None => {
+                let options: Vec<_> = RenderMode::ALL.iter().map(|m| m.flag()).collect();
+                eprintln!(
+                    "Unknown render mode '{}'. Options: {}",
+                    v,
+                    options.join(", ")
+                );
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = get_val("--exposure") {
+        config.post.exposure = v.parse().expect("Invalid --exposure");
+    }
+    if let Some(v) = get_val("--tonemap") {
+        config.post.tonemapper = match v.as_str() {
+            "aces" => Tonemapper::Aces,
+            "agx" => Tonemapper::Agx,
+            "reinhard" => Tonemapper::Reinhard,
+            "none" => Tonemapper::None,
+            _ => {
+                eprintln!(
+                    "Unknown tonemapper '{}'. Options: aces, agx, reinhard, none",
+                    v
+                );
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = get_val("--bloom") {
+        config.post.bloom_strength = v.parse().expect("Invalid --bloom");
+    }
+    if let Some(v) = get_val("--bloom-threshold") {
+        config.post.bloom_threshold = v.parse().expect("Invalid --bloom-threshold");
+    }
+    if let Some(v) = get_val("--spp") {
+        config.samples = v.parse().expect("Invalid --spp");
+        if config.samples == 0 {
+            eprintln!("--spp must be at least 1");
+            std::process::exit(1);
+        }
+    }
+    let format = get_val("--format").map(|v| {
+        ImageFormat::from_extension(&v).unwrap_or_else(|| {
+            eprintln!("Unknown format '{}'. Options: png, exr", v);
+            std::process::exit(1);
+        })
+    });
+    let output = get_val("--output").map(PathBuf::from);
+    (config.output, config.format) = output_format(output, format).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
     if let Some(v) = get_val("--sim-time") {
         config.sim_time = v.parse().expect("Invalid --sim-time");
     }
+    if let Some(v) = get_val("--spin") {
+        let spin: f32 = v.parse().expect("Invalid --spin");
+        config.spin = Some(spin);
+        if spin.abs() >= 1.0 {
+            eprintln!("--spin must lie strictly between -1 and 1");
+            std::process::exit(1);
+        }
+    }
 
-    Some(config)
-}
-
-pub fn render_screenshot(config: &ScreenshotConfig) {
-    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-        backends: wgpu::Backends::all(),
-        ..Default::default()
-    });
-
-    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::HighPerformance,
-        compatible_surface: None,
-        force_fallback_adapter: false,
-    }))
-    .expect("Failed to find a suitable GPU adapter");
+    if let Some(v) = get_val("--pn-order") {
+        config.pn_order = Some(match v.as_str() {
+            "newtonian" | "0" => PnOrder::Newtonian,
+            "1pn" | "1" => PnOrder::OnePn,
+            "2pn" | "2" => PnOrder::TwoPn,
+            "2.5pn" | "2.5" => PnOrder::TwoAndHalfPn,
+            _ => {
+                eprintln!(
+                    "Unknown PN order '{}'. Options: newtonian, 1pn, 2pn, 2.5pn",
+                    v
+                );
+                std::process::exit(1);
+            }
+        });
+    }
 
-    log::info!("Using adapter: {:?}", adapter.get_info());
+    if let Some(v) = get_val("--observer-inclination") {
+        config.observer.inclination = v.parse().expect("Invalid --observer-inclination");
+    }
+    if let Some(v) = get_val("--observer-azimuth") {
+        config.observer.azimuth = v.parse().expect("Invalid --observer-azimuth");
+    }
+    config.strain_csv = get_val("--strain-csv").map(PathBuf::from);
+    config.strain_wav = get_val("--strain-wav").map(PathBuf::from);
 
-    let (device, queue) = pollster::block_on(adapter.request_device(
-        &wgpu::DeviceDescriptor {
-            label: Some("Screenshot Device"),
-            required_features: wgpu::Features::empty(),
-            required_limits: wgpu::Limits::default(),
-            memory_hints: Default::default(),
-        },
-        None,
-    ))
-    .expect("Failed to create device");
+    config.exact_geodesics |= args.iter().any(|a| a == "--exact-geodesics");
+    config.cpu_reference = args.iter().any(|a| a == "--cpu-reference");
+    if let Some(v) = get_val("--trace-pixel") {
+        let parsed = v
+            .split_once(',')
+            .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)));
+        config.trace_pixel = Some(parsed.expect("Invalid --trace-pixel, expected <x>,<y>"));
+    }
+    config.frames = get_val("--frames").map(PathBuf::from);
+    if let Some(v) = get_val("--fps") {
+        config.fps = v.parse().expect("Invalid --fps");
+        if config.fps <= 0.0 {
+            eprintln!("--fps must be positive");
+            std::process::exit(1);
+        }
+    }
 
-    // Use a non-sRGB format for headless since there's no surface
-    let surface_format = wgpu::TextureFormat::Bgra8Unorm;
+    Some(config)
+}
 
-    let pipeline = RayMarchPipeline::new(&device, surface_format, config.width, config.height);
+pub fn render_screenshot(config: &ScreenshotConfig) {
+    if let Some(dir) = &config.frames {
+        render_sequence(config, dir);
+        return;
+    }
 
     // Set up camera
-    let camera = OrbitalCamera::new(config.camera_distance, config.camera_azimuth, config.camera_elevation);
+    let mut camera = OrbitalCamera::new(
+        config.camera_distance,
+        config.camera_azimuth,
+        config.camera_elevation,
+    );
+    camera.target = config.camera_target;
+    camera.fov = config.camera_fov;
 
     // Set up simulation and advance to desired time
-    let mut simulation = Simulation::new(config.preset);
+    let mut simulation = setup_simulation(config);
     if config.sim_time > 0.0 {
-        simulation.paused = false;
-        let steps = (config.sim_time / 0.016).ceil() as u32;
-        let dt = config.sim_time / steps as f32;
-        for _ in 0..steps {
-            simulation.step(dt);
+        advance(&mut simulation, config.sim_time);
+        for event in &simulation.mergers {
+            println!(
+                "Merger at t = {:.1}: M = {:.3}, a/M = {:.2}, kick {:.0} km/s",
+                event.time,
+                event.remnant_mass,
+                event.remnant_spin,
+                event.kick as f64 * C_KM_S
+            );
         }
     }
+    export_strain(config, &simulation);
 
     let gpu_bodies = simulation.gpu_bodies();
-    pipeline.update_bodies(&queue, &gpu_bodies);
-
-    let uniforms = Uniforms {
-        camera_pos: [
-            camera.position().x,
-            camera.position().y,
-            camera.position().z,
-            0.0,
-        ],
-        camera_forward: [
-            camera.forward().x,
-            camera.forward().y,
-            camera.forward().z,
-            0.0,
-        ],
-        camera_up: [
-            camera.up().x,
-            camera.up().y,
-            camera.up().z,
-            0.0,
-        ],
-        camera_right: [
-            camera.right().x,
-            camera.right().y,
-            camera.right().z,
-            0.0,
-        ],
-        resolution: [config.width as f32, config.height as f32],
-        fov: config.camera_fov,
-        num_bodies: simulation.bodies.len() as u32,
-        max_steps: config.max_steps,
-        step_size: config.step_size,
-        disk_enabled: 1,
-        background_mode: config.background_mode,
-        time: config.sim_time,
-        grid_enabled: 0,
-        _padding: [0.0; 2],
-    };
-    pipeline.update_uniforms(&queue, &uniforms);
+    let environment = load_environment(config);
+    let stars = load_stars(config);
+    // The disk animates with the simulation clock, so a --scene renders as
+    // it looked at the time it was saved, plus any --sim-time.
+    let uniforms = frame_uniforms(
+        config,
+        &camera,
+        &simulation,
+        environment.as_deref(),
+        simulation.time as f32,
+        config.camera_observer.proper_time,
+    );
 
-    // Dispatch compute
-    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-        label: Some("Screenshot Compute Encoder"),
-    });
-    pipeline.dispatch_compute(&mut encoder);
-    queue.submit(std::iter::once(encoder.finish()));
+    let tracer = ReferenceTracer::new(&uniforms, &gpu_bodies)
+        .with_environment(environment.clone())
+        .with_stars(stars.clone());
+    if let Some((x, y)) = config.trace_pixel {
+        let trace = tracer.trace_pixel(x, y, config.width, config.height);
+        println!("Pixel ({}, {}): {:?}", x, y, trace.fate);
+        println!(
+            "  steps: {} ({} rejected)",
+            trace.steps, trace.rejected_steps
+        );
+        println!(
+            "  color: {:.4} {:.4} {:.4}",
+            trace.color.x, trace.color.y, trace.color.z
+        );
+        println!("  final position: {:.4}", trace.final_position);
+        println!("  final direction: {:.4}", trace.final_direction);
+        for (body, p) in &trace.disk_crossings {
+            println!("  disk plane crossing of body {}: {:.4}", body, p);
+        }
+        if let Some(hit) = trace.disk_hit {
+            println!(
+                "  first disk hit: body {} at r = {:.4}, g = {:.4}",
+                hit.body, hit.radius, hit.redshift
+            );
+        }
+        println!("  disk transmittance: {:.4}", trace.transmittance);
+        let inspection = Inspection::trace(
+            &uniforms,
+            &gpu_bodies,
+            (x, y),
+            (config.width, config.height),
+        );
+        for (i, pass) in inspection.passes.iter().enumerate() {
+            println!(
+                "  body {}: impact parameter {:.4}, closest approach {:.4}, {:.2} orbits",
+                i, pass.impact_parameter, pass.closest_approach, pass.orbits
+            );
+        }
+        return;
+    }
 
-    // Capture and save
-    match pipeline.capture_screenshot_to(&device, &queue, &config.output) {
+    let saved = if config.cpu_reference {
+        let texels = tracer.render_accumulated(config.width, config.height, config.samples);
+        config.format.save(
+            &texels,
+            config.width,
+            config.height,
+            &config.post.for_mode(config.render_mode),
+            &config.output,
+        )
+    } else {
+        HeadlessRenderer::new(config, environment.as_deref(), stars.as_deref()).render(
+            &gpu_bodies,
+            &uniforms,
+            &config.output,
+            config.format,
+        )
+    };
+
+    match saved {
         Some(path) => {
             println!("Screenshot saved to {}", path.display());
         }
@@ -210,3 +576,356 @@ pub fn render_screenshot(config: &ScreenshotConfig) {
         }
     }
 }
+
+/// Render the scene's camera keyframes into `dir` as numbered images
+/// (`frame_00000.png` or `.exr` onwards), stepping the simulation forward to
+/// each frame's simulation time.
+fn render_sequence(config: &ScreenshotConfig, dir: &Path) {
+    let timeline = match &config.scene {
+        Some(scene) if !scene.timeline.is_empty() => &scene.timeline,
+        _ => {
+            eprintln!("--frames needs a --scene with [[keyframe]] entries");
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        eprintln!("Failed to create {}: {}", dir.display(), e);
+        std::process::exit(1);
+    }
+
+    let mut simulation = setup_simulation(config);
+    if timeline.keyframes()[0].sim_time < simulation.time {
+        eprintln!(
+            "Keyframes before the scene time t = {:.1} show the scene as saved",
+            simulation.time
+        );
+    }
+    let mut camera = OrbitalCamera::new(10.0, 0.0, 0.0);
+    let environment = load_environment(config);
+    let stars = load_stars(config);
+    let mut renderer = (!config.cpu_reference)
+        .then(|| HeadlessRenderer::new(config, environment.as_deref(), stars.as_deref()));
+
+    let frames = timeline.frame_count(config.fps);
+    for frame in 0..frames {
+        let time = frame as f64 / config.fps;
+        let Some(key) = timeline.sample(time) else {
+            break;
+        };
+        let span = key.sim_time - simulation.time;
+        advance(&mut simulation, span as f32);
+        key.camera.apply(&mut camera);
+
+        let gpu_bodies = simulation.gpu_bodies();
+        // The disk animates with the animation clock, as in the app, and a
+        // moving camera sets off again from each keyframe's position.
+        let proper_time =
+            config.camera_observer.proper_time + time * config.camera_observer.time_scale;
+        let uniforms = frame_uniforms(
+            config,
+            &camera,
+            &simulation,
+            environment.as_deref(),
+            time as f32,
+            proper_time,
+        );
+        let path = dir.join(format!("frame_{:05}.{}", frame, config.format.extension()));
+        let saved = match &mut renderer {
+            Some(renderer) => renderer.render(&gpu_bodies, &uniforms, &path, config.format),
+            None => {
+                let tracer = ReferenceTracer::new(&uniforms, &gpu_bodies)
+                    .with_environment(environment.clone())
+                    .with_stars(stars.clone());
+                let texels = tracer.render_accumulated(config.width, config.height, config.samples);
+                let post = config.post.for_mode(config.render_mode);
+                config
+                    .format
+                    .save(&texels, config.width, config.height, &post, &path)
+            }
+        };
+        if saved.is_none() {
+            eprintln!("Failed to render frame {}", frame);
+            std::process::exit(1);
+        }
+        println!("Frame {}/{} saved to {}", frame + 1, frames, path.display());
+    }
+    export_strain(config, &simulation);
+}
+
+/// The background image, if the background shows one.
+fn load_environment(config: &ScreenshotConfig) -> Option<Arc<EnvironmentMap>> {
+    if config.background_mode != 2 {
+        return None;
+    }
+    let path = config.background_image.as_ref()?;
+    match EnvironmentMap::load(path) {
+        Ok(map) => Some(Arc::new(map)),
+        Err(e) => {
+            eprintln!("Failed to load background image {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The catalogue stars binned for the sky location, if the background shows
+/// them.
+fn load_stars(config: &ScreenshotConfig) -> Option<Arc<StarBins>> {
+    if config.background_mode != 3 {
+        return None;
+    }
+    let path = config.star_catalog.as_ref()?;
+    match StarCatalog::load(path) {
+        Ok(catalog) => Some(Arc::new(catalog.bin(config.sky))),
+        Err(e) => {
+            eprintln!("Failed to load star catalogue {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Preset or scene bodies with the command-line overrides applied.
+fn setup_simulation(config: &ScreenshotConfig) -> Simulation {
+    let mut simulation = Simulation::new(config.preset);
+    if let Some(scene) = &config.scene {
+        scene.restore(&mut simulation);
+    }
+    if let Some(spin) = config.spin {
+        for body in &mut simulation.bodies {
+            body.spin = spin;
+        }
+    }
+    if let Some(order) = config.pn_order {
+        simulation.pn_order = order;
+    }
+    simulation.strain.observer = config.observer;
+    simulation
+        .strain
+        .set_recording(config.strain_csv.is_some() || config.strain_wav.is_some());
+    if config.exact_geodesics && !simulation.supports_exact_geodesics() {
+        eprintln!(
+            "--exact-geodesics needs a single non-rotating black hole; using the default model"
+        );
+    }
+    let motion = config.camera_observer.motion;
+    if let Some(body) = simulation.bodies.first() {
+        if !motion.supports_spin(body.spin) {
+            eprintln!(
+                "--camera-motion {} needs a non-rotating body 0; rendering from rest",
+                motion.flag()
+            );
+        }
+    }
+    simulation
+}
+
+/// Step the simulation `span` seconds forward in steps of at most 0.016.
+/// Spans count simulation seconds, whatever the scene's speed.
+fn advance(simulation: &mut Simulation, span: f32) {
+    if span <= 0.0 {
+        return;
+    }
+    simulation.paused = false;
+    simulation.speed = 1.0;
+    let steps = (span / 0.016).ceil() as u32;
+    let dt = span / steps as f32;
+    for _ in 0..steps {
+        simulation.step(dt);
+    }
+}
+
+/// Uniforms for `camera`, moving as `config.camera_observer` does after
+/// `proper_time` from where `camera` is, with `environment` as the image
+/// background.
+fn frame_uniforms(
+    config: &ScreenshotConfig,
+    camera: &OrbitalCamera,
+    simulation: &Simulation,
+    environment: Option<&EnvironmentMap>,
+    time: f32,
+    proper_time: f64,
+) -> Uniforms {
+    let frame = match simulation.bodies.first() {
+        Some(body) if config.camera_observer.motion.supports_spin(body.spin) => {
+            let mut observer = config.camera_observer.clone();
+            observer.restart(camera, body.position);
+            observer.proper_time = proper_time;
+            observer.frame(camera, body.position, body.rs)
+        }
+        _ => ObserverFrame::at_rest(camera),
+    };
+    let to4 = |v: Vec3| [v.x, v.y, v.z, 0.0];
+    Uniforms {
+        camera_pos: to4(frame.position),
+        camera_forward: to4(frame.forward),
+        camera_up: to4(frame.up),
+        camera_right: to4(frame.right),
+        observer_velocity: to4(frame.velocity),
+        resolution: [config.width as f32, config.height as f32],
+        fov: camera.fov,
+        num_bodies: simulation.bodies.len() as u32,
+        max_steps: config.integration.max_steps,
+        step_size: config.integration.step_size,
+        disk_enabled: config.disk_enabled as u32,
+        background_mode: config.background_mode,
+        environment_projection: environment.map_or(0, |map| map.projection as u32),
+        time,
+        grid_enabled: config.grid_enabled as u32,
+        geodesic_model: simulation.geodesic_model(config.exact_geodesics) as u32,
+        integrator: config.integration.integrator as u32,
+        tolerance: config.integration.tolerance,
+        min_step: config.integration.min_step,
+        max_step: config.integration.max_step,
+        body_cutoff: config.integration.body_cutoff,
+        disk_model: config.disk.model as u32,
+        disk_scale_height: config.disk.scale_height,
+        disk_optical_depth: config.disk.optical_depth,
+        doppler_beaming: config.disk.doppler_beaming as u32,
+        gravitational_redshift: config.disk.gravitational_redshift as u32,
+        intensity_exponent: config.disk.intensity_exponent(),
+        render_mode: config.render_mode as u32,
+        ..Default::default()
+    }
+}
+
+fn export_strain(config: &ScreenshotConfig, simulation: &Simulation) {
+    let strain = &simulation.strain;
+    if let Some(path) = &config.strain_csv {
+        match strain.write_csv(path) {
+            Ok(()) => println!("Strain CSV saved to {}", path.display()),
+            Err(e) => {
+                eprintln!("Failed to write {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(path) = &config.strain_wav {
+        let written = strain.write_wav(
+            path,
+            gravitational_waves::WAV_DURATION,
+            gravitational_waves::WAV_PEAK_FREQUENCY,
+        );
+        match written {
+            Ok(()) => println!("Strain WAV saved to {}", path.display()),
+            Err(e) => {
+                eprintln!("Failed to write {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Offscreen device and ray-march pipeline, reused across frames.
+struct HeadlessRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: RayMarchPipeline,
+    /// Jittered samples averaged per image.
+    samples: u32,
+}
+
+impl HeadlessRenderer {
+    fn new(
+        config: &ScreenshotConfig,
+        environment: Option<&EnvironmentMap>,
+        stars: Option<&StarBins>,
+    ) -> Self {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        }))
+        .expect("Failed to find a suitable GPU adapter");
+
+        log::info!("Using adapter: {:?}", adapter.get_info());
+
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("Screenshot Device"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::default(),
+                memory_hints: Default::default(),
+            },
+            None,
+        ))
+        .expect("Failed to create device");
+
+        // Use a non-sRGB format for headless since there's no surface
+        let surface_format = wgpu::TextureFormat::Bgra8Unorm;
+
+        let mut pipeline =
+            RayMarchPipeline::new(&device, surface_format, config.width, config.height);
+        pipeline.update_post(&queue, &config.post.for_mode(config.render_mode));
+        pipeline.set_environment(&device, &queue, environment);
+        if let Some(stars) = stars {
+            pipeline.set_stars(&device, stars);
+        }
+        Self {
+            device,
+            queue,
+            pipeline,
+            samples: config.samples,
+        }
+    }
+
+    fn render(
+        &mut self,
+        gpu_bodies: &[GpuBody],
+        uniforms: &Uniforms,
+        path: &Path,
+        format: ImageFormat,
+    ) -> Option<PathBuf> {
+        self.pipeline
+            .update_bodies(&self.device, &self.queue, gpu_bodies);
+
+        // Dispatch compute once per jittered sample
+        for i in 0..self.samples {
+            let sample = Uniforms {
+                jitter: sample_jitter(i),
+                sample_index: i,
+                ..*uniforms
+            };
+            self.pipeline.update_uniforms(&self.queue, &sample);
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Screenshot Compute Encoder"),
+                });
+            self.pipeline.dispatch_compute(&mut encoder);
+            self.queue.submit(std::iter::once(encoder.finish()));
+        }
+
+        self.pipeline
+            .capture_screenshot_to(&self.device, &self.queue, path, format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_extension_decides_the_format() {
+        let resolve =
+            |output: Option<&str>, format| output_format(output.map(PathBuf::from), format);
+        let exr = Some(ImageFormat::Exr);
+        assert_eq!(
+            resolve(Some("disk.exr"), None),
+            Ok((PathBuf::from("disk.exr"), ImageFormat::Exr))
+        );
+        assert_eq!(
+            resolve(None, exr),
+            Ok((PathBuf::from("screenshot.exr"), ImageFormat::Exr))
+        );
+        assert_eq!(
+            resolve(Some("shots/disk"), exr),
+            Ok((PathBuf::from("shots/disk.exr"), ImageFormat::Exr))
+        );
+        assert!(resolve(Some("disk.png"), exr).is_err());
+        assert!(resolve(Some("disk.jpg"), None).is_err());
+    }
+}
@@ -1,26 +1,82 @@
+use std::collections::VecDeque;
+
 use bytemuck::{Pod, Zeroable};
-use glam::Vec3;
+use glam::{DVec3, Vec3};
 
-pub const MAX_BODIES: usize = 8;
+use crate::gravitational_waves::StrainRecorder;
+use crate::metrics::{self, GeodesicModel, Metric};
+use crate::post_newtonian::{self, PnOrder};
+use crate::remnant;
 
 #[derive(Clone)]
 pub struct Body {
     pub position: Vec3,
     pub velocity: Vec3,
     pub rs: f32,
+    /// Dimensionless Kerr spin a/M in (-1, 1). Positive spin points along +y.
+    pub spin: f32,
     pub disk_inner_mult: f32,
     pub disk_outer_mult: f32,
+    /// Unit normal of the accretion disk plane, +y for a disk in the
+    /// orbital plane of the presets. Independent of the spin axis.
+    pub disk_normal: Vec3,
+    /// Recent positions, oldest first, spaced `TRAIL_SPACING` apart.
+    pub trail: VecDeque<Vec3>,
 }
 
+/// Distance a body moves between recorded trail points.
+pub const TRAIL_SPACING: f32 = 0.05;
+
 impl Body {
     pub fn new(position: Vec3, velocity: Vec3, rs: f32) -> Self {
         Self {
             position,
             velocity,
             rs,
+            spin: 0.0,
             disk_inner_mult: 3.0,
             disk_outer_mult: 15.0,
+            disk_normal: Vec3::Y,
+            trail: VecDeque::new(),
+        }
+    }
+
+    /// Disk normal `inclination` radians away from +y, tipped towards the
+    /// direction `azimuth` radians round from +x in the xz-plane.
+    pub fn tilted_disk_normal(inclination: f32, azimuth: f32) -> Vec3 {
+        let (sin_i, cos_i) = inclination.sin_cos();
+        Vec3::new(sin_i * azimuth.cos(), cos_i, sin_i * azimuth.sin())
+    }
+
+    /// Inclination and azimuth of the disk normal, as taken by
+    /// `tilted_disk_normal`.
+    pub fn disk_tilt(&self) -> (f32, f32) {
+        let n = self.disk_normal;
+        (n.y.clamp(-1.0, 1.0).acos(), n.z.atan2(n.x))
+    }
+
+    /// Extend the trail to the current position, keeping at most `max_points`.
+    fn record_trail(&mut self, max_points: usize) {
+        let moved = self
+            .trail
+            .back()
+            .is_none_or(|last| last.distance(self.position) >= TRAIL_SPACING);
+        if moved {
+            self.trail.push_back(self.position);
         }
+        while self.trail.len() > max_points {
+            self.trail.pop_front();
+        }
+    }
+
+    /// Mass in geometric units, M = rs / 2.
+    pub fn mass(&self) -> f32 {
+        0.5 * self.rs
+    }
+
+    /// Spacetime around this body: Schwarzschild when non-rotating, Kerr otherwise.
+    pub fn metric(&self) -> Box<dyn Metric> {
+        metrics::black_hole(self.rs as f64, self.spin as f64)
     }
 }
 
@@ -31,7 +87,33 @@ pub struct GpuBody {
     pub rs: f32,
     pub disk_inner: f32,
     pub disk_outer: f32,
-    pub _padding: f32,
+    pub spin: f32,
+    pub horizon: f32,
+    pub isco: f32,
+    pub _padding: [f32; 2],
+    /// Unit normal of the disk plane, w unused.
+    pub disk_normal: [f32; 4],
+}
+
+/// Two bodies that merged during `Simulation::step`.
+#[derive(Debug, Clone, Copy)]
+pub struct MergerEvent {
+    pub time: f64,
+    /// Indices of the pair before the merger. The remnant takes the first
+    /// index and the second body is removed, shifting later bodies down.
+    pub indices: (usize, usize),
+    pub masses: (f32, f32),
+    pub remnant_mass: f32,
+    pub remnant_spin: f32,
+    /// Recoil speed in units of c.
+    pub kick: f32,
+}
+
+impl MergerEvent {
+    /// Fraction of the initial mass carried away by gravitational waves.
+    pub fn radiated_fraction(&self) -> f32 {
+        1.0 - self.remnant_mass / (self.masses.0 + self.masses.1)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -39,16 +121,24 @@ pub enum Preset {
     Single,
     Binary,
     Triple,
+    /// The binary with each disk tilted out of the orbital plane.
+    Misaligned,
 }
 
 impl Preset {
-    pub const ALL: [Preset; 3] = [Preset::Single, Preset::Binary, Preset::Triple];
+    pub const ALL: [Preset; 4] = [
+        Preset::Single,
+        Preset::Binary,
+        Preset::Triple,
+        Preset::Misaligned,
+    ];
 
     pub fn name(self) -> &'static str {
         match self {
             Preset::Single => "Single",
             Preset::Binary => "Binary",
             Preset::Triple => "Triple",
+            Preset::Misaligned => "Misaligned",
         }
     }
 }
@@ -58,7 +148,16 @@ pub struct Simulation {
     pub time: f64,
     pub paused: bool,
     pub speed: f32,
-    pub preset: Preset,
+    /// Preset the bodies came from, or `None` for a loaded scene file.
+    pub preset: Option<Preset>,
+    /// Order of the post-Newtonian orbital dynamics, chosen per scene.
+    pub pn_order: PnOrder,
+    /// Mergers since the scene was loaded, oldest first.
+    pub mergers: Vec<MergerEvent>,
+    /// Quadrupole gravitational-wave strain, one sample per step.
+    pub strain: StrainRecorder,
+    /// Points kept in each body's trail.
+    pub trail_length: usize,
 }
 
 impl Simulation {
@@ -68,27 +167,40 @@ impl Simulation {
             time: 0.0,
             paused: true,
             speed: 1.0,
-            preset,
+            preset: Some(preset),
+            pn_order: PnOrder::Newtonian,
+            mergers: Vec::new(),
+            strain: StrainRecorder::default(),
+            trail_length: 500,
         };
         sim.load_preset(preset);
         sim
     }
 
     pub fn load_preset(&mut self, preset: Preset) {
-        self.preset = preset;
-        self.time = 0.0;
+        self.reset();
+        self.preset = Some(preset);
 
         match preset {
             Preset::Single => {
                 self.bodies = vec![Body::new(Vec3::ZERO, Vec3::ZERO, 1.0)];
                 self.paused = true;
+                self.pn_order = PnOrder::Newtonian;
             }
-            Preset::Binary => {
+            Preset::Binary | Preset::Misaligned => {
                 let separation = 6.0;
                 let rs = 0.5;
-                // Circular orbit velocity: v = sqrt(rs_other / (4 * d))
-                // where d is the separation between the two bodies
-                let v = (rs / (4.0_f32 * separation)).sqrt();
+                // Radiation reaction makes the pair inspiral. Each body moves
+                // at v = ω d / 2 with ω the PN-corrected circular frequency of
+                // two masses rs/2 at separation d.
+                self.pn_order = PnOrder::TwoAndHalfPn;
+                let omega = post_newtonian::circular_angular_frequency(
+                    rs as f64,
+                    separation as f64,
+                    0.25,
+                    self.pn_order,
+                );
+                let v = (omega * separation as f64 / 2.0) as f32;
                 self.bodies = vec![
                     Body::new(
                         Vec3::new(separation / 2.0, 0.0, 0.0),
@@ -101,6 +213,11 @@ impl Simulation {
                         rs,
                     ),
                 ];
+                if preset == Preset::Misaligned {
+                    self.bodies[0].disk_normal = Body::tilted_disk_normal(0.5, 0.0);
+                    self.bodies[1].disk_normal =
+                        Body::tilted_disk_normal(1.0, 0.5 * std::f32::consts::PI);
+                }
                 self.paused = false;
             }
             Preset::Triple => {
@@ -122,86 +239,346 @@ impl Simulation {
                 }
                 self.bodies = bodies;
                 self.paused = false;
+                self.pn_order = PnOrder::Newtonian;
             }
         }
     }
 
-    /// Leapfrog (kick-drift-kick) N-body integration
+    /// Replace the scene with `bodies`, e.g. from a scene file.
+    pub fn load_bodies(&mut self, bodies: Vec<Body>) {
+        self.reset();
+        self.bodies = bodies;
+    }
+
+    /// Add a body, e.g. one placed with the mouse, and return its index.
+    pub fn add_body(&mut self, body: Body) -> usize {
+        self.preset = None;
+        self.bodies.push(body);
+        self.bodies.len() - 1
+    }
+
+    pub fn remove_body(&mut self, index: usize) -> Body {
+        self.preset = None;
+        self.bodies.remove(index)
+    }
+
+    fn reset(&mut self) {
+        self.preset = None;
+        self.time = 0.0;
+        self.mergers.clear();
+        self.strain.clear();
+    }
+
+    /// Leapfrog (kick-drift-kick) N-body integration. PN accelerations
+    /// depend on velocity, so the second kick uses the half-kicked velocities.
     pub fn step(&mut self, dt: f32) {
-        if self.paused || self.bodies.len() <= 1 {
+        // A lone body still drifts, e.g. a remnant carrying its recoil kick.
+        if self.paused || self.bodies.is_empty() {
             return;
         }
 
         let dt = dt * self.speed;
-        let n = self.bodies.len();
 
         // Half-kick: update velocities by dt/2
-        let mut accels = vec![Vec3::ZERO; n];
-        for i in 0..n {
-            for j in 0..n {
-                if i == j {
-                    continue;
-                }
-                let delta = self.bodies[j].position - self.bodies[i].position;
-                let r = delta.length();
-                if r < 0.1 {
-                    continue;
-                }
-                // a = rs_other / (2 * r^2) * r_hat
-                // With G=c=1: M = rs/2, so a = M/r^2 = rs/(2*r^2)
-                let a_mag = self.bodies[j].rs / (2.0 * r * r);
-                accels[i] += a_mag * delta / r;
-            }
-        }
-
-        for i in 0..n {
-            self.bodies[i].velocity += accels[i] * dt * 0.5;
+        let accels = self.accelerations();
+        for (body, accel) in self.bodies.iter_mut().zip(&accels) {
+            body.velocity += *accel * dt * 0.5;
         }
 
         // Drift: update positions by dt
-        for i in 0..n {
-            let vel = self.bodies[i].velocity;
-            self.bodies[i].position += vel * dt;
+        for body in &mut self.bodies {
+            body.position += body.velocity * dt;
         }
 
         // Half-kick: recompute accelerations and update velocities by dt/2
-        let mut accels = vec![Vec3::ZERO; n];
-        for i in 0..n {
-            for j in 0..n {
-                if i == j {
-                    continue;
+        let accels = self.accelerations();
+        for (body, accel) in self.bodies.iter_mut().zip(&accels) {
+            body.velocity += *accel * dt * 0.5;
+        }
+
+        self.time += dt as f64;
+        self.strain.record(self.time, &self.bodies, &accels);
+        self.merge_close_pairs();
+        for body in &mut self.bodies {
+            body.record_trail(self.trail_length);
+        }
+    }
+
+    /// Replace every pair whose horizons touch with its merger remnant.
+    fn merge_close_pairs(&mut self) {
+        while let Some((i, j)) = self.find_merging_pair() {
+            let event = self.merge(i, j);
+            log::info!(
+                "Merger at t = {:.1}: bodies {} and {} -> M = {:.3}, a/M = {:.3}",
+                event.time,
+                i,
+                j,
+                event.remnant_mass,
+                event.remnant_spin
+            );
+            self.mergers.push(event);
+        }
+    }
+
+    fn find_merging_pair(&self) -> Option<(usize, usize)> {
+        let horizons: Vec<f32> = self
+            .bodies
+            .iter()
+            .map(|body| body.metric().horizon_radius() as f32)
+            .collect();
+        for i in 0..self.bodies.len() {
+            for j in i + 1..self.bodies.len() {
+                let d = self.bodies[i].position.distance(self.bodies[j].position);
+                if d < horizons[i] + horizons[j] {
+                    return Some((i, j));
                 }
-                let delta = self.bodies[j].position - self.bodies[i].position;
-                let r = delta.length();
+            }
+        }
+        None
+    }
+
+    /// Merge bodies `i < j` into a remnant at their centre of mass. Spins are
+    /// fixed to the y axis, so only their components along the orbital
+    /// angular momentum enter the fits.
+    fn merge(&mut self, i: usize, j: usize) -> MergerEvent {
+        let other = self.bodies.remove(j);
+        let body = &self.bodies[i];
+
+        let (m_i, m_j) = (body.mass() as f64, other.mass() as f64);
+        let m = m_i + m_j;
+        let position = (m_i * body.position.as_dvec3() + m_j * other.position.as_dvec3()) / m;
+        let velocity = (m_i * body.velocity.as_dvec3() + m_j * other.velocity.as_dvec3()) / m;
+
+        let x = (body.position - other.position).as_dvec3();
+        let orbital = x.cross((body.velocity - other.velocity).as_dvec3());
+        let orientation = if orbital.y < 0.0 { -1.0 } else { 1.0 };
+        let l_hat = if orbital.length_squared() > 0.0 {
+            orbital.normalize()
+        } else {
+            DVec3::Y
+        };
+
+        let (heavy, light, e1) = if m_i >= m_j {
+            (body, &other, x.normalize_or_zero())
+        } else {
+            (&other, body, -x.normalize_or_zero())
+        };
+        let fit = remnant::remnant(
+            heavy.mass() as f64,
+            light.mass() as f64,
+            heavy.spin as f64 * orientation,
+            light.spin as f64 * orientation,
+        );
+        let kick = fit.kick.x * e1 + fit.kick.y * l_hat.cross(e1);
+
+        let merged = Body {
+            position: position.as_vec3(),
+            velocity: (velocity + kick).as_vec3(),
+            rs: 2.0 * fit.mass as f32,
+            spin: (fit.spin * orientation).clamp(-0.998, 0.998) as f32,
+            disk_inner_mult: heavy.disk_inner_mult,
+            disk_outer_mult: heavy.disk_outer_mult,
+            disk_normal: heavy.disk_normal,
+            // The remnant carries on from the heavier hole's path.
+            trail: heavy.trail.clone(),
+        };
+        let event = MergerEvent {
+            time: self.time,
+            indices: (i, j),
+            masses: (body.mass(), other.mass()),
+            remnant_mass: fit.mass as f32,
+            remnant_spin: merged.spin,
+            kick: kick.length() as f32,
+        };
+        self.bodies[i] = merged;
+        event
+    }
+
+    /// Acceleration of every body due to all the others: Newtonian gravity
+    /// plus, pair by pair, the post-Newtonian two-body terms up to `pn_order`.
+    fn accelerations(&self) -> Vec<Vec3> {
+        let mut accels = vec![DVec3::ZERO; self.bodies.len()];
+        for (i, body) in self.bodies.iter().enumerate() {
+            for (j, other) in self.bodies.iter().enumerate().skip(i + 1) {
+                let x = (body.position - other.position).as_dvec3();
+                let r = x.length();
                 if r < 0.1 {
                     continue;
                 }
-                let a_mag = self.bodies[j].rs / (2.0 * r * r);
-                accels[i] += a_mag * delta / r;
+                // With G=c=1: M = rs/2, so a = M/r^2 toward the other body
+                let (m1, m2) = (body.mass() as f64, other.mass() as f64);
+                let newtonian = x / (r * r * r);
+                accels[i] -= m2 * newtonian;
+                accels[j] += m1 * newtonian;
+
+                // The expansion is meaningless once the horizons overlap.
+                if self.pn_order == PnOrder::Newtonian || r < (body.rs + other.rs) as f64 {
+                    continue;
+                }
+                let m = m1 + m2;
+                let v = (body.velocity - other.velocity).as_dvec3();
+                let relative = post_newtonian::relative_acceleration_correction(
+                    x,
+                    v,
+                    m,
+                    m1 * m2 / (m * m),
+                    self.pn_order,
+                );
+                accels[i] += m2 / m * relative;
+                accels[j] -= m1 / m * relative;
             }
         }
+        accels.iter().map(|a| a.as_vec3()).collect()
+    }
+
+    pub fn gpu_bodies(&self) -> Vec<GpuBody> {
+        self.bodies
+            .iter()
+            .map(|body| {
+                let metric = body.metric();
+                GpuBody {
+                    position: [body.position.x, body.position.y, body.position.z, 0.0],
+                    rs: body.rs,
+                    disk_inner: body.disk_inner_mult * body.rs,
+                    disk_outer: body.disk_outer_mult * body.rs,
+                    spin: body.spin,
+                    horizon: metric.horizon_radius() as f32,
+                    isco: metric.isco_radius() as f32,
+                    _padding: [0.0; 2],
+                    disk_normal: body.disk_normal.extend(0.0).to_array(),
+                }
+            })
+            .collect()
+    }
 
-        for i in 0..n {
-            self.bodies[i].velocity += accels[i] * dt * 0.5;
+    /// Shader integration path able to render every body in the scene.
+    /// `exact` asks for full null geodesics, which are only available for a
+    /// single non-rotating body.
+    pub fn geodesic_model(&self, exact: bool) -> GeodesicModel {
+        let model = self
+            .bodies
+            .iter()
+            .map(|body| body.metric().geodesic_model())
+            .max()
+            .unwrap_or(GeodesicModel::PseudoNewtonian);
+        if exact && self.supports_exact_geodesics() {
+            GeodesicModel::NullGeodesic
+        } else {
+            model
         }
+    }
 
-        self.time += dt as f64;
+    pub fn supports_exact_geodesics(&self) -> bool {
+        self.bodies.len() == 1 && self.bodies[0].spin == 0.0
     }
+}
 
-    pub fn gpu_bodies(&self) -> [GpuBody; MAX_BODIES] {
-        let mut result = [GpuBody::zeroed(); MAX_BODIES];
-        for (i, body) in self.bodies.iter().enumerate() {
-            if i >= MAX_BODIES {
-                break;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Equal-mass binary in the xz-plane, separation `d`, relative speed `v_rel`
+    /// perpendicular to the separation.
+    fn binary(d: f32, v_rel: f32, rs: f32, order: PnOrder) -> Simulation {
+        let mut sim = Simulation::new(Preset::Binary);
+        sim.bodies = vec![
+            Body::new(
+                Vec3::new(d / 2.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, v_rel / 2.0),
+                rs,
+            ),
+            Body::new(
+                Vec3::new(-d / 2.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, -v_rel / 2.0),
+                rs,
+            ),
+        ];
+        sim.pn_order = order;
+        sim
+    }
+
+    fn separation(sim: &Simulation) -> Vec3 {
+        sim.bodies[0].position - sim.bodies[1].position
+    }
+
+    #[test]
+    fn one_pn_periastron_advance_matches_einstein() {
+        // Start at apastron of an e = 0.3 orbit, weak field (m/a = 1%).
+        let (m, a, e) = (0.5_f32, 50.0_f32, 0.3_f32);
+        let r_apo = a * (1.0 + e);
+        let v_apo = (m / a * (1.0 - e) / (1.0 + e)).sqrt();
+        let mut sim = binary(r_apo, v_apo, m, PnOrder::OnePn);
+
+        // Record the direction of each periastron passage.
+        let mut periastra = Vec::new();
+        let (mut r_prev2, mut r_prev) = (f32::MAX, f32::MAX);
+        let mut x_prev = separation(&sim);
+        while periastra.len() < 3 {
+            sim.step(0.5);
+            let x = separation(&sim);
+            let r = x.length();
+            if r_prev < r_prev2 && r_prev < r {
+                periastra.push(x_prev.z.atan2(x_prev.x));
             }
-            result[i] = GpuBody {
-                position: [body.position.x, body.position.y, body.position.z, 0.0],
-                rs: body.rs,
-                disk_inner: body.disk_inner_mult * body.rs,
-                disk_outer: body.disk_outer_mult * body.rs,
-                _padding: 0.0,
-            };
-        }
-        result
+            (r_prev2, r_prev, x_prev) = (r_prev, r, x);
+        }
+
+        let advance = (periastra[2] - periastra[1]).rem_euclid(std::f32::consts::TAU);
+        let expected = 6.0 * std::f32::consts::PI * m / (a * (1.0 - e * e));
+        assert!(
+            (advance - expected).abs() < 0.05 * expected,
+            "advance {advance} rad per orbit, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn binary_preset_inspirals_and_merges_into_one_remnant() {
+        let mut sim = Simulation::new(Preset::Binary);
+        let total_mass: f32 = sim.bodies.iter().map(Body::mass).sum();
+        while sim.time < 1000.0 && sim.bodies.len() > 1 {
+            sim.step(0.016);
+        }
+
+        assert_eq!(sim.bodies.len(), 1, "no merger by t = {}", sim.time);
+        assert_eq!(sim.mergers.len(), 1);
+        let event = sim.mergers[0];
+        assert_eq!(event.indices, (0, 1));
+        // Equal masses, no spin: ~4.8% radiated, |a/M| ≈ 0.69, no kick. The
+        // preset's orbital angular momentum points along -y, and so does the spin.
+        assert!((event.radiated_fraction() - 0.048).abs() < 2e-3);
+        assert!((event.remnant_spin + 0.686).abs() < 5e-3);
+        assert!(event.kick < 1e-6);
+
+        let remnant = &sim.bodies[0];
+        assert!((remnant.mass() - event.remnant_mass).abs() < 1e-6);
+        assert!(remnant.mass() < total_mass);
+        assert!(
+            remnant.position.length() < 0.1,
+            "remnant at {}",
+            remnant.position
+        );
+        assert_eq!(sim.gpu_bodies().len(), 1);
+    }
+
+    #[test]
+    fn trails_are_bounded_and_evenly_spaced() {
+        let mut sim = Simulation::new(Preset::Triple);
+        sim.trail_length = 50;
+        for _ in 0..2000 {
+            sim.step(0.016);
+        }
+
+        for body in &sim.bodies {
+            assert_eq!(body.trail.len(), 50);
+            let spacings = body.trail.iter().zip(body.trail.iter().skip(1));
+            for (a, b) in spacings {
+                let d = a.distance(*b);
+                assert!(
+                    (TRAIL_SPACING..2.0 * TRAIL_SPACING).contains(&d),
+                    "spacing {d}"
+                );
+            }
+            assert!(body.trail.back().unwrap().distance(body.position) < TRAIL_SPACING);
+        }
     }
 }
@@ -1,13 +1,78 @@
+use std::collections::VecDeque;
+
+use glam::DVec3;
+
+use crate::animation::{Keyframe, Timeline};
+use crate::gravitational_waves::{self, StrainRecorder, StrainSample};
+use crate::inspector::{Inspection, PixelInspector};
+use crate::placement::PlacementTool;
+use crate::post_newtonian::PnOrder;
+use crate::remnant::C_KM_S;
 use crate::renderer::camera::OrbitalCamera;
-use crate::simulation::{Preset, Simulation};
+use crate::renderer::observer::{Motion, Observer, STOP_RADIUS};
+use crate::renderer::pipeline::ImageFormat;
+use crate::renderer::post::PostSettings;
+use crate::renderer::reference::RayFate;
+use crate::renderer::stars::SkyLocation;
+use crate::renderer::tonemap::Tonemapper;
+use crate::renderer::uniforms::{
+    ramp_color, redshift_color, DiskModel, DiskSettings, IntegrationSettings, Integrator,
+    RenderMode, BODY_COLORS, DISK_ORDER_COLORS, FATE_COLORS,
+};
+use crate::scene::{Background, CameraSettings, RenderSettings, Scene};
+use crate::simulation::{Body, GpuBody, MergerEvent, Preset, Simulation};
 
 pub struct UiState {
     pub show_ui: bool,
     pub background_mode: u32,
+    /// Equirectangular image or cubemap directory for the image background.
+    pub background_image: String,
+    /// Set by the Load button; the app loads `background_image` and clears it.
+    pub background_image_requested: bool,
+    /// Result of the last background image load.
+    pub background_image_status: Option<String>,
+    /// Star catalogue CSV for the catalogue background.
+    pub star_catalog: String,
+    /// Set by the Load button; the app loads `star_catalog` and clears it.
+    pub star_catalog_requested: bool,
+    /// Result of the last star catalogue load.
+    pub star_catalog_status: Option<String>,
+    /// Where the black hole sits on the catalogue's sky.
+    pub sky: SkyLocation,
     pub disk_enabled: bool,
+    /// Thin or volumetric disk, shared by every body.
+    pub disk: DiskSettings,
     pub selected_body: usize,
     pub screenshot_requested: bool,
+    pub screenshot_format: ImageFormat,
+    /// Exposure, tonemapping and bloom of the displayed image.
+    pub post: PostSettings,
+    /// Average jittered samples while the scene is paused and the camera still.
+    pub accumulate: bool,
+    /// Samples in the displayed image.
+    pub accumulated_samples: u32,
     pub grid_enabled: bool,
+    /// Shaded image or a diagnostic view.
+    pub render_mode: RenderMode,
+    pub exact_geodesics: bool,
+    /// Number of `Simulation::mergers` already applied to `selected_body`.
+    pub mergers_seen: usize,
+    /// Result of the last strain export, shown under the plot.
+    pub strain_status: Option<String>,
+    /// Scene file for the Save and Load buttons.
+    pub scene_path: String,
+    /// Result of the last scene save or load.
+    pub scene_status: Option<String>,
+    pub placement: PlacementTool,
+    pub inspector: PixelInspector,
+    /// Static, free-falling or orbiting camera around body 0.
+    pub observer: Observer,
+    /// Draw each body's recent path over the image.
+    pub trails_enabled: bool,
+    /// Camera keyframes, saved with the scene.
+    pub timeline: Timeline,
+    /// Position of the keyframe preview slider.
+    pub animation_time: f64,
 }
 
 impl Default for UiState {
@@ -15,10 +80,34 @@ impl Default for UiState {
         Self {
             show_ui: true,
             background_mode: 1,
+            background_image: String::new(),
+            background_image_requested: false,
+            background_image_status: None,
+            star_catalog: String::new(),
+            star_catalog_requested: false,
+            star_catalog_status: None,
+            sky: SkyLocation::default(),
             disk_enabled: true,
+            disk: DiskSettings::default(),
             selected_body: 0,
             screenshot_requested: false,
+            screenshot_format: ImageFormat::Png,
+            post: PostSettings::default(),
+            accumulate: true,
+            accumulated_samples: 0,
             grid_enabled: false,
+            render_mode: RenderMode::Shaded,
+            exact_geodesics: false,
+            mergers_seen: 0,
+            strain_status: None,
+            scene_path: "scenes/scene.toml".to_string(),
+            scene_status: None,
+            placement: PlacementTool::default(),
+            inspector: PixelInspector::default(),
+            observer: Observer::default(),
+            trails_enabled: true,
+            timeline: Timeline::default(),
+            animation_time: 0.0,
         }
     }
 }
@@ -28,13 +117,37 @@ pub fn draw_ui(
     ui_state: &mut UiState,
     simulation: &mut Simulation,
     camera: &mut OrbitalCamera,
-    max_steps: &mut u32,
-    step_size: &mut f32,
+    integration: &mut IntegrationSettings,
 ) {
+    track_mergers(ui_state, simulation);
+    if ui_state.trails_enabled {
+        draw_trails(ctx, simulation, camera);
+    }
+    draw_placement(ctx, &ui_state.placement, camera);
+    if ui_state.inspector.enabled {
+        inspector_window(ctx, &mut ui_state.inspector);
+    }
+
     if !ui_state.show_ui {
         return;
     }
 
+    // Announce the latest merger for a few seconds of simulation time
+    if let Some(event) = simulation
+        .mergers
+        .last()
+        .filter(|event| simulation.time - event.time < 5.0)
+    {
+        egui::Area::new(egui::Id::new("merger_banner"))
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 20.0])
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.heading("Black hole merger");
+                    ui.label(merger_summary(event));
+                });
+            });
+    }
+
     egui::Window::new("Black Hole Parameters")
         .default_pos([10.0, 10.0])
         .show(ctx, |ui| {
@@ -43,7 +156,7 @@ pub fn draw_ui(
             ui.horizontal(|ui| {
                 for preset in Preset::ALL {
                     if ui
-                        .selectable_label(simulation.preset == preset, preset.name())
+                        .selectable_label(simulation.preset == Some(preset), preset.name())
                         .clicked()
                     {
                         simulation.load_preset(preset);
@@ -51,6 +164,7 @@ pub fn draw_ui(
                     }
                 }
             });
+            scene_panel(ui, ui_state, simulation, camera, integration);
 
             ui.separator();
 
@@ -64,18 +178,63 @@ pub fn draw_ui(
                         .logarithmic(true),
                 );
             });
+            ui.horizontal(|ui| {
+                ui.label("Dynamics:");
+                for order in PnOrder::ALL {
+                    ui.selectable_value(&mut simulation.pn_order, order, order.name());
+                }
+            })
+            .response
+            .on_hover_text("Post-Newtonian order; 2.5PN adds gravitational-wave radiation reaction");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut ui_state.trails_enabled, "Trails");
+                ui.add_enabled(
+                    ui_state.trails_enabled,
+                    egui::Slider::new(&mut simulation.trail_length, 10..=5000)
+                        .text("Length")
+                        .logarithmic(true),
+                )
+                .on_hover_text("Points per trail, one every 0.05 units travelled");
+            });
             ui.label(format!("Time: {:.1}s", simulation.time));
+            if !simulation.mergers.is_empty() {
+                ui.collapsing(format!("Mergers ({})", simulation.mergers.len()), |ui| {
+                    for event in &simulation.mergers {
+                        ui.label(format!("t = {:.1}s: {}", event.time, merger_summary(event)));
+                    }
+                });
+            }
+
+            ui.collapsing("Gravitational waves", |ui| {
+                strain_panel(ui, ui_state, &mut simulation.strain);
+            });
 
             ui.separator();
 
             // Bodies list
             ui.heading("Bodies");
+            let placement = &mut ui_state.placement;
+            if ui.checkbox(&mut placement.enabled, "Place bodies")
+                .on_hover_text("Click the disk plane to drop a black hole; drag before releasing to set its velocity")
+                .changed()
+            {
+                ui_state.inspector.enabled &= !placement.enabled;
+            }
+            if placement.enabled {
+                ui.add(egui::Slider::new(&mut placement.rs, 0.1..=5.0).text("New body rs"));
+                ui.add(
+                    egui::Slider::new(&mut placement.velocity_scale, 0.01..=0.5)
+                        .text("Velocity per unit drag")
+                        .logarithmic(true),
+                );
+            }
             let num_bodies = simulation.bodies.len();
             for i in 0..num_bodies {
                 let label = format!(
                     "Body {} (rs={:.2})",
                     i, simulation.bodies[i].rs
                 );
+                let label = egui::RichText::new(label).color(trail_color(i));
                 if ui
                     .selectable_label(ui_state.selected_body == i, label)
                     .clicked()
@@ -94,20 +253,35 @@ pub fn draw_ui(
             // Selected body details
             if num_bodies > 0 {
                 let idx = ui_state.selected_body;
-                ui.heading(format!("Body {}", idx));
+                let delete = ui
+                    .horizontal(|ui| {
+                        ui.heading(format!("Body {}", idx));
+                        ui.button("Delete (Del)").clicked()
+                    })
+                    .inner;
 
                 ui.add(
                     egui::Slider::new(&mut simulation.bodies[idx].rs, 0.1..=5.0)
                         .text("Schwarzschild radius (rs)"),
                 );
 
-                let rs = simulation.bodies[idx].rs;
-                ui.label(format!("Photon sphere: r = {:.2}", 1.5 * rs));
+                ui.add(
+                    egui::Slider::new(&mut simulation.bodies[idx].spin, -0.998..=0.998)
+                        .text("Spin (a/M)"),
+                );
+
+                let metric = simulation.bodies[idx].metric();
+                ui.label(format!(
+                    "{} horizon: r = {:.2}",
+                    metric.name(),
+                    metric.horizon_radius()
+                ));
+                ui.label(format!("Photon sphere: r = {:.2}", metric.photon_sphere_radius()));
                 ui.label(format!(
                     "Critical impact param: b = {:.2}",
-                    3.0 * 3.0_f32.sqrt() / 2.0 * rs
+                    metric.critical_impact_parameter()
                 ));
-                ui.label(format!("ISCO: r = {:.2}", 3.0 * rs));
+                ui.label(format!("ISCO: r = {:.2}", metric.isco_radius()));
 
                 ui.label(format!(
                     "Position: ({:.2}, {:.2}, {:.2})",
@@ -123,7 +297,7 @@ pub fn draw_ui(
                     ui.add(
                         egui::Slider::new(
                             &mut simulation.bodies[idx].disk_inner_mult,
-                            1.5..=10.0,
+                            0.5..=10.0,
                         )
                         .text("Inner radius (×rs)"),
                     );
@@ -134,6 +308,59 @@ pub fn draw_ui(
                         )
                         .text("Outer radius (×rs)"),
                     );
+                    let (mut inclination, mut azimuth) = simulation.bodies[idx].disk_tilt();
+                    let tilt_changed = ui
+                        .add(
+                            egui::Slider::new(&mut inclination, 0.0..=std::f32::consts::PI)
+                                .text("Disk tilt"),
+                        )
+                        .changed()
+                        | ui.add(
+                            egui::Slider::new(
+                                &mut azimuth,
+                                -std::f32::consts::PI..=std::f32::consts::PI,
+                            )
+                            .text("Tilt direction"),
+                        )
+                        .changed();
+                    if tilt_changed {
+                        simulation.bodies[idx].disk_normal =
+                            Body::tilted_disk_normal(inclination, azimuth);
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Model:");
+                        for model in DiskModel::ALL {
+                            ui.selectable_value(&mut ui_state.disk.model, model, model.name());
+                        }
+                    });
+                    if ui_state.disk.model == DiskModel::Volumetric {
+                        ui.add(
+                            egui::Slider::new(&mut ui_state.disk.scale_height, 0.02..=0.5)
+                                .text("Scale height (H/R)"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut ui_state.disk.optical_depth, 0.05..=50.0)
+                                .text("Optical depth")
+                                .logarithmic(true),
+                        );
+                    }
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut ui_state.disk.doppler_beaming, "Doppler beaming")
+                            .on_hover_text("Shift and boost light by the gas's orbital motion");
+                        ui.checkbox(
+                            &mut ui_state.disk.gravitational_redshift,
+                            "Gravitational redshift",
+                        )
+                        .on_hover_text("Shift and dim light climbing out of the potential well");
+                    });
+                    ui.checkbox(&mut ui_state.disk.specific_intensity, "Specific intensity (g³)")
+                        .on_hover_text(
+                            "Scale intensity as g³ at a fixed frequency instead of g⁴ over all frequencies",
+                        );
+                }
+
+                if delete {
+                    simulation.remove_body(idx);
                 }
             }
 
@@ -148,18 +375,62 @@ pub fn draw_ui(
                 egui::Slider::new(&mut camera.fov, 0.2..=2.5)
                     .text("FOV (radians)"),
             );
+            if let Some(body) = simulation.bodies.first() {
+                observer_panel(ui, &mut ui_state.observer, camera, body);
+            }
 
             ui.separator();
             ui.heading("Integration");
+            ui.horizontal(|ui| {
+                for integrator in Integrator::ALL {
+                    ui.selectable_value(&mut integration.integrator, integrator, integrator.name());
+                }
+            });
             ui.add(
-                egui::Slider::new(max_steps, 50..=2000)
-                    .text("Max RK4 steps"),
+                egui::Slider::new(&mut integration.max_steps, 50..=2000)
+                    .text("Max steps"),
             );
+            match integration.integrator {
+                Integrator::FixedRk4 => {
+                    ui.add(
+                        egui::Slider::new(&mut integration.step_size, 0.01..=1.0)
+                            .text("Step size (dt)")
+                            .logarithmic(true),
+                    );
+                }
+                Integrator::AdaptiveRk45 => {
+                    ui.add(
+                        egui::Slider::new(&mut integration.tolerance, 1e-7..=1e-2)
+                            .text("Tolerance")
+                            .logarithmic(true),
+                    )
+                    .on_hover_text("Local error per step, relative to the distance to the nearest body");
+                    ui.add(
+                        egui::Slider::new(&mut integration.min_step, 0.001..=0.1)
+                            .text("Min step")
+                            .logarithmic(true),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut integration.max_step, 0.1..=10.0)
+                            .text("Max step")
+                            .logarithmic(true),
+                    );
+                }
+            }
             ui.add(
-                egui::Slider::new(step_size, 0.01..=1.0)
-                    .text("Step size (dt)")
+                egui::Slider::new(&mut integration.body_cutoff, 0.0..=1000.0)
+                    .text("Far-body cutoff (×rs)")
                     .logarithmic(true),
+            )
+            .on_hover_text(
+                "Bodies farther than this from a ray step are evaluated once per step instead of at every integrator stage; 0 evaluates all of them every time. Speeds up scenes with many bodies.",
             );
+            ui.add_enabled(
+                simulation.supports_exact_geodesics(),
+                egui::Checkbox::new(&mut ui_state.exact_geodesics, "Exact null geodesics"),
+            )
+            .on_disabled_hover_text("Needs a single non-rotating black hole")
+            .on_hover_text("Integrate k^μ with Christoffel symbols, with light-travel delay and exact redshift");
 
             ui.separator();
             ui.heading("Rendering");
@@ -167,10 +438,849 @@ pub fn draw_ui(
                 ui.label("Background:");
                 ui.selectable_value(&mut ui_state.background_mode, 0, "Checkerboard");
                 ui.selectable_value(&mut ui_state.background_mode, 1, "Star field");
+                ui.selectable_value(&mut ui_state.background_mode, 2, "Image");
+                ui.selectable_value(&mut ui_state.background_mode, 3, "Star catalogue");
             });
+            if ui_state.background_mode == 2 {
+                ui.horizontal(|ui| {
+                    ui.label("Image:");
+                    ui.text_edit_singleline(&mut ui_state.background_image)
+                        .on_hover_text(
+                            "Equirectangular PNG, HDR or EXR file, or a directory of cube faces px, nx, py, ny, pz, nz",
+                        );
+                    if ui.button("Load").clicked() {
+                        ui_state.background_image_requested = true;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let open_image = ui.button("Open image…").clicked();
+                    let open_cubemap = ui.button("Open cubemap…").clicked();
+                    let picked = if open_image {
+                        rfd::FileDialog::new()
+                            .add_filter("Image", &["png", "hdr", "exr", "jpg", "jpeg"])
+                            .pick_file()
+                    } else if open_cubemap {
+                        rfd::FileDialog::new()
+                            .set_title("Directory of cube faces")
+                            .pick_folder()
+                    } else {
+                        None
+                    };
+                    if let Some(path) = picked {
+                        ui_state.background_image = path.display().to_string();
+                        ui_state.background_image_requested = true;
+                    }
+                });
+                if let Some(status) = &ui_state.background_image_status {
+                    ui.label(status);
+                }
+            }
+            if ui_state.background_mode == 3 {
+                ui.horizontal(|ui| {
+                    ui.label("Catalogue:");
+                    ui.text_edit_singleline(&mut ui_state.star_catalog)
+                        .on_hover_text(
+                            "CSV with ra (hours) or ra_deg, dec, mag and ci columns, such as the HYG database",
+                        );
+                    if ui.button("Load").clicked() {
+                        ui_state.star_catalog_requested = true;
+                    }
+                });
+                if let Some(status) = &ui_state.star_catalog_status {
+                    ui.label(status);
+                }
+                ui.add(
+                    egui::Slider::new(&mut ui_state.sky.ra, 0.0..=360.0)
+                        .text("Sky RA")
+                        .suffix("°"),
+                )
+                .on_hover_text("Right ascension of the black hole; the default is Sagittarius A*");
+                ui.add(
+                    egui::Slider::new(&mut ui_state.sky.dec, -90.0..=90.0)
+                        .text("Sky Dec")
+                        .suffix("°"),
+                )
+                .on_hover_text("Declination of the black hole. Looking down −z, north is up");
+            }
             ui.checkbox(&mut ui_state.grid_enabled, "Spacetime Grid");
+            ui.horizontal_wrapped(|ui| {
+                ui.label("View:");
+                for mode in RenderMode::ALL {
+                    ui.selectable_value(&mut ui_state.render_mode, mode, mode.name());
+                }
+            });
+            render_mode_legend(ui, ui_state.render_mode, integration.max_steps);
+            if ui
+                .checkbox(&mut ui_state.inspector.enabled, "Inspect pixels")
+                .on_hover_text("Click a pixel to re-trace its ray on the CPU and see its path from the side")
+                .changed()
+            {
+                ui_state.placement.enabled &= !ui_state.inspector.enabled;
+            }
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut ui_state.accumulate, "Accumulate samples")
+                    .on_hover_text(
+                        "While paused with the camera still, average jittered rays per pixel \
+                         for a clean, anti-aliased image",
+                    );
+                if ui_state.accumulate {
+                    ui.label(format!("{} spp", ui_state.accumulated_samples));
+                }
+            });
+            let post = &mut ui_state.post;
+            if ui_state.render_mode != RenderMode::Shaded {
+                ui.weak("False-colour views are shown without exposure, tonemapping or bloom");
+            }
+            ui.add(
+                egui::Slider::new(&mut post.exposure, -6.0..=6.0)
+                    .text("Exposure")
+                    .suffix(" EV"),
+            );
+            ui.horizontal(|ui| {
+                ui.label("Tonemap:");
+                for tonemapper in Tonemapper::ALL {
+                    ui.selectable_value(&mut post.tonemapper, tonemapper, tonemapper.name());
+                }
+            });
+            ui.add(egui::Slider::new(&mut post.bloom_strength, 0.0..=2.0).text("Bloom"))
+                .on_hover_text("Glow around pixels brighter than the threshold, 0 = off");
+            ui.add_enabled(
+                post.bloom_strength > 0.0,
+                egui::Slider::new(&mut post.bloom_threshold, 0.0..=20.0)
+                    .text("Bloom threshold")
+                    .logarithmic(true),
+            );
+            ui.horizontal(|ui| {
+                ui.label("Screenshot format:");
+                for format in ImageFormat::ALL {
+                    ui.selectable_value(&mut ui_state.screenshot_format, format, format.name());
+                }
+            });
             if ui.button("Screenshot (F12)").clicked() {
                 ui_state.screenshot_requested = true;
             }
+            ui.collapsing("Camera keyframes", |ui| {
+                animation_panel(ui, ui_state, simulation, camera);
+            });
         });
 }
+
+/// Key to the false colours of the disk image order and diagnostic views.
+fn render_mode_legend(ui: &mut egui::Ui, mode: RenderMode, max_steps: u32) {
+    let swatches = |ui: &mut egui::Ui, names: &[&str], colors: &[[f32; 3]]| {
+        ui.horizontal_wrapped(|ui| {
+            for (name, &[r, g, b]) in names.iter().zip(colors) {
+                let color = egui::Rgba::from_rgb(r, g, b);
+                ui.colored_label(color, format!("■ {}", name));
+            }
+        });
+    };
+    match mode {
+        RenderMode::Shaded => {}
+        RenderMode::DiskOrder => swatches(
+            ui,
+            &["Direct", "Secondary", "Tertiary", "Photon ring (3+)"],
+            &DISK_ORDER_COLORS,
+        ),
+        RenderMode::Steps => {
+            color_bar(ui, ramp_color, "0", &format!("{} steps", max_steps));
+        }
+        RenderMode::Deflection => {
+            color_bar(ui, ramp_color, "0°", "180°");
+            ui.weak("Rays that never reach the sky are black");
+        }
+        RenderMode::Fate => swatches(
+            ui,
+            &[
+                "Escaped",
+                "Captured",
+                "Out of steps, heading away",
+                "Out of steps, heading in",
+                "Absorbed by disk",
+            ],
+            &FATE_COLORS,
+        ),
+        RenderMode::Redshift => {
+            // log₂ g from -1 to 1 across the bar
+            color_bar(
+                ui,
+                |t| redshift_color((2.0 * t - 1.0).exp2()),
+                "g = ½",
+                "g = 2",
+            );
+            ui.weak(
+                "Where the ray first meets a disk; toggle beaming and redshift to separate them",
+            );
+        }
+        RenderMode::Body => {
+            let names: Vec<_> = (0..BODY_COLORS.len())
+                .map(|i| format!("Body {}", i))
+                .collect();
+            let names: Vec<_> = names.iter().map(String::as_str).collect();
+            swatches(ui, &names, &BODY_COLORS);
+            ui.weak("Colours repeat for later bodies");
+        }
+    }
+}
+
+/// Horizontal bar of `color` over 0..1 between two labels.
+fn color_bar(ui: &mut egui::Ui, color: impl Fn(f32) -> [f32; 3], low: &str, high: &str) {
+    const SEGMENTS: usize = 48;
+    ui.horizontal(|ui| {
+        ui.label(low);
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(160.0, 12.0), egui::Sense::hover());
+        let painter = ui.painter();
+        let width = rect.width() / SEGMENTS as f32;
+        for i in 0..SEGMENTS {
+            let [r, g, b] = color((i as f32 + 0.5) / SEGMENTS as f32);
+            let left = rect.left() + i as f32 * width;
+            let segment = egui::Rect::from_x_y_ranges(left..=left + width, rect.y_range());
+            painter.rect_filled(segment, 0.0, egui::Rgba::from_rgb(r, g, b));
+        }
+        ui.label(high);
+    });
+}
+
+/// Observer motion controls and the moving camera's proper time and speed.
+fn observer_panel(ui: &mut egui::Ui, observer: &mut Observer, camera: &OrbitalCamera, body: &Body) {
+    let (motion, radius) = (observer.motion, observer.radius);
+    ui.horizontal(|ui| {
+        ui.label("Observer");
+        for motion in Motion::ALL {
+            ui.add_enabled_ui(motion.supports_spin(body.spin), |ui| {
+                ui.selectable_value(&mut observer.motion, motion, motion.name())
+            })
+            .inner
+            .on_disabled_hover_text("Follows a non-rotating body only");
+        }
+    });
+    if !observer.motion.supports_spin(body.spin) {
+        ui.weak("The camera stays at rest around a spinning body");
+        return;
+    }
+    if observer.motion == Motion::Static {
+        return;
+    }
+
+    // Orbits end at the photon sphere, falls just outside the horizon
+    let rs = body.rs as f64;
+    let metric = body.metric();
+    let min_radius = match observer.motion {
+        Motion::Orbit => metric.photon_sphere_radius() / rs + 0.05,
+        _ => STOP_RADIUS + 0.15,
+    };
+    ui.add(
+        egui::Slider::new(&mut observer.radius, min_radius..=100.0)
+            .text(match observer.motion {
+                Motion::Orbit => "Orbit radius (×rs)",
+                _ => "Start radius (×rs)",
+            })
+            .logarithmic(true),
+    );
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::Slider::new(&mut observer.time_scale, 0.1..=20.0)
+                .text("Proper time per second")
+                .logarithmic(true),
+        );
+        // Start over from the camera whenever the motion changes
+        if ui.button("Restart").clicked() || observer.motion != motion || observer.radius != radius
+        {
+            observer.restart(camera, body.position);
+        }
+    });
+
+    let state = observer.state(rs);
+    ui.label(format!(
+        "τ = {:.2}   r = {:.2} rs   v = {:.3}c",
+        observer.proper_time, state.radius, state.speed
+    ))
+    .on_hover_text(
+        "Proper time, distance from the body and speed relative to a static observer there",
+    );
+    match observer.motion {
+        Motion::Infall => {
+            let left = observer.stop_time(rs) - observer.proper_time;
+            ui.label(if left > 0.0 {
+                format!("{:.2} of proper time to r = {} rs", left, STOP_RADIUS)
+            } else {
+                format!(
+                    "Stopped at r = {} rs, just outside the horizon",
+                    STOP_RADIUS
+                )
+            });
+        }
+        Motion::Orbit if observer.radius * rs < metric.isco_radius() => {
+            ui.label("Inside the ISCO (unstable)");
+        }
+        _ => {}
+    }
+}
+
+/// Editor for the camera keyframes rendered by `--frames`.
+fn animation_panel(
+    ui: &mut egui::Ui,
+    ui_state: &mut UiState,
+    simulation: &Simulation,
+    camera: &mut OrbitalCamera,
+) {
+    let timeline = &mut ui_state.timeline;
+    if ui
+        .button("Add keyframe")
+        .on_hover_text("Current camera and simulation time, 2 s after the last keyframe")
+        .clicked()
+    {
+        let time = if timeline.is_empty() {
+            0.0
+        } else {
+            timeline.duration() + 2.0
+        };
+        timeline.insert(Keyframe {
+            time,
+            sim_time: simulation.time,
+            camera: CameraSettings::from(&*camera),
+        });
+    }
+
+    let mut edited = None;
+    let mut removed = None;
+    egui::Grid::new("keyframes").striped(true).show(ui, |ui| {
+        ui.label("Time");
+        ui.label("Sim time");
+        ui.end_row();
+        for (i, key) in timeline.keyframes().iter().enumerate() {
+            let mut edit = *key;
+            let time = ui.add(
+                egui::DragValue::new(&mut edit.time)
+                    .speed(0.05)
+                    .range(0.0..=f64::MAX)
+                    .suffix(" s"),
+            );
+            let sim_time = ui.add(
+                egui::DragValue::new(&mut edit.sim_time)
+                    .speed(0.5)
+                    .range(0.0..=f64::MAX)
+                    .suffix(" s"),
+            );
+            if time.changed() || sim_time.changed() {
+                edited = Some((i, edit));
+            }
+            if ui.button("View").clicked() {
+                key.camera.apply(camera);
+            }
+            if ui
+                .button("Set")
+                .on_hover_text("Replace with the current camera")
+                .clicked()
+            {
+                edit.camera = CameraSettings::from(&*camera);
+                edited = Some((i, edit));
+            }
+            if ui.button("Remove").clicked() {
+                removed = Some(i);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some((i, key)) = edited {
+        timeline.update(i, key);
+    }
+    if let Some(i) = removed {
+        timeline.remove(i);
+    }
+
+    if timeline.is_empty() {
+        return;
+    }
+    let preview = ui.add(
+        egui::Slider::new(&mut ui_state.animation_time, 0.0..=timeline.duration())
+            .text("Preview")
+            .suffix(" s"),
+    );
+    if preview.changed() {
+        if let Some(key) = timeline.sample(ui_state.animation_time) {
+            key.camera.apply(camera);
+        }
+    }
+    match timeline.validate() {
+        Ok(()) => ui.label(format!(
+            "{} frames at 30 fps. Save the scene and render with --scene <file> --frames <dir>.",
+            timeline.frame_count(30.0)
+        )),
+        Err(e) => ui.colored_label(egui::Color32::LIGHT_RED, e),
+    };
+}
+
+fn scene_panel(
+    ui: &mut egui::Ui,
+    ui_state: &mut UiState,
+    simulation: &mut Simulation,
+    camera: &mut OrbitalCamera,
+    integration: &mut IntegrationSettings,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Scene file:");
+        ui.text_edit_singleline(&mut ui_state.scene_path);
+    });
+    ui.horizontal(|ui| {
+        let path = std::path::PathBuf::from(&ui_state.scene_path);
+        if ui.button("Save scene").clicked() {
+            let render = RenderSettings {
+                background: Background::from_mode(ui_state.background_mode),
+                background_image: (!ui_state.background_image.is_empty())
+                    .then(|| ui_state.background_image.clone().into()),
+                star_catalog: (!ui_state.star_catalog.is_empty())
+                    .then(|| ui_state.star_catalog.clone().into()),
+                sky_ra: ui_state.sky.ra,
+                sky_dec: ui_state.sky.dec,
+                grid: ui_state.grid_enabled,
+                disk: ui_state.disk_enabled,
+                exact_geodesics: ui_state.exact_geodesics,
+                ..RenderSettings::new(integration, &ui_state.disk, &ui_state.post)
+            };
+            let scene = Scene {
+                timeline: ui_state.timeline.clone(),
+                ..Scene::capture(simulation, camera, render)
+            };
+            let saved = scene.save(&path);
+            ui_state.scene_status = Some(match saved {
+                Ok(()) => format!("Saved {}", path.display()),
+                Err(e) => format!("Save failed: {}", e),
+            });
+        }
+        if ui.button("Load scene").clicked() {
+            ui_state.scene_status = Some(match Scene::load(&path) {
+                Ok(scene) => {
+                    scene.restore(simulation);
+                    scene.camera.apply(camera);
+                    *integration = scene.render.integration();
+                    ui_state.background_mode = scene.render.background.mode();
+                    if let Some(image) = &scene.render.background_image {
+                        ui_state.background_image = image.display().to_string();
+                        ui_state.background_image_requested = true;
+                    }
+                    if let Some(catalog) = &scene.render.star_catalog {
+                        ui_state.star_catalog = catalog.display().to_string();
+                        ui_state.star_catalog_requested = true;
+                    }
+                    ui_state.sky = scene.render.sky();
+                    ui_state.grid_enabled = scene.render.grid;
+                    ui_state.disk_enabled = scene.render.disk;
+                    ui_state.disk = scene.render.disk_settings();
+                    ui_state.exact_geodesics = scene.render.exact_geodesics;
+                    ui_state.post = scene.render.post();
+                    ui_state.selected_body = 0;
+                    ui_state.timeline = scene.timeline;
+                    format!("Loaded {}", path.display())
+                }
+                Err(e) => format!("Load failed: {}", e),
+            });
+        }
+    });
+    if let Some(status) = &ui_state.scene_status {
+        ui.label(status);
+    }
+}
+
+/// Trail colour of body `i`, also used for its entry in the body list.
+fn trail_color(i: usize) -> egui::Color32 {
+    const COLORS: [egui::Color32; 6] = [
+        egui::Color32::from_rgb(255, 170, 60),
+        egui::Color32::from_rgb(90, 180, 255),
+        egui::Color32::from_rgb(240, 90, 120),
+        egui::Color32::from_rgb(170, 120, 255),
+        egui::Color32::from_rgb(80, 220, 200),
+        egui::Color32::from_rgb(240, 220, 90),
+    ];
+    COLORS[i % COLORS.len()]
+}
+
+/// Each body's recent path as a polyline, projected straight onto the screen
+/// (no lensing) behind the UI windows and fading out towards its oldest end.
+fn draw_trails(ctx: &egui::Context, simulation: &Simulation, camera: &OrbitalCamera) {
+    let screen = ctx.screen_rect();
+    let project = |p: &glam::Vec3| {
+        camera
+            .project(*p, screen.width(), screen.height())
+            .map(|(x, y)| screen.min + egui::vec2(x, y))
+    };
+    let painter = ctx.layer_painter(egui::LayerId::background());
+    for (i, body) in simulation.bodies.iter().enumerate() {
+        let skip = body.trail.len().saturating_sub(simulation.trail_length);
+        let points: Vec<_> = body
+            .trail
+            .iter()
+            .skip(skip)
+            .chain([&body.position])
+            .map(project)
+            .collect();
+        let color = trail_color(i);
+        for (k, pair) in points.windows(2).enumerate() {
+            if let [Some(a), Some(b)] = pair {
+                let fade = (k + 1) as f32 / (points.len() - 1) as f32;
+                painter.line_segment(
+                    [*a, *b],
+                    egui::Stroke::new(1.5_f32, color.gamma_multiply(fade)),
+                );
+            }
+        }
+    }
+}
+
+const PATH_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 220, 80);
+const DISK_HIT_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 90, 60);
+
+/// The inspected pixel's marker, and a window with its ray's report and
+/// side view.
+fn inspector_window(ctx: &egui::Context, inspector: &mut PixelInspector) {
+    if let Some(inspection) = &inspector.inspection {
+        let screen = ctx.screen_rect();
+        let (x, y) = inspection.pixel;
+        let (width, height) = inspection.size;
+        let marker = screen.min
+            + egui::vec2(
+                (x as f32 + 0.5) / width as f32 * screen.width(),
+                (y as f32 + 0.5) / height as f32 * screen.height(),
+            );
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("inspector"),
+        ));
+        painter.circle_stroke(marker, 6.0, egui::Stroke::new(2.0_f32, PATH_COLOR));
+    }
+
+    let mut open = true;
+    egui::Window::new("Pixel inspector")
+        .open(&mut open)
+        .default_pos(ctx.screen_rect().right_top() + egui::vec2(-380.0, 20.0))
+        .show(ctx, |ui| match &inspector.inspection {
+            Some(inspection) => inspection_report(ui, inspection),
+            None => {
+                ui.label("Click a pixel to trace its ray.");
+            }
+        });
+    inspector.enabled &= open;
+}
+
+fn inspection_report(ui: &mut egui::Ui, inspection: &Inspection) {
+    let trace = &inspection.trace;
+    let (x, y) = inspection.pixel;
+    let fate = match trace.fate {
+        RayFate::Captured(body) => format!("captured by body {}", body),
+        RayFate::Escaped => "escaped".to_string(),
+        RayFate::OutOfSteps { heading_away: true } => {
+            "out of steps, heading away (drawn as escaped)".to_string()
+        }
+        RayFate::OutOfSteps {
+            heading_away: false,
+        } => "out of steps, heading in (drawn as captured)".to_string(),
+        RayFate::Absorbed => "absorbed by the disk".to_string(),
+    };
+    ui.label(format!("Pixel ({}, {}): {}", x, y, fate));
+    ui.label(format!(
+        "{} steps ({} rejected)",
+        trace.steps, trace.rejected_steps
+    ));
+
+    egui::Grid::new("inspector_bodies")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Body");
+            ui.label("Impact parameter");
+            ui.label("Closest approach");
+            ui.label("Orbits");
+            ui.end_row();
+            for (i, pass) in inspection.passes.iter().enumerate() {
+                ui.colored_label(trail_color(i), format!("{}", i));
+                ui.label(format!(
+                    "{:.3} ({:.2} rs)",
+                    pass.impact_parameter,
+                    pass.impact_parameter / pass.rs
+                ));
+                ui.label(format!(
+                    "{:.3} ({:.2} rs)",
+                    pass.closest_approach,
+                    pass.closest_approach / pass.rs
+                ));
+                ui.label(format!("{:.2}", pass.orbits)).on_hover_text(
+                    "Turns around the body within twice its outermost photon-orbit radius",
+                );
+                ui.end_row();
+            }
+        });
+
+    match trace.disk_hit {
+        Some(hit) => {
+            let rs = inspection.bodies[hit.body].rs as f64;
+            ui.label(format!(
+                "Disk of body {} hit at r = {:.3} ({:.2} rs), g = {:.3}",
+                hit.body,
+                hit.radius,
+                hit.radius / rs,
+                hit.redshift
+            ));
+        }
+        None => {
+            ui.label("No disk hit");
+        }
+    }
+    side_view(ui, inspection);
+}
+
+/// The ray's path seen from the camera's right, with the bodies, their
+/// photon spheres and disks, fitted around the camera and the bodies.
+fn side_view(ui: &mut egui::Ui, inspection: &Inspection) {
+    let size = egui::vec2(ui.available_width().max(320.0), 220.0);
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    // Looking along -right, forward runs left to right and up stays up
+    let (forward, up) = (inspection.forward, inspection.up);
+    let flat = |p: DVec3| (p.dot(forward), p.dot(up));
+    let centre = |body: &GpuBody| glam::Vec4::from(body.position).truncate().as_dvec3();
+    let extent = |body: &GpuBody| (body.disk_outer.max(3.0 * body.rs)) as f64;
+
+    let camera = inspection.path[0];
+    let (mut min, mut max) = (flat(camera), flat(camera));
+    for body in &inspection.bodies {
+        let (u, v) = flat(centre(body));
+        let r = extent(body);
+        min = (min.0.min(u - r), min.1.min(v - r));
+        max = (max.0.max(u + r), max.1.max(v + r));
+    }
+    let span = ((max.0 - min.0) / rect.width() as f64).max((max.1 - min.1) / rect.height() as f64);
+    let scale = 0.9 / span.max(1e-9);
+    let mid = (0.5 * (min.0 + max.0), 0.5 * (min.1 + max.1));
+    let to_screen = |p: DVec3| {
+        let (u, v) = flat(p);
+        rect.center() + egui::vec2(((u - mid.0) * scale) as f32, (-(v - mid.1) * scale) as f32)
+    };
+    let thin = egui::Stroke::new(1.0_f32, ui.visuals().weak_text_color());
+
+    for (i, (body, pass)) in inspection.bodies.iter().zip(&inspection.passes).enumerate() {
+        let c = centre(body);
+        let normal = glam::Vec4::from(body.disk_normal).truncate().as_dvec3();
+        let (e1, e2) = normal.any_orthonormal_pair();
+        for radius in [body.disk_inner, body.disk_outer] {
+            let ring = (0..=64)
+                .map(|k| {
+                    let a = k as f64 / 64.0 * std::f64::consts::TAU;
+                    to_screen(c + radius as f64 * (a.cos() * e1 + a.sin() * e2))
+                })
+                .collect();
+            painter.add(egui::Shape::line(
+                ring,
+                egui::Stroke::new(1.0_f32, trail_color(i).gamma_multiply(0.6)),
+            ));
+        }
+        painter.circle_stroke(to_screen(c), (pass.photon_sphere * scale) as f32, thin);
+        painter.circle(
+            to_screen(c),
+            (body.horizon as f64 * scale) as f32,
+            egui::Color32::BLACK,
+            egui::Stroke::new(1.0_f32, trail_color(i)),
+        );
+    }
+
+    let points = inspection.path.iter().map(|p| to_screen(*p)).collect();
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1.5_f32, PATH_COLOR),
+    ));
+    painter.circle_filled(to_screen(camera), 3.0, egui::Color32::WHITE);
+    painter.text(
+        to_screen(camera) + egui::vec2(0.0, -6.0),
+        egui::Align2::CENTER_BOTTOM,
+        "camera",
+        egui::FontId::proportional(11.0),
+        egui::Color32::WHITE,
+    );
+    if let Some(hit) = inspection.trace.disk_hit {
+        painter.circle_filled(to_screen(hit.position), 3.0, DISK_HIT_COLOR);
+    }
+}
+
+const PLACEMENT_COLOR: egui::Color32 = egui::Color32::from_rgb(120, 220, 120);
+
+/// Outline of the body a click would place and, while dragging, its
+/// velocity arrow.
+fn draw_placement(ctx: &egui::Context, placement: &PlacementTool, camera: &OrbitalCamera) {
+    if !placement.enabled {
+        return;
+    }
+    let screen = ctx.screen_rect();
+    let project = |p: glam::Vec3| {
+        camera
+            .project(p, screen.width(), screen.height())
+            .map(|(x, y)| screen.min + egui::vec2(x, y))
+    };
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("placement"),
+    ));
+    let stroke = egui::Stroke::new(2.0_f32, PLACEMENT_COLOR);
+
+    let (position, velocity) = match (placement.preview(), placement.hover()) {
+        (Some(preview), _) => preview,
+        (None, Some(hover)) => (hover, glam::Vec3::ZERO),
+        (None, None) => return,
+    };
+    let Some(centre) = project(position) else {
+        return;
+    };
+    // Horizon outline, sized as seen from the camera
+    if let Some(edge) = project(position + placement.rs * camera.right()) {
+        painter.circle_stroke(centre, centre.distance(edge).max(3.0), stroke);
+    }
+    if velocity != glam::Vec3::ZERO {
+        let tip = position + velocity / placement.velocity_scale;
+        if let Some(tip) = project(tip) {
+            painter.arrow(centre, tip - centre, stroke);
+            painter.text(
+                tip + egui::vec2(8.0, 0.0),
+                egui::Align2::LEFT_CENTER,
+                format!("v = {:.3}c", velocity.length()),
+                egui::FontId::proportional(14.0),
+                PLACEMENT_COLOR,
+            );
+        }
+    }
+}
+
+/// Keep `selected_body` on the same body as mergers remove bodies from the list.
+fn track_mergers(ui_state: &mut UiState, simulation: &Simulation) {
+    // Loading a scene clears the merger log.
+    if ui_state.mergers_seen > simulation.mergers.len() {
+        ui_state.mergers_seen = 0;
+    }
+    for event in &simulation.mergers[ui_state.mergers_seen..] {
+        let (kept, removed) = event.indices;
+        if ui_state.selected_body == removed {
+            ui_state.selected_body = kept;
+        } else if ui_state.selected_body > removed {
+            ui_state.selected_body -= 1;
+        }
+    }
+    ui_state.mergers_seen = simulation.mergers.len();
+}
+
+fn merger_summary(event: &MergerEvent) -> String {
+    format!(
+        "bodies {} + {} -> M = {:.3} ({:.1}% radiated), a/M = {:.2}, kick {:.0} km/s",
+        event.indices.0,
+        event.indices.1,
+        event.remnant_mass,
+        100.0 * event.radiated_fraction(),
+        event.remnant_spin,
+        event.kick as f64 * C_KM_S
+    )
+}
+
+fn strain_panel(ui: &mut egui::Ui, ui_state: &mut UiState, strain: &mut StrainRecorder) {
+    let observer = strain.observer;
+    ui.add(
+        egui::Slider::new(&mut strain.observer.inclination, 0.0..=std::f32::consts::PI)
+            .text("Observer inclination"),
+    );
+    ui.add(
+        egui::Slider::new(&mut strain.observer.azimuth, 0.0..=std::f32::consts::TAU)
+            .text("Observer azimuth"),
+    );
+    // Samples for another line of sight would not form one waveform
+    if strain.observer.inclination != observer.inclination
+        || strain.observer.azimuth != observer.azimuth
+    {
+        strain.clear();
+    }
+
+    strain_plot(ui, strain.recent());
+    ui.horizontal(|ui| {
+        ui.colored_label(PLUS_COLOR, "D·h+");
+        ui.colored_label(CROSS_COLOR, "D·h×");
+        if let Some(last) = strain.recent().back() {
+            ui.label(format!("{:+.2e} / {:+.2e}", last.h_plus, last.h_cross));
+        }
+    });
+
+    let mut recording = strain.is_recording();
+    ui.checkbox(&mut recording, "Record for export")
+        .on_hover_text("Keep every sample from now on; the plot only shows the latest");
+    strain.set_recording(recording);
+    if recording {
+        ui.label(format!("{} samples recorded", strain.recorded().len()));
+    }
+
+    ui.horizontal(|ui| {
+        let export = |extension: &str| -> std::io::Result<std::path::PathBuf> {
+            let path = gravitational_waves::export_path(extension)?;
+            match extension {
+                "csv" => strain.write_csv(&path)?,
+                _ => strain.write_wav(
+                    &path,
+                    gravitational_waves::WAV_DURATION,
+                    gravitational_waves::WAV_PEAK_FREQUENCY,
+                )?,
+            }
+            Ok(path)
+        };
+        for (label, extension) in [("Export CSV", "csv"), ("Export WAV", "wav")] {
+            let button = egui::Button::new(label);
+            if ui.add_enabled(recording, button).clicked() {
+                ui_state.strain_status = Some(match export(extension) {
+                    Ok(path) => format!("Saved {}", path.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                });
+            }
+        }
+        if ui.button("Clear").clicked() {
+            strain.clear();
+        }
+    });
+    if let Some(status) = &ui_state.strain_status {
+        ui.label(status);
+    }
+}
+
+const PLUS_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 170, 60);
+const CROSS_COLOR: egui::Color32 = egui::Color32::from_rgb(90, 180, 255);
+
+/// Both polarisations over the recent samples, scaled to the largest |h|.
+fn strain_plot(ui: &mut egui::Ui, samples: &VecDeque<StrainSample>) {
+    let size = egui::vec2(ui.available_width().max(200.0), 100.0);
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    painter.hline(
+        rect.x_range(),
+        rect.center().y,
+        egui::Stroke::new(1.0_f32, ui.visuals().weak_text_color()),
+    );
+    let (Some(first), Some(last)) = (samples.front(), samples.back()) else {
+        return;
+    };
+
+    let stride = (samples.len() / rect.width() as usize).max(1);
+    let scale = samples
+        .iter()
+        .map(|s| s.h_plus.abs().max(s.h_cross.abs()))
+        .fold(0.0, f64::max)
+        .max(1e-30);
+    let span = (last.time - first.time).max(1e-9);
+    let point = |time: f64, h: f64| {
+        egui::pos2(
+            rect.left() + ((time - first.time) / span) as f32 * rect.width(),
+            rect.center().y - (h / scale) as f32 * 0.45 * rect.height(),
+        )
+    };
+    let line = |pick: fn(&StrainSample) -> f64, color| {
+        let points = samples
+            .iter()
+            .step_by(stride)
+            .map(|s| point(s.time, pick(s)))
+            .collect();
+        egui::Shape::line(points, egui::Stroke::new(1.0_f32, color))
+    };
+    painter.add(line(|s| s.h_plus, PLUS_COLOR));
+    painter.add(line(|s| s.h_cross, CROSS_COLOR));
+}